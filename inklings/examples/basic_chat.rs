@@ -9,16 +9,10 @@ async fn main() {
     let client = Client::new(Box::new(provider));
 
     let messages = vec![
-        Message {
-            role: Role::System,
-            content: "You are a helpful assistant.".to_string(),
-        },
-        Message {
-            role: Role::User,
-            content: "What's the weather like?".to_string(),
-        },
+        Message::new(Role::System, "You are a helpful assistant."),
+        Message::new(Role::User, "What's the weather like?"),
     ];
 
     let response = client.chat(messages).await.unwrap();
-    println!("Response: {}", response);
+    println!("Response: {}", response.content());
 }
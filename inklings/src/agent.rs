@@ -0,0 +1,168 @@
+//! Running the call-model, execute-tools, send-results loop.
+
+use futures::future::{self, BoxFuture};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::client::Client;
+use crate::types::{ChatResponse, Error, Message, Tool, ToolCall, ToolResult, Usage};
+
+type Handler = Arc<dyn Fn(serde_json::Value) -> BoxFuture<'static, Result<String, String>> + Send + Sync>;
+type StepObserver = Arc<dyn Fn(&AgentStep) + Send + Sync>;
+
+/// Answers a conversation with the help of tools implemented in Rust.
+///
+/// The agent sends the conversation to the model together with its tools, runs the handlers of
+/// any tools the model calls, sends the results back and repeats until the model replies without
+/// calling a tool. Handler errors are passed to the model as error results so it can recover.
+pub struct Agent {
+    client: Client,
+    tools: Vec<Tool>,
+    handlers: HashMap<String, Handler>,
+    max_iterations: usize,
+    parallel_tool_calls: bool,
+    on_step: Option<StepObserver>,
+}
+
+impl Agent {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            tools: Vec::new(),
+            handlers: HashMap::new(),
+            max_iterations: 10,
+            parallel_tool_calls: true,
+            on_step: None,
+        }
+    }
+
+    /// Offers `tool` to the model and runs `handler` with the call's arguments when the model
+    /// calls it. A tool registered under an existing name replaces the earlier one.
+    pub fn tool<F, Fut>(mut self, tool: Tool, handler: F) -> Self
+    where
+        F: Fn(serde_json::Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, String>> + Send + 'static,
+    {
+        self.tools.retain(|existing| existing.name != tool.name);
+        let handler: Handler = Arc::new(move |arguments| Box::pin(handler(arguments)));
+        self.handlers.insert(tool.name.clone(), handler);
+        self.tools.push(tool);
+        self
+    }
+
+    /// The most model calls a run may make. Defaults to 10.
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Whether the tool calls of one model turn run concurrently. Defaults to `true`; results
+    /// are sent back in call order either way.
+    pub fn parallel_tool_calls(mut self, parallel_tool_calls: bool) -> Self {
+        self.parallel_tool_calls = parallel_tool_calls;
+        self
+    }
+
+    /// Calls `observer` with every step as it happens, e.g. for logging or progress display.
+    pub fn on_step(mut self, observer: impl Fn(&AgentStep) + Send + Sync + 'static) -> Self {
+        self.on_step = Some(Arc::new(observer));
+        self
+    }
+
+    /// Runs the loop on `messages` until the model gives a final answer.
+    ///
+    /// Fails with [`Error::MaxIterationsExceeded`] if the model is still calling tools after
+    /// `max_iterations` calls, and with the client's error if a model call fails.
+    pub async fn run(&self, mut messages: Vec<Message>) -> Result<AgentRun, Error> {
+        let mut steps = Vec::new();
+        let mut usage = Usage::default();
+
+        for _ in 0..self.max_iterations {
+            let response = self.client.chat_with_tools(messages.clone(), &self.tools).await?;
+            if let Some(response_usage) = response.usage {
+                usage.input_tokens = usage.input_tokens.saturating_add(response_usage.input_tokens);
+                usage.output_tokens = usage.output_tokens.saturating_add(response_usage.output_tokens);
+            }
+            messages.push(response.message.clone());
+            self.record(&mut steps, AgentStep::Response(response.clone()));
+
+            let calls = response.tool_calls().to_vec();
+            if calls.is_empty() {
+                return Ok(AgentRun { response, messages, steps, usage });
+            }
+
+            let executions = if self.parallel_tool_calls {
+                future::join_all(calls.into_iter().map(|call| self.execute(call))).await
+            } else {
+                let mut executions = Vec::with_capacity(calls.len());
+                for call in calls {
+                    executions.push(self.execute(call).await);
+                }
+                executions
+            };
+            let mut results = Vec::with_capacity(executions.len());
+            for (call, result, elapsed) in executions {
+                results.push(result.clone());
+                self.record(&mut steps, AgentStep::ToolCall { call, result, elapsed });
+            }
+            messages.push(Message::tool_results(results));
+        }
+        Err(Error::MaxIterationsExceeded(self.max_iterations))
+    }
+
+    async fn execute(&self, call: ToolCall) -> (ToolCall, ToolResult, Duration) {
+        let started = Instant::now();
+        let result = match self.handlers.get(&call.name) {
+            Some(handler) => match handler(call.arguments.clone()).await {
+                Ok(output) => ToolResult::new(&call.id, output),
+                Err(error) => ToolResult::error(&call.id, error),
+            },
+            None => ToolResult::error(&call.id, format!("Unknown tool: {}", call.name)),
+        };
+        (call, result, started.elapsed())
+    }
+
+    fn record(&self, steps: &mut Vec<AgentStep>, step: AgentStep) {
+        if let Some(observer) = &self.on_step {
+            observer(&step);
+        }
+        steps.push(step);
+    }
+}
+
+/// One thing that happened during an [`Agent`] run.
+#[derive(Debug, Clone)]
+pub enum AgentStep {
+    /// A reply from the model. Any tool calls in it are executed next.
+    Response(ChatResponse),
+    /// A tool call and the result sent back to the model.
+    ToolCall { call: ToolCall, result: ToolResult, elapsed: Duration },
+}
+
+/// The outcome of [`Agent::run`].
+#[derive(Debug, Clone)]
+pub struct AgentRun {
+    /// The model's final reply, which calls no tools.
+    pub response: ChatResponse,
+    /// The whole conversation, from the caller's messages to the final reply.
+    pub messages: Vec<Message>,
+    /// Every model reply and tool call, in order.
+    pub steps: Vec<AgentStep>,
+    /// Tokens used by all model calls together.
+    pub usage: Usage,
+}
+
+impl AgentRun {
+    /// How many times the model was called.
+    pub fn iterations(&self) -> usize {
+        self.steps.iter().filter(|step| matches!(step, AgentStep::Response(_))).count()
+    }
+
+    pub fn tool_calls(&self) -> impl Iterator<Item = (&ToolCall, &ToolResult)> {
+        self.steps.iter().filter_map(|step| match step {
+            AgentStep::ToolCall { call, result, .. } => Some((call, result)),
+            AgentStep::Response(_) => None,
+        })
+    }
+}
@@ -1,26 +1,141 @@
-use futures::Stream;
-use std::pin::Pin;
 use crate::provider::Provider;
-use crate::types::{Message, Error};
+use crate::rate_limit::{self, RateLimiter};
+use crate::retry::RetryPolicy;
+use crate::structured::Schema;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Message, StructuredOutputError, Tool, Error};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 
 pub struct Client {
     provider: Box<dyn Provider + Send + Sync>,
+    options: ChatOptions,
+    retry_policy: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    structured_retries: u32,
 }
 
 impl Client {
     pub fn new(provider: Box<dyn Provider + Send + Sync>) -> Self {
-        Self { provider }
+        Self {
+            provider,
+            options: ChatOptions::default(),
+            retry_policy: RetryPolicy::none(),
+            rate_limiter: None,
+            structured_retries: 0,
+        }
+    }
+
+    /// Retries failed requests according to `retry_policy`. Streams are only retried while
+    /// connecting; once events are flowing, errors are passed on to the caller.
+    pub fn with_retry(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Holds requests back until `rate_limiter`'s budget allows them. Every attempt made by
+    /// [`Client::with_retry`] waits its turn.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// How many times [`Client::chat_structured`] asks the model again, quoting what was wrong,
+    /// when a reply does not match the schema. Defaults to 0.
+    pub fn with_structured_retries(mut self, retries: u32) -> Self {
+        self.structured_retries = retries;
+        self
+    }
+
+    /// Sets the options used by [`Client::chat`], [`Client::stream_chat`] and friends.
+    pub fn with_options(mut self, options: ChatOptions) -> Self {
+        self.options = options;
+        self
     }
 
     pub async fn complete(&self, prompt: &str) -> Result<String, Error> {
-        self.provider.complete(prompt).await
+        self.chat_text(vec![Message::user(prompt)]).await
+    }
+
+    pub async fn chat(&self, messages: Vec<Message>) -> Result<ChatResponse, Error> {
+        self.chat_with_options(messages, &self.options).await
+    }
+
+    /// Like [`Client::chat`], but uses `options` instead of the client's defaults.
+    pub async fn chat_with_options(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        self.retry_policy.run(|| self.send_chat(messages.clone(), options)).await
+    }
+
+    /// Like [`Client::chat`], but returns only the text of the reply.
+    pub async fn chat_text(&self, messages: Vec<Message>) -> Result<String, Error> {
+        Ok(self.chat(messages).await?.content())
+    }
+
+    /// Like [`Client::chat`], but offers `tools` to the model so it can answer with
+    /// [`ToolCall`](crate::types::ToolCall)s to be executed.
+    pub async fn chat_with_tools(&self, messages: Vec<Message>, tools: &[Tool]) -> Result<ChatResponse, Error> {
+        let options = self.options.clone().tools(tools.to_vec());
+        self.chat_with_options(messages, &options).await
+    }
+
+    /// Like [`Client::chat`], but constrains the reply to the JSON Schema of `T` and returns it
+    /// deserialised.
+    ///
+    /// Replies are validated against the schema. An invalid one is answered with the validation
+    /// errors and a request to try again, up to the number of times set with
+    /// [`Client::with_structured_retries`]; after that [`Error::StructuredOutput`] is returned.
+    pub async fn chat_structured<T: DeserializeOwned + JsonSchema>(&self, mut messages: Vec<Message>) -> Result<T, Error> {
+        let schema = Schema::new::<T>();
+        let options = self.options.clone().response_format(schema.format.clone());
+        if self.provider.unsupported_options(&options).contains(&"response_format") {
+            // Without native support, describing the schema is the best we can do.
+            messages.insert(0, Message::system(format!(
+                "Respond only with a JSON object matching this JSON Schema:\n{}",
+                schema.format.schema,
+            )));
+        }
+
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let response = self.chat_with_options(messages.clone(), &options).await?;
+            let (output, value) = schema.extract(&response);
+            let errors = match value.and_then(|value| schema.validate(value)) {
+                Ok(value) => match serde_json::from_value(value) {
+                    Ok(parsed) => return Ok(parsed),
+                    Err(e) => vec![e.to_string()],
+                },
+                Err(errors) => errors,
+            };
+            if attempts > self.structured_retries {
+                return Err(Error::StructuredOutput(StructuredOutputError { output, errors, attempts }));
+            }
+            messages.push(Message::assistant(output));
+            messages.push(Message::user(format!(
+                "That reply does not match the schema:\n- {}\nReply again with corrected JSON only.",
+                errors.join("\n- "),
+            )));
+        }
+    }
+
+    pub async fn stream_chat(&self, messages: Vec<Message>) -> Result<ChatStream, Error> {
+        self.stream_chat_with_options(messages, &self.options).await
+    }
+
+    /// Like [`Client::stream_chat`], but uses `options` instead of the client's defaults.
+    pub async fn stream_chat_with_options(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        self.retry_policy.run(|| self.send_stream(messages.clone(), options)).await
+    }
+
+    /// Names of the fields set in `options` that the provider will ignore.
+    pub fn unsupported_options(&self, options: &ChatOptions) -> Vec<&'static str> {
+        self.provider.unsupported_options(options)
     }
 
-    pub async fn chat(&self, messages: Vec<Message>) -> Result<String, Error> {
-        self.provider.chat(messages).await
+    async fn send_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        rate_limit::chat(self.rate_limiter.as_ref(), &*self.provider, messages, options).await
     }
 
-    pub async fn stream_chat(&self, messages: Vec<Message>) -> Result<Pin<Box<dyn Stream<Item = Result<String, Error>> + Send>>, Error> {
-        self.provider.stream_chat(messages).await
+    async fn send_stream(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        rate_limit::stream_chat(self.rate_limiter.as_ref(), &*self.provider, messages, options).await
     }
 }
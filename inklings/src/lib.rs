@@ -1,5 +1,13 @@
 pub mod provider;
 pub mod types;
 pub mod client;
+pub mod stream;
+pub mod retry;
+pub mod rate_limit;
+pub mod structured;
+pub mod agent;
+#[cfg(feature = "testing")]
+pub mod testing;
 
+pub use agent::Agent;
 pub use client::Client;
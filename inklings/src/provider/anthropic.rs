@@ -1,123 +1,381 @@
 use async_trait::async_trait;
 use serde_json::json;
-use crate::types::{Error, Message, Role};
+use crate::types::{ChatOptions, ChatResponse, ChatStream, ContentPart, Error, FinishReason, MediaSource, Message, Role, StreamEvent, Tool, ToolCall, ToolCallDelta, Usage};
+use crate::rate_limit::RateLimitStatus;
+use crate::stream;
 use super::Provider;
-use futures::{Stream, StreamExt};
+use super::http::{HttpClient, HttpConfig};
+use super::normalize::{normalize, RoleRules, SystemPlacement};
+use futures::StreamExt;
 use eventsource_stream::Eventsource;
-use std::pin::Pin;
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+const DEFAULT_MODEL: &str = "claude-3-5-haiku-20241022";
+const API_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 1000;
+
+/// The Messages API takes the system prompt as a top-level field and expects strictly
+/// alternating turns that open with the user.
+const ROLE_RULES: RoleRules = RoleRules {
+    system: SystemPlacement::TopLevel,
+    alternate: true,
+    leading_user: true,
+    drop_empty: true,
+};
 
 pub struct AnthropicProvider {
     api_key: String,
-    client: reqwest::Client,
+    http: HttpClient,
     model: String,
 }
 
 impl AnthropicProvider {
     pub fn new(api_key: String, model: Option<String>) -> Self {
-        Self {
-            api_key,
-            client: reqwest::Client::new(),
-            model: model.unwrap_or_else(|| "claude-3-5-haiku-20241022".to_string()),
+        let mut builder = Self::builder(api_key);
+        if let Some(model) = model {
+            builder = builder.model(model);
+        }
+        builder.build().expect("default Anthropic configuration is valid")
+    }
+
+    pub fn builder(api_key: impl Into<String>) -> AnthropicProviderBuilder {
+        AnthropicProviderBuilder {
+            api_key: api_key.into(),
+            model: DEFAULT_MODEL.to_string(),
+            http: HttpConfig::new(DEFAULT_BASE_URL),
+        }
+    }
+
+    fn convert_messages(messages: &[Message]) -> Vec<serde_json::Value> {
+        messages.iter().map(|m| json!({
+            // System turns never get here; normalisation hoists them into `system`.
+            "role": match m.role {
+                Role::Assistant => "assistant",
+                Role::System | Role::User => "user",
+            },
+            "content": Self::convert_content(m),
+        })).collect()
+    }
+
+    fn convert_content(message: &Message) -> serde_json::Value {
+        if !message.has_media() && message.tool_calls.is_empty() && message.tool_results.is_empty() {
+            return json!(message.text());
+        }
+
+        // Tool traffic and media need the block form; tool results must come before any text.
+        let mut blocks: Vec<serde_json::Value> = message.tool_results
+            .iter()
+            .map(|result| json!({
+                "type": "tool_result",
+                "tool_use_id": result.tool_call_id,
+                "content": result.content,
+                "is_error": result.is_error,
+            }))
+            .collect();
+        blocks.extend(message.content.iter().map(|part| match part {
+            ContentPart::Text(text) => json!({ "type": "text", "text": text }),
+            ContentPart::Image(source) => json!({ "type": "image", "source": Self::convert_source(source) }),
+            ContentPart::Document { source, name } => {
+                let mut block = json!({ "type": "document", "source": Self::convert_source(source) });
+                if let Some(name) = name {
+                    block["title"] = name.as_str().into();
+                }
+                block
+            }
+        }));
+        blocks.extend(message.tool_calls.iter().map(|call| json!({
+            "type": "tool_use",
+            "id": call.id,
+            "name": call.name,
+            "input": call.arguments,
+        })));
+        blocks.into()
+    }
+
+    fn convert_source(source: &MediaSource) -> serde_json::Value {
+        match source {
+            MediaSource::Base64 { media_type, data } => json!({ "type": "base64", "media_type": media_type, "data": data }),
+            MediaSource::Url { url, .. } => json!({ "type": "url", "url": url }),
+        }
+    }
+
+    fn build_body(&self, messages: Vec<Message>, options: &ChatOptions, stream: bool) -> serde_json::Value {
+        let normalized = normalize(messages, &ROLE_RULES);
+        let mut body = json!({
+            "model": self.model,
+            "messages": Self::convert_messages(&normalized.messages),
+            // The Messages API requires an explicit output limit.
+            "max_tokens": options.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        });
+        if let Some(system) = normalized.system {
+            body["system"] = system.into();
+        }
+        if stream {
+            body["stream"] = true.into();
+        }
+        let mut tools = Self::convert_tools(&options.tools);
+        // The Messages API has no JSON mode; forcing a call to a tool whose input is the schema
+        // gets the same result.
+        if let Some(format) = &options.response_format {
+            tools.push(json!({
+                "name": format.name,
+                "description": "Respond with the requested data by calling this tool.",
+                "input_schema": format.schema,
+            }));
+            body["tool_choice"] = json!({ "type": "tool", "name": format.name });
+        }
+        if !tools.is_empty() {
+            body["tools"] = tools.into();
+        }
+        if let Some(temperature) = options.temperature {
+            body["temperature"] = temperature.into();
+        }
+        if let Some(top_p) = options.top_p {
+            body["top_p"] = top_p.into();
+        }
+        if !options.stop.is_empty() {
+            body["stop_sequences"] = options.stop.clone().into();
+        }
+        body
+    }
+
+    fn convert_tools(tools: &[Tool]) -> Vec<serde_json::Value> {
+        tools
+            .iter()
+            .map(|tool| json!({
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": tool.parameters,
+            }))
+            .collect()
+    }
+
+    fn parse_response(response: serde_json::Value) -> Result<ChatResponse, Error> {
+        let blocks = response["content"]
+            .as_array()
+            .ok_or_else(|| Error::ProviderError("Invalid response format".to_string()))?;
+
+        let mut message = Message::assistant("");
+        for block in blocks {
+            match block["type"].as_str() {
+                Some("text") => message.content.push(ContentPart::text(block["text"].as_str().unwrap_or_default())),
+                Some("tool_use") => message.tool_calls.push(ToolCall {
+                    id: block["id"].as_str().unwrap_or_default().to_string(),
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: block["input"].clone(),
+                }),
+                _ => {}
+            }
+        }
+        Ok(ChatResponse {
+            id: response["id"].as_str().map(str::to_string),
+            model: response["model"].as_str().unwrap_or_default().to_string(),
+            provider: None,
+            message,
+            usage: Self::parse_usage(&response["usage"]),
+            finish_reason: Self::convert_finish_reason(response["stop_reason"].as_str().unwrap_or_default()),
+            rate_limit: None,
+            raw: response,
+        })
+    }
+
+    fn parse_usage(usage: &serde_json::Value) -> Option<Usage> {
+        Some(Usage {
+            input_tokens: usage["input_tokens"].as_u64()? as u32,
+            output_tokens: usage["output_tokens"].as_u64()? as u32,
+        })
+    }
+
+    fn convert_finish_reason(reason: &str) -> FinishReason {
+        match reason {
+            "end_turn" | "stop_sequence" => FinishReason::Stop,
+            "max_tokens" => FinishReason::Length,
+            "tool_use" => FinishReason::ToolCalls,
+            "refusal" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_string()),
         }
     }
+}
+
+/// Configures an [`AnthropicProvider`]; created with [`AnthropicProvider::builder`].
+pub struct AnthropicProviderBuilder {
+    api_key: String,
+    model: String,
+    http: HttpConfig,
+}
+
+impl AnthropicProviderBuilder {
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// The API root, including the version segment. Defaults to `https://api.anthropic.com/v1`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.http.base_url = base_url.into();
+        self
+    }
+
+    super::http::http_setters!(
+        /// Adds a header to every request, e.g. `anthropic-beta`.
+    );
 
+    pub fn build(self) -> Result<AnthropicProvider, Error> {
+        Ok(AnthropicProvider {
+            api_key: self.api_key,
+            http: self.http.build()?,
+            model: self.model,
+        })
+    }
 }
 
 #[async_trait]
 impl Provider for AnthropicProvider {
     async fn complete(&self, prompt: &str) -> Result<String, Error> {
-        let messages = vec![Message {
-            role: Role::User,
-            content: prompt.to_string(),
-        }];
-        self.chat(messages).await
+        let messages = vec![Message::user(prompt)];
+        Ok(self.chat(messages, &ChatOptions::default()).await?.content())
     }
 
-    async fn chat(&self, messages: Vec<Message>) -> Result<String, Error> {
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
         let response = self
-            .client
-            .post("https://api.anthropic.com/v1/messages")
+            .http
+            .post("/messages")
             .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .json(&json!({
-                "model": self.model,
-                "messages": messages.iter().map(|m| json!({
-                    "role": match m.role {
-                        Role::System => "system",
-                        Role::User => "user",
-                        Role::Assistant => "assistant"
-                    },
-                    "content": m.content
-                })).collect::<Vec<_>>(),
-                "max_tokens": 1000,
-            }))
+            .header("anthropic-version", API_VERSION)
+            .json(&self.build_body(messages, options, false))
             .send()
             .await?;
 
         if !response.status().is_success() {
-            return Err(Error::ProviderError(format!(
-                "Anthropic API error: {}",
-                response.text().await?
-            )));
+            return Err(super::error_from_response(response).await);
         }
 
+        let rate_limit = RateLimitStatus::from_headers(response.headers());
         let response: serde_json::Value = response.json().await?;
-        Ok(response["content"][0]["text"]
-            .as_str()
-            .ok_or_else(|| Error::ProviderError("Invalid response format".to_string()))?
-            .to_string())
+        Ok(ChatResponse { rate_limit, ..Self::parse_response(response)? })
     }
 
-    async fn stream_chat(&self, messages: Vec<Message>) -> Result<Pin<Box<dyn Stream<Item = Result<String, Error>> + Send>>, Error> {
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
         let response = self
-            .client
-            .post("https://api.anthropic.com/v1/messages")
+            .http
+            .post("/messages")
             .header("x-api-key", &self.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .json(&json!({
-                "model": self.model,
-                "messages": messages.iter().map(|m| json!({
-                    "role": match m.role {
-                        Role::System => "system",
-                        Role::User => "user",
-                        Role::Assistant => "assistant"
-                    },
-                    "content": m.content
-                })).collect::<Vec<_>>(),
-                "max_tokens": 1000,
-                "stream": true,
-            }))
+            .header("anthropic-version", API_VERSION)
+            .json(&self.build_body(messages, options, true))
             .send()
             .await?;
 
         if !response.status().is_success() {
-            return Err(Error::ProviderError(format!(
-                "Anthropic API error: {}",
-                response.text().await?
-            )));
+            return Err(super::error_from_response(response).await);
         }
 
-        Ok(Box::pin(response
+        let mut parser = StreamParser::default();
+        Ok(stream::guard(response
             .bytes_stream()
             .eventsource()
-            .map(|event| {
-                event
-                    .map_err(|e| Error::ProviderError(e.to_string()))
-                    .and_then(|event| {
-                        let data = event.data;
-                        if data == "[DONE]" {
-                            return Ok("".to_string());
+            .flat_map(move |event| {
+                let events = match event {
+                    Ok(event) => match parser.parse(&event.data) {
+                        Ok(events) => events.into_iter().map(Ok).collect(),
+                        Err(e) => vec![Err(e)],
+                    },
+                    Err(e) => vec![Err(Error::ProviderError(e.to_string()))],
+                };
+                futures::stream::iter(events)
+            })))
+    }
+
+    fn unsupported_options(&self, options: &ChatOptions) -> Vec<&'static str> {
+        let mut unsupported = Vec::new();
+        if options.seed.is_some() {
+            unsupported.push("seed");
+        }
+        if options.presence_penalty.is_some() {
+            unsupported.push("presence_penalty");
+        }
+        if options.frequency_penalty.is_some() {
+            unsupported.push("frequency_penalty");
+        }
+        unsupported
+    }
+}
+
+/// Turns Messages API stream events into [`StreamEvent`]s.
+#[derive(Default)]
+struct StreamParser {
+    /// Reported in `message_start` only, but needed for every later usage update.
+    input_tokens: u32,
+}
+
+impl StreamParser {
+    fn parse(&mut self, data: &str) -> Result<Vec<StreamEvent>, Error> {
+        let json: serde_json::Value = serde_json::from_str(data)
+            .map_err(|e| Error::ProviderError(e.to_string()))?;
+
+        let mut events = Vec::new();
+        match json["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                let message = &json["message"];
+                events.push(StreamEvent::Start {
+                    id: message["id"].as_str().map(str::to_string),
+                    model: message["model"].as_str().unwrap_or_default().to_string(),
+                    provider: None,
+                });
+                if let Some(usage) = AnthropicProvider::parse_usage(&message["usage"]) {
+                    self.input_tokens = usage.input_tokens;
+                    events.push(StreamEvent::Usage(usage));
+                }
+            }
+            "content_block_start" => {
+                let index = json["index"].as_u64().unwrap_or_default() as usize;
+                let block = &json["content_block"];
+                match block["type"].as_str() {
+                    Some("text") => {
+                        if let Some(text) = block["text"].as_str().filter(|text| !text.is_empty()) {
+                            events.push(StreamEvent::TextDelta(text.to_string()));
                         }
-                        let json: serde_json::Value = serde_json::from_str(&data)
-                            .map_err(|e| Error::ProviderError(e.to_string()))?;
-                        
-                        // Handle the delta format from Anthropic's streaming response
-                        Ok(json["delta"]["text"]
-                            .as_str()
-                            .unwrap_or("")
-                            .to_string())
-                    })
-            })
-            .filter(|result| futures::future::ready(!matches!(result, Ok(s) if s.is_empty())))))
+                    }
+                    Some("tool_use") => events.push(StreamEvent::ToolCallDelta(ToolCallDelta {
+                        index,
+                        id: block["id"].as_str().map(str::to_string),
+                        name: block["name"].as_str().map(str::to_string),
+                        arguments: String::new(),
+                    })),
+                    _ => {}
+                }
+            }
+            "content_block_delta" => {
+                let index = json["index"].as_u64().unwrap_or_default() as usize;
+                let delta = &json["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        events.push(StreamEvent::TextDelta(delta["text"].as_str().unwrap_or_default().to_string()));
+                    }
+                    Some("input_json_delta") => events.push(StreamEvent::ToolCallDelta(ToolCallDelta {
+                        index,
+                        id: None,
+                        name: None,
+                        arguments: delta["partial_json"].as_str().unwrap_or_default().to_string(),
+                    })),
+                    _ => {}
+                }
+            }
+            "message_delta" => {
+                if let Some(output_tokens) = json["usage"]["output_tokens"].as_u64() {
+                    events.push(StreamEvent::Usage(Usage {
+                        input_tokens: self.input_tokens,
+                        output_tokens: output_tokens as u32,
+                    }));
+                }
+                if let Some(reason) = json["delta"]["stop_reason"].as_str() {
+                    events.push(StreamEvent::Stop(AnthropicProvider::convert_finish_reason(reason)));
+                }
+            }
+            "message_stop" => events.push(StreamEvent::Done),
+            "error" => return Err(Error::from_error_body(None, &json)),
+            // `ping` and `content_block_stop` carry nothing we need.
+            _ => {}
+        }
+        Ok(events)
     }
 }
@@ -0,0 +1,267 @@
+//! AWS credentials and Signature Version 4 request signing.
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::types::Error;
+
+/// Keys used to sign requests to AWS.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// Present for temporary credentials, e.g. from an assumed role or SSO.
+    pub session_token: Option<String>,
+}
+
+impl AwsCredentials {
+    pub fn new(access_key_id: impl Into<String>, secret_access_key: impl Into<String>) -> Self {
+        Self {
+            access_key_id: access_key_id.into(),
+            secret_access_key: secret_access_key.into(),
+            session_token: None,
+        }
+    }
+
+    pub fn session_token(mut self, session_token: impl Into<String>) -> Self {
+        self.session_token = Some(session_token.into());
+        self
+    }
+
+    /// Reads `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+    pub fn from_env() -> Option<Self> {
+        let credentials = Self::new(
+            std::env::var("AWS_ACCESS_KEY_ID").ok()?,
+            std::env::var("AWS_SECRET_ACCESS_KEY").ok()?,
+        );
+        Some(match std::env::var("AWS_SESSION_TOKEN") {
+            Ok(token) => credentials.session_token(token),
+            Err(_) => credentials,
+        })
+    }
+
+    /// Reads the named profile from the shared credentials file (`~/.aws/credentials`, or
+    /// `AWS_SHARED_CREDENTIALS_FILE`).
+    pub fn from_profile(profile: &str) -> Result<Self, Error> {
+        let path = std::env::var_os("AWS_SHARED_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| aws_dir().map(|dir| dir.join("credentials")))
+            .ok_or_else(|| Error::Configuration("cannot locate the AWS credentials file".to_string()))?;
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| Error::Configuration(format!("cannot read {}: {}", path.display(), e)))?;
+        let section = parse_ini(&contents)
+            .remove(profile)
+            .ok_or_else(|| Error::Configuration(format!("profile {:?} not found in {}", profile, path.display())))?;
+
+        let key = |name: &str| section.get(name).cloned().ok_or_else(|| {
+            Error::Configuration(format!("profile {:?} has no {}", profile, name))
+        });
+        let credentials = Self::new(key("aws_access_key_id")?, key("aws_secret_access_key")?);
+        Ok(match section.get("aws_session_token") {
+            Some(token) => credentials.session_token(token.clone()),
+            None => credentials,
+        })
+    }
+
+    /// Credentials from the environment, falling back to the profile named by `AWS_PROFILE`
+    /// (or `default`).
+    pub fn load() -> Result<Self, Error> {
+        match Self::from_env() {
+            Some(credentials) => Ok(credentials),
+            None => Self::from_profile(&profile_name()),
+        }
+    }
+}
+
+impl fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The region from `AWS_REGION`, `AWS_DEFAULT_REGION` or the current profile in
+/// `~/.aws/config` (or `AWS_CONFIG_FILE`).
+pub(crate) fn default_region() -> Option<String> {
+    if let Ok(region) = std::env::var("AWS_REGION").or_else(|_| std::env::var("AWS_DEFAULT_REGION")) {
+        return Some(region);
+    }
+    let path = std::env::var_os("AWS_CONFIG_FILE")
+        .map(PathBuf::from)
+        .or_else(|| aws_dir().map(|dir| dir.join("config")))?;
+    let mut sections = parse_ini(&std::fs::read_to_string(path).ok()?);
+    let profile = profile_name();
+    // The config file names every profile but the default one `profile <name>`.
+    let section = if profile == "default" { profile } else { format!("profile {}", profile) };
+    sections.remove(&section)?.remove("region")
+}
+
+fn profile_name() -> String {
+    std::env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string())
+}
+
+fn aws_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".aws"))
+}
+
+/// Parses the `[section]` / `key = value` format of the AWS shared config files.
+fn parse_ini(contents: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = None;
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            current = Some(name.trim().to_string());
+            sections.entry(name.trim().to_string()).or_default();
+        } else if let (Some(section), Some((key, value))) = (&current, line.split_once('=')) {
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+/// Signs requests for one service in one region.
+#[derive(Debug, Clone)]
+pub(crate) struct SigV4Signer {
+    pub credentials: AwsCredentials,
+    pub region: String,
+    pub service: String,
+}
+
+impl SigV4Signer {
+    /// The headers to add to a request so AWS accepts it: `x-amz-date`, the optional
+    /// `x-amz-security-token` and `authorization`. `url` must be exactly the URL sent, with
+    /// its path already percent-encoded, and `headers` any other headers to sign.
+    pub fn sign(
+        &self,
+        method: &str,
+        url: &reqwest::Url,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        time: SystemTime,
+    ) -> Vec<(String, String)> {
+        let amz_date = format_amz_date(time);
+        let date = &amz_date[..8];
+
+        let mut host = url.host_str().unwrap_or_default().to_string();
+        if let Some(port) = url.port() {
+            host = format!("{}:{}", host, port);
+        }
+        let mut signed: Vec<(String, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+            .collect();
+        signed.push(("host".to_string(), host));
+        signed.push(("x-amz-date".to_string(), amz_date.clone()));
+        if let Some(token) = &self.credentials.session_token {
+            signed.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        signed.sort();
+
+        let canonical_headers: String = signed.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+        let signed_headers = signed.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method,
+            canonical_uri(url.path()),
+            canonical_query(url),
+            canonical_headers,
+            signed_headers,
+            hex::encode(Sha256::digest(body)),
+        );
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes())),
+        );
+        let mut key = hmac(format!("AWS4{}", self.credentials.secret_access_key).as_bytes(), date.as_bytes());
+        for part in [self.region.as_str(), self.service.as_str(), "aws4_request"] {
+            key = hmac(&key, part.as_bytes());
+        }
+        let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+        let mut result = vec![("x-amz-date".to_string(), amz_date)];
+        if let Some(token) = &self.credentials.session_token {
+            result.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        result.push((
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.credentials.access_key_id, scope, signed_headers, signature,
+            ),
+        ));
+        result
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes every byte except the unreserved characters, as SigV4 requires.
+pub(crate) fn uri_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Services other than S3 sign the path with each segment encoded once more, so an `%3A`
+/// already in the path becomes `%253A`.
+fn canonical_uri(path: &str) -> String {
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    path.split('/').map(uri_encode).collect::<Vec<_>>().join("/")
+}
+
+fn canonical_query(url: &reqwest::Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| (uri_encode(&name), uri_encode(&value)))
+        .collect();
+    pairs.sort();
+    pairs.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&")
+}
+
+/// Formats `time` as `YYYYMMDD'T'HHMMSS'Z'` in UTC.
+fn format_amz_date(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default();
+    let (days, rest) = ((seconds / 86_400) as i64, seconds % 86_400);
+
+    // Converts days since 1970-01-01 to a civil date (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year, month, day, rest / 3600, rest % 3600 / 60, rest % 60,
+    )
+}
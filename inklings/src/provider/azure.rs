@@ -0,0 +1,210 @@
+use async_trait::async_trait;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Error, Message};
+use super::Provider;
+use super::http::{HttpClient, HttpConfig};
+use super::openai::OpenAIProvider;
+use super::openai_compatible::OpenAICompatibleQuirks;
+use std::sync::Arc;
+
+const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+/// Supplies Microsoft Entra ID access tokens for [`AzureOpenAIProvider`].
+///
+/// Tokens must be issued for the `https://cognitiveservices.azure.com/.default` scope. The
+/// credential is asked for a token before every request, so implementations should cache tokens
+/// and refresh them shortly before they expire.
+#[async_trait]
+pub trait TokenCredential: Send + Sync {
+    async fn token(&self) -> Result<String, Error>;
+}
+
+/// A fixed token, for callers that manage refreshing themselves.
+#[async_trait]
+impl TokenCredential for String {
+    async fn token(&self) -> Result<String, Error> {
+        Ok(self.clone())
+    }
+}
+
+#[derive(Clone)]
+enum Credential {
+    ApiKey(String),
+    Token(Arc<dyn TokenCredential>),
+}
+
+/// A provider for a chat model deployed to an Azure OpenAI resource.
+///
+/// Requests go to `/openai/deployments/{deployment}/chat/completions?api-version=...` on the
+/// resource and are otherwise the same as OpenAI's. Azure annotates responses with the results
+/// of its content filters; read them with [`ContentFilterResults::from_response`].
+pub struct AzureOpenAIProvider {
+    credential: Credential,
+    http: HttpClient,
+    deployment: String,
+    api_version: String,
+}
+
+impl AzureOpenAIProvider {
+    /// A provider for `deployment` on the resource `https://{resource}.openai.azure.com`,
+    /// authenticated with `api_key`.
+    pub fn new(resource: &str, deployment: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self::builder(resource, deployment)
+            .api_key(api_key)
+            .build()
+            .expect("default Azure OpenAI configuration is valid")
+    }
+
+    /// Starts configuring a provider for `deployment` on the resource
+    /// `https://{resource}.openai.azure.com`. An API key or token credential must be set.
+    pub fn builder(resource: &str, deployment: impl Into<String>) -> AzureOpenAIProviderBuilder {
+        AzureOpenAIProviderBuilder {
+            credential: None,
+            deployment: deployment.into(),
+            api_version: DEFAULT_API_VERSION.to_string(),
+            http: HttpConfig::new(&format!("https://{}.openai.azure.com/openai", resource)),
+        }
+    }
+
+    async fn post(&self, messages: Vec<Message>, options: &ChatOptions, stream: bool) -> Result<reqwest::RequestBuilder, Error> {
+        // The deployment decides the model; `model` in the body is ignored.
+        let body = OpenAIProvider::build_body(&self.deployment, messages, options, stream, &OpenAICompatibleQuirks::default())?;
+        let request = self
+            .http
+            .post(&format!("/deployments/{}/chat/completions", self.deployment))
+            .query(&[("api-version", &self.api_version)])
+            .json(&body);
+        Ok(match &self.credential {
+            Credential::ApiKey(api_key) => request.header("api-key", api_key),
+            Credential::Token(credential) => request.bearer_auth(credential.token().await?),
+        })
+    }
+}
+
+/// Configures an [`AzureOpenAIProvider`]; created with [`AzureOpenAIProvider::builder`].
+pub struct AzureOpenAIProviderBuilder {
+    credential: Option<Credential>,
+    deployment: String,
+    api_version: String,
+    http: HttpConfig,
+}
+
+impl AzureOpenAIProviderBuilder {
+    /// Authenticates with a resource key, sent as the `api-key` header.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.credential = Some(Credential::ApiKey(api_key.into()));
+        self
+    }
+
+    /// Authenticates with Microsoft Entra ID tokens from `credential`, sent as a bearer token.
+    pub fn credential(mut self, credential: impl TokenCredential + 'static) -> Self {
+        self.credential = Some(Credential::Token(Arc::new(credential)));
+        self
+    }
+
+    pub fn deployment(mut self, deployment: impl Into<String>) -> Self {
+        self.deployment = deployment.into();
+        self
+    }
+
+    /// Defaults to `2024-10-21`.
+    pub fn api_version(mut self, api_version: impl Into<String>) -> Self {
+        self.api_version = api_version.into();
+        self
+    }
+
+    /// The resource endpoint including the `/openai` segment, for custom domains or gateways.
+    /// Defaults to `https://{resource}.openai.azure.com/openai`.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.http.base_url = endpoint.into();
+        self
+    }
+
+    super::http::http_setters!();
+
+    pub fn build(self) -> Result<AzureOpenAIProvider, Error> {
+        let credential = self.credential.ok_or_else(|| {
+            Error::Configuration("Azure OpenAI needs an API key or a token credential".to_string())
+        })?;
+        Ok(AzureOpenAIProvider {
+            credential,
+            http: self.http.build()?,
+            deployment: self.deployment,
+            api_version: self.api_version,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for AzureOpenAIProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let messages = vec![Message::user(prompt)];
+        Ok(self.chat(messages, &ChatOptions::default()).await?.content())
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        OpenAIProvider::send_chat(self.post(messages, options, false).await?).await
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        OpenAIProvider::send_stream(self.post(messages, options, true).await?).await
+    }
+}
+
+/// The verdict of one Azure content filter category.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentFilterResult {
+    /// The category, e.g. `hate`, `self_harm`, `sexual`, `violence`, `jailbreak` or
+    /// `protected_material_text`.
+    pub category: String,
+    pub filtered: bool,
+    /// `safe`, `low`, `medium` or `high` for the severity-graded categories.
+    pub severity: Option<String>,
+    /// Whether the content was detected, for the detection-only categories.
+    pub detected: Option<bool>,
+}
+
+/// The content filter annotations Azure adds to a chat completion.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentFilterResults {
+    /// Results for the prompt, from `prompt_filter_results`.
+    pub prompt: Vec<ContentFilterResult>,
+    /// Results for the completion, from the choice's `content_filter_results`.
+    pub completion: Vec<ContentFilterResult>,
+}
+
+impl ContentFilterResults {
+    /// Reads the annotations from the raw response. Responses without them, such as ones from
+    /// other providers, give empty results.
+    pub fn from_response(response: &ChatResponse) -> Self {
+        let prompt = response.raw["prompt_filter_results"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|result| Self::parse(&result["content_filter_results"]))
+            .collect();
+        Self {
+            prompt,
+            completion: Self::parse(&response.raw["choices"][0]["content_filter_results"]),
+        }
+    }
+
+    /// Whether any filter blocked or altered content.
+    pub fn is_filtered(&self) -> bool {
+        self.prompt.iter().chain(&self.completion).any(|result| result.filtered)
+    }
+
+    fn parse(results: &serde_json::Value) -> Vec<ContentFilterResult> {
+        results
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, result)| result.is_object())
+            .map(|(category, result)| ContentFilterResult {
+                category: category.clone(),
+                filtered: result["filtered"].as_bool().unwrap_or_default(),
+                severity: result["severity"].as_str().map(str::to_string),
+                detected: result["detected"].as_bool(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,489 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::json;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, ContentPart, Error, FinishReason, MediaSource, Message, Role, StreamEvent, Tool, ToolCall, ToolCallDelta, Usage};
+use crate::stream;
+use super::Provider;
+use super::aws::{self, AwsCredentials, SigV4Signer};
+use super::eventstream::{EventMessage, EventStreamDecoder};
+use super::http::{HttpClient, HttpConfig};
+use super::normalize::{normalize, RoleRules, SystemPlacement};
+use std::time::SystemTime;
+
+const DEFAULT_MODEL: &str = "anthropic.claude-3-5-haiku-20241022-v1:0";
+const SERVICE: &str = "bedrock";
+
+/// Converse takes the system prompt separately and expects strictly alternating turns that
+/// open with the user.
+const ROLE_RULES: RoleRules = RoleRules {
+    system: SystemPlacement::TopLevel,
+    alternate: true,
+    leading_user: true,
+    drop_empty: true,
+};
+
+/// A provider for models on Amazon Bedrock, using the Converse and ConverseStream APIs.
+///
+/// Requests are signed with SigV4. Unless given explicitly, credentials come from the
+/// environment or the shared credentials file and the region from `AWS_REGION` or the
+/// profile's config.
+pub struct BedrockProvider {
+    signer: SigV4Signer,
+    http: HttpClient,
+    model: String,
+}
+
+impl BedrockProvider {
+    /// A provider using the default credentials and region.
+    pub fn new(model: Option<String>) -> Result<Self, Error> {
+        let mut builder = Self::builder();
+        if let Some(model) = model {
+            builder = builder.model(model);
+        }
+        builder.build()
+    }
+
+    pub fn builder() -> BedrockProviderBuilder {
+        BedrockProviderBuilder {
+            model: DEFAULT_MODEL.to_string(),
+            region: None,
+            credentials: None,
+            endpoint: None,
+            http: HttpConfig::new(""),
+        }
+    }
+
+    /// Sends `body` to the model's `action` endpoint, signed.
+    async fn send(&self, action: &str, body: &serde_json::Value) -> Result<reqwest::Response, Error> {
+        // Model ids contain `:` and inference profile ARNs `/`, so the id is encoded as one segment.
+        let path = format!("/model/{}/{}", aws::uri_encode(&self.model), action);
+        let url = reqwest::Url::parse(&self.http.url(&path))
+            .map_err(|e| Error::Configuration(format!("invalid Bedrock endpoint: {}", e)))?;
+        let body = serde_json::to_vec(body).map_err(|e| Error::ProviderError(e.to_string()))?;
+        let content_type = ("content-type", "application/json");
+
+        let mut request = self.http.post(&path).header(content_type.0, content_type.1);
+        for (name, value) in self.signer.sign("POST", &url, &[content_type], &body, SystemTime::now()) {
+            request = request.header(name, value);
+        }
+        let response = request.body(body).send().await?;
+
+        if !response.status().is_success() {
+            return Err(Self::error_from_response(response).await);
+        }
+        Ok(response)
+    }
+
+    /// AWS names the error in the `x-amzn-ErrorType` header rather than the body.
+    async fn error_from_response(response: reqwest::Response) -> Error {
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let error_type = headers
+            .get("x-amzn-errortype")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(':').next())
+            .map(str::to_string);
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) => return e.into(),
+        };
+        let body = match (serde_json::from_str::<serde_json::Value>(&body), error_type) {
+            (Ok(mut json), Some(error_type)) if json.is_object() => {
+                json["type"] = error_type.into();
+                json.to_string()
+            }
+            _ => body,
+        };
+        Error::from_response_parts(status, &headers, &body)
+    }
+
+    fn convert_role(role: &Role) -> &'static str {
+        match role {
+            Role::Assistant => "assistant",
+            // System turns have been moved to the `system` field by now.
+            Role::System | Role::User => "user",
+        }
+    }
+
+    fn convert_content(message: &Message) -> Result<Vec<serde_json::Value>, Error> {
+        let mut content = Vec::new();
+        for result in &message.tool_results {
+            let mut block = json!({
+                "toolUseId": result.tool_call_id,
+                "content": [{ "text": result.content }],
+            });
+            if result.is_error {
+                block["status"] = "error".into();
+            }
+            content.push(json!({ "toolResult": block }));
+        }
+        for part in &message.content {
+            content.push(match part {
+                // Converse rejects empty text blocks.
+                ContentPart::Text(text) if text.is_empty() => continue,
+                ContentPart::Text(text) => json!({ "text": text }),
+                ContentPart::Image(source) => {
+                    let (media_type, data) = Self::inline_data(source)?;
+                    let format = media_type.strip_prefix("image/").unwrap_or(media_type);
+                    json!({ "image": { "format": format, "source": { "bytes": data } } })
+                }
+                ContentPart::Document { source, name } => {
+                    let (media_type, data) = Self::inline_data(source)?;
+                    json!({
+                        "document": {
+                            "format": Self::document_format(media_type)?,
+                            "name": name.as_deref().unwrap_or("document"),
+                            "source": { "bytes": data },
+                        }
+                    })
+                }
+            });
+        }
+        for call in &message.tool_calls {
+            content.push(json!({
+                "toolUse": { "toolUseId": call.id, "name": call.name, "input": call.arguments }
+            }));
+        }
+        Ok(content)
+    }
+
+    fn inline_data(source: &MediaSource) -> Result<(&str, &str), Error> {
+        match source {
+            MediaSource::Base64 { media_type, data } => Ok((media_type, data)),
+            MediaSource::Url { .. } => Err(Error::Unsupported("Bedrock only accepts images and documents as inline data".to_string())),
+        }
+    }
+
+    fn document_format(media_type: &str) -> Result<&'static str, Error> {
+        Ok(match media_type {
+            "application/pdf" => "pdf",
+            "text/csv" => "csv",
+            "text/html" => "html",
+            "text/plain" => "txt",
+            "text/markdown" => "md",
+            "application/msword" => "doc",
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => "docx",
+            "application/vnd.ms-excel" => "xls",
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => "xlsx",
+            _ => return Err(Error::Unsupported(format!("Bedrock does not accept {} documents", media_type))),
+        })
+    }
+
+    fn build_body(messages: Vec<Message>, options: &ChatOptions) -> Result<serde_json::Value, Error> {
+        let normalized = normalize(messages, &ROLE_RULES);
+        let mut messages = Vec::with_capacity(normalized.messages.len());
+        for m in &normalized.messages {
+            let content = Self::convert_content(m)?;
+            // An empty trailing assistant turn has nothing to send.
+            if !content.is_empty() {
+                messages.push(json!({ "role": Self::convert_role(&m.role), "content": content }));
+            }
+        }
+
+        let mut body = json!({ "messages": messages });
+        if let Some(system) = normalized.system {
+            body["system"] = json!([{ "text": system }]);
+        }
+        let mut tools = Self::convert_tools(&options.tools);
+        let mut tool_choice = None;
+        // Like Anthropic's API, Converse has no JSON mode, so the schema becomes a forced tool.
+        if let Some(format) = &options.response_format {
+            tools.push(json!({
+                "toolSpec": {
+                    "name": format.name,
+                    "description": "Respond with the requested data by calling this tool.",
+                    "inputSchema": { "json": format.schema },
+                }
+            }));
+            tool_choice = Some(json!({ "tool": { "name": format.name } }));
+        }
+        if !tools.is_empty() {
+            body["toolConfig"] = json!({ "tools": tools });
+        }
+        if let Some(tool_choice) = tool_choice {
+            body["toolConfig"]["toolChoice"] = tool_choice;
+        }
+
+        let mut config = serde_json::Map::new();
+        if let Some(max_tokens) = options.max_tokens {
+            config.insert("maxTokens".to_string(), max_tokens.into());
+        }
+        if let Some(temperature) = options.temperature {
+            config.insert("temperature".to_string(), temperature.into());
+        }
+        if let Some(top_p) = options.top_p {
+            config.insert("topP".to_string(), top_p.into());
+        }
+        if !options.stop.is_empty() {
+            config.insert("stopSequences".to_string(), options.stop.clone().into());
+        }
+        if !config.is_empty() {
+            body["inferenceConfig"] = config.into();
+        }
+        Ok(body)
+    }
+
+    fn convert_tools(tools: &[Tool]) -> Vec<serde_json::Value> {
+        tools
+            .iter()
+            .map(|tool| json!({
+                "toolSpec": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "inputSchema": { "json": tool.parameters },
+                }
+            }))
+            .collect()
+    }
+
+    fn parse_response(&self, response: serde_json::Value, request_id: Option<String>) -> Result<ChatResponse, Error> {
+        let content = response["output"]["message"]["content"]
+            .as_array()
+            .ok_or_else(|| Error::ProviderError("Invalid response format".to_string()))?;
+
+        let mut text = String::new();
+        let mut tool_calls = Vec::new();
+        for block in content {
+            if let Some(block_text) = block["text"].as_str() {
+                text.push_str(block_text);
+            }
+            if let Some(tool_use) = block.get("toolUse") {
+                tool_calls.push(ToolCall {
+                    id: tool_use["toolUseId"].as_str().unwrap_or_default().to_string(),
+                    name: tool_use["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: tool_use["input"].clone(),
+                });
+            }
+        }
+
+        let message = Message {
+            tool_calls,
+            ..Message::assistant(text)
+        };
+        Ok(ChatResponse {
+            // Converse has no response id; the request id identifies the call to AWS support.
+            id: request_id,
+            model: self.model.clone(),
+            provider: None,
+            message,
+            usage: Self::parse_usage(&response["usage"]),
+            finish_reason: Self::convert_finish_reason(response["stopReason"].as_str().unwrap_or_default()),
+            rate_limit: None,
+            raw: response,
+        })
+    }
+
+    fn parse_usage(usage: &serde_json::Value) -> Option<Usage> {
+        Some(Usage {
+            input_tokens: usage["inputTokens"].as_u64()? as u32,
+            output_tokens: usage["outputTokens"].as_u64()? as u32,
+        })
+    }
+
+    fn convert_finish_reason(reason: &str) -> FinishReason {
+        match reason {
+            "end_turn" | "stop_sequence" => FinishReason::Stop,
+            "max_tokens" => FinishReason::Length,
+            "tool_use" => FinishReason::ToolCalls,
+            "guardrail_intervened" | "content_filtered" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+}
+
+/// Configures a [`BedrockProvider`]; created with [`BedrockProvider::builder`].
+pub struct BedrockProviderBuilder {
+    model: String,
+    region: Option<String>,
+    credentials: Option<AwsCredentials>,
+    endpoint: Option<String>,
+    http: HttpConfig,
+}
+
+impl BedrockProviderBuilder {
+    /// A model id such as `anthropic.claude-3-5-sonnet-20241022-v2:0`, or an inference profile
+    /// id or ARN.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn region(mut self, region: impl Into<String>) -> Self {
+        self.region = Some(region.into());
+        self
+    }
+
+    /// Signs requests with `credentials` instead of loading them from the environment or the
+    /// shared credentials file.
+    pub fn credentials(mut self, credentials: AwsCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Loads credentials from a named profile in the shared credentials file.
+    pub fn profile(self, profile: &str) -> Result<Self, Error> {
+        Ok(self.credentials(AwsCredentials::from_profile(profile)?))
+    }
+
+    /// Defaults to `https://bedrock-runtime.{region}.amazonaws.com`.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = Some(endpoint.into());
+        self
+    }
+
+    super::http::http_setters!(
+        /// Adds an unsigned header to every request.
+    );
+
+    pub fn build(mut self) -> Result<BedrockProvider, Error> {
+        let region = self.region
+            .or_else(aws::default_region)
+            .ok_or_else(|| Error::Configuration("no AWS region configured; set AWS_REGION or call region()".to_string()))?;
+        let credentials = match self.credentials {
+            Some(credentials) => credentials,
+            None => AwsCredentials::load()?,
+        };
+        self.http.base_url = self.endpoint
+            .unwrap_or_else(|| format!("https://bedrock-runtime.{}.amazonaws.com", region));
+        Ok(BedrockProvider {
+            signer: SigV4Signer { credentials, region, service: SERVICE.to_string() },
+            http: self.http.build()?,
+            model: self.model,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for BedrockProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let messages = vec![Message::user(prompt)];
+        Ok(self.chat(messages, &ChatOptions::default()).await?.content())
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        let response = self.send("converse", &Self::build_body(messages, options)?).await?;
+        let request_id = response
+            .headers()
+            .get("x-amzn-requestid")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let response: serde_json::Value = response.json().await?;
+        self.parse_response(response, request_id)
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        let response = self.send("converse-stream", &Self::build_body(messages, options)?).await?;
+
+        let mut decoder = EventStreamDecoder::default();
+        let mut parser = StreamParser { model: self.model.clone() };
+        Ok(stream::guard(response.bytes_stream().flat_map(move |chunk| {
+            let mut events = Vec::new();
+            match chunk {
+                Ok(chunk) => {
+                    decoder.push(&chunk);
+                    loop {
+                        match decoder.next_message().and_then(|message| message.map(|m| parser.parse(&m)).transpose()) {
+                            Ok(Some(parsed)) => events.extend(parsed.into_iter().map(Ok)),
+                            Ok(None) => break,
+                            Err(e) => {
+                                events.push(Err(e));
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => events.push(Err(e.into())),
+            }
+            futures::stream::iter(events)
+        })))
+    }
+
+    fn unsupported_options(&self, options: &ChatOptions) -> Vec<&'static str> {
+        let mut unsupported = Vec::new();
+        if options.seed.is_some() {
+            unsupported.push("seed");
+        }
+        if options.presence_penalty.is_some() {
+            unsupported.push("presence_penalty");
+        }
+        if options.frequency_penalty.is_some() {
+            unsupported.push("frequency_penalty");
+        }
+        unsupported
+    }
+}
+
+/// Turns ConverseStream events into [`StreamEvent`]s.
+struct StreamParser {
+    model: String,
+}
+
+impl StreamParser {
+    fn parse(&mut self, message: &EventMessage) -> Result<Vec<StreamEvent>, Error> {
+        let json: serde_json::Value = serde_json::from_slice(&message.payload)
+            .map_err(|e| Error::ProviderError(e.to_string()))?;
+
+        match message.header(":message-type") {
+            Some("event") => {}
+            Some("exception") => {
+                // Exception names are camel-cased in streams (`throttlingException`) but not in
+                // HTTP responses (`ThrottlingException`).
+                let mut error_type = message.header(":exception-type").unwrap_or_default().to_string();
+                if let Some(first) = error_type.get_mut(..1) {
+                    first.make_ascii_uppercase();
+                }
+                let message = json["message"].as_str().or(json["Message"].as_str()).unwrap_or_default();
+                return Err(Error::from_error_body(None, &json!({ "message": message, "type": error_type })));
+            }
+            _ => {
+                let error = json!({
+                    "message": message.header(":error-message").unwrap_or_default(),
+                    "type": message.header(":error-code"),
+                });
+                return Err(Error::from_error_body(None, &error));
+            }
+        }
+
+        let mut events = Vec::new();
+        match message.header(":event-type").unwrap_or_default() {
+            "messageStart" => events.push(StreamEvent::Start { id: None, model: self.model.clone(), provider: None }),
+            "contentBlockStart" => {
+                let tool_use = &json["start"]["toolUse"];
+                if tool_use.is_object() {
+                    events.push(StreamEvent::ToolCallDelta(ToolCallDelta {
+                        index: json["contentBlockIndex"].as_u64().unwrap_or_default() as usize,
+                        id: tool_use["toolUseId"].as_str().map(str::to_string),
+                        name: tool_use["name"].as_str().map(str::to_string),
+                        arguments: String::new(),
+                    }));
+                }
+            }
+            "contentBlockDelta" => {
+                let delta = &json["delta"];
+                if let Some(text) = delta["text"].as_str().filter(|text| !text.is_empty()) {
+                    events.push(StreamEvent::TextDelta(text.to_string()));
+                }
+                if let Some(input) = delta["toolUse"]["input"].as_str() {
+                    events.push(StreamEvent::ToolCallDelta(ToolCallDelta {
+                        index: json["contentBlockIndex"].as_u64().unwrap_or_default() as usize,
+                        id: None,
+                        name: None,
+                        arguments: input.to_string(),
+                    }));
+                }
+            }
+            "messageStop" => events.push(StreamEvent::Stop(BedrockProvider::convert_finish_reason(
+                json["stopReason"].as_str().unwrap_or_default(),
+            ))),
+            // Usage and latency come last, after the stop reason.
+            "metadata" => {
+                if let Some(usage) = BedrockProvider::parse_usage(&json["usage"]) {
+                    events.push(StreamEvent::Usage(usage));
+                }
+                events.push(StreamEvent::Done);
+            }
+            _ => {}
+        }
+        Ok(events)
+    }
+}
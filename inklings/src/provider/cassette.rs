@@ -0,0 +1,512 @@
+//! Recording provider traffic to cassette files and serving it back, so tests written against
+//! live APIs can run offline.
+//!
+//! Both wrappers start a local server and build the wrapped provider with that server as its
+//! base URL, so the cassette holds the provider's real HTTP requests and responses, streams
+//! included, and replaying exercises the same parsing code as a live call.
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures::future::{self, FutureExt};
+use futures::stream::{self, StreamExt};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Error, Message};
+use super::Provider;
+use super::loopback::{Handler, LoopbackRequest, LoopbackResponse, LoopbackServer};
+
+/// What secrets are replaced with in cassettes.
+const REDACTED: &str = "REDACTED";
+
+/// Request headers that carry credentials.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "api-key",
+    "x-goog-api-key",
+    "x-amz-security-token",
+    "cookie",
+];
+
+/// Query parameters that carry credentials.
+const SECRET_PARAMS: &[&str] = &["key", "api_key", "api-key", "access_token"];
+
+/// Headers that describe a single connection and are not forwarded or recorded.
+const CONNECTION_HEADERS: &[&str] = &["host", "content-length", "connection", "transfer-encoding", "accept-encoding", "keep-alive"];
+
+/// Wraps a provider and records every request it makes, and the response, to a cassette file.
+///
+/// The cassette is rewritten after each response, so it is complete whenever the provider is
+/// idle. Credentials in request headers and query parameters are replaced by `REDACTED`
+/// everywhere in the cassette, including response bodies that echo them.
+pub struct RecordingProvider<P> {
+    inner: P,
+    recorder: Arc<Recorder>,
+    _server: LoopbackServer,
+}
+
+impl<P> RecordingProvider<P> {
+    /// Starts a new recording to `cassette`, replacing any earlier one.
+    ///
+    /// `build` is called with the URL the wrapped provider must use as its base URL; requests
+    /// to it are forwarded to `upstream`, the provider's real base URL. Requests signed over
+    /// the host name, such as Bedrock's, are rejected upstream and cannot be recorded this way.
+    pub async fn start<F>(cassette: impl Into<PathBuf>, upstream: &str, build: F) -> Result<Self, Error>
+    where
+        F: FnOnce(&str) -> Result<P, Error>,
+    {
+        let recorder = Arc::new(Recorder {
+            path: cassette.into(),
+            upstream: upstream.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            interactions: Mutex::new(Vec::new()),
+            write_error: Mutex::new(None),
+        });
+        recorder.write()?;
+
+        let handler: Handler = {
+            let recorder = recorder.clone();
+            Arc::new(move |request| recorder.clone().forward(request).boxed())
+        };
+        let server = LoopbackServer::start(handler).await?;
+        let inner = build(server.url())?;
+        Ok(Self { inner, recorder, _server: server })
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Fails with the last cassette write error, if there was one.
+    fn check_written(&self) -> Result<(), Error> {
+        match self.recorder.write_error.lock().unwrap().take() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync> Provider for RecordingProvider<P> {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        self.check_written()?;
+        let result = self.inner.complete(prompt).await;
+        self.check_written()?;
+        result
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        self.check_written()?;
+        let result = self.inner.chat(messages, options).await;
+        self.check_written()?;
+        result
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        self.check_written()?;
+        self.inner.stream_chat(messages, options).await
+    }
+
+    fn unsupported_options(&self, options: &ChatOptions) -> Vec<&'static str> {
+        self.inner.unsupported_options(options)
+    }
+}
+
+struct Recorder {
+    path: PathBuf,
+    upstream: String,
+    client: reqwest::Client,
+    interactions: Mutex<Vec<Value>>,
+    /// Writes happen when a response body ends, so failures are reported by the next call.
+    write_error: Mutex<Option<Error>>,
+}
+
+impl Recorder {
+    async fn forward(self: Arc<Self>, request: LoopbackRequest) -> LoopbackResponse {
+        let url = format!("{}{}", self.upstream, request.path);
+        let method = reqwest::Method::from_bytes(request.method.as_bytes()).unwrap_or(reqwest::Method::POST);
+        let mut upstream = self.client.request(method, &url).body(request.body.clone());
+        for (name, value) in &request.headers {
+            if !CONNECTION_HEADERS.contains(&name.as_str()) {
+                upstream = upstream.header(name, value);
+            }
+        }
+        let response = match upstream.send().await {
+            Ok(response) => response,
+            Err(e) => return LoopbackResponse::json(502, json!({
+                "error": { "message": format!("could not reach {}: {}", url, e), "type": "recording_error" }
+            })),
+        };
+
+        let status = response.status().as_u16();
+        let headers: Vec<(String, String)> = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| !CONNECTION_HEADERS.contains(&name.as_str()))
+            .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).to_string()))
+            .collect();
+
+        // Pass chunks on as they arrive and record the interaction once the body ends. A body
+        // that fails upstream fails for the client too and is not recorded, since replaying it
+        // would turn the failure into a reply that merely ends early.
+        let chunks = Arc::new(Mutex::new(Some(Vec::new())));
+        let recorded = chunks.clone();
+        let response_headers = headers.clone();
+        let save = stream::once(async move {
+            if let Some(chunks) = chunks.lock().unwrap().take() {
+                self.record(&request, status, &response_headers, chunks);
+            }
+        });
+        let body = response
+            .bytes_stream()
+            .map(move |chunk| {
+                let mut recorded = recorded.lock().unwrap();
+                match chunk {
+                    Ok(chunk) => {
+                        if let Some(chunks) = recorded.as_mut() {
+                            chunks.push(chunk.to_vec());
+                        }
+                        Ok(chunk.to_vec())
+                    }
+                    Err(e) => {
+                        *recorded = None;
+                        Err(io::Error::other(e))
+                    }
+                }
+            })
+            .chain(save.filter_map(|()| future::ready(None)));
+
+        LoopbackResponse { status, headers, body: body.boxed() }
+    }
+
+    fn record(&self, request: &LoopbackRequest, status: u16, headers: &[(String, String)], chunks: Vec<Vec<u8>>) {
+        let secrets = secrets(request);
+        let path = redact_path(&request.path);
+        let body = redact(&String::from_utf8_lossy(&request.body), &secrets);
+
+        let mut response = json!({
+            "status": status,
+            "headers": redact_headers(headers, &secrets),
+        });
+        let chunks = rechunk(&chunks, &secrets);
+        match chunks.iter().map(|chunk| std::str::from_utf8(chunk)).collect::<Result<Vec<_>, _>>() {
+            Ok(text) => {
+                response["body"] = text.into_iter().map(|chunk| redact(chunk, &secrets)).collect();
+            }
+            Err(_) => {
+                response["body_base64"] = chunks.iter().map(|chunk| STANDARD.encode(redact_bytes(chunk, &secrets))).collect();
+            }
+        }
+        let interaction = json!({
+            "key": request_key(&request.method, &path, &body),
+            "request": {
+                "method": request.method,
+                "path": path,
+                "headers": redact_headers(&request.headers, &secrets),
+                "body": serde_json::from_str(&body).unwrap_or(Value::String(body)),
+            },
+            "response": response,
+        });
+
+        self.interactions.lock().unwrap().push(interaction);
+        if let Err(error) = self.write() {
+            *self.write_error.lock().unwrap() = Some(error);
+        }
+    }
+
+    fn write(&self) -> Result<(), Error> {
+        let cassette = json!({ "interactions": *self.interactions.lock().unwrap() });
+        let contents = serde_json::to_string_pretty(&cassette).expect("cassettes serialise to JSON");
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| Error::Configuration(format!("could not create {}: {}", parent.display(), e)))?;
+        }
+        std::fs::write(&self.path, contents)
+            .map_err(|e| Error::Configuration(format!("could not write cassette {}: {}", self.path.display(), e)))
+    }
+}
+
+/// Wraps a provider and answers its requests from a cassette written by [`RecordingProvider`],
+/// without network access.
+///
+/// Requests are matched by a hash of the method, path and body, with JSON bodies compared
+/// regardless of key order and credentials ignored, so cassettes can be edited by hand. Several
+/// recordings of the same request are served in order, the last one repeating. A request that
+/// was never recorded gets a 404 whose error message names the cassette.
+pub struct ReplayProvider<P> {
+    inner: P,
+    _server: LoopbackServer,
+}
+
+impl<P> ReplayProvider<P> {
+    /// Loads `cassette` and calls `build` with the URL the wrapped provider must use as its
+    /// base URL. The provider's API key is not checked, so any placeholder will do.
+    pub async fn start<F>(cassette: impl AsRef<Path>, build: F) -> Result<Self, Error>
+    where
+        F: FnOnce(&str) -> Result<P, Error>,
+    {
+        let cassette = cassette.as_ref();
+        let responses = load(cassette)?;
+        let player = Arc::new(Player { name: cassette.display().to_string(), responses: Mutex::new(responses) });
+        let handler: Handler = Arc::new(move |request| {
+            let response = player.respond(&request);
+            future::ready(response).boxed()
+        });
+        let server = LoopbackServer::start(handler).await?;
+        let inner = build(server.url())?;
+        Ok(Self { inner, _server: server })
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync> Provider for ReplayProvider<P> {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        self.inner.complete(prompt).await
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        self.inner.chat(messages, options).await
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        self.inner.stream_chat(messages, options).await
+    }
+
+    fn unsupported_options(&self, options: &ChatOptions) -> Vec<&'static str> {
+        self.inner.unsupported_options(options)
+    }
+}
+
+#[derive(Clone)]
+struct RecordedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    chunks: Vec<Vec<u8>>,
+}
+
+struct Player {
+    name: String,
+    /// Responses by request key, with the index of the next one to serve.
+    responses: Mutex<HashMap<String, (Vec<RecordedResponse>, usize)>>,
+}
+
+impl Player {
+    fn respond(&self, request: &LoopbackRequest) -> LoopbackResponse {
+        let secrets = secrets(request);
+        let path = redact_path(&request.path);
+        let body = redact(&String::from_utf8_lossy(&request.body), &secrets);
+        let key = request_key(&request.method, &path, &body);
+
+        let mut responses = self.responses.lock().unwrap();
+        let Some((recorded, next)) = responses.get_mut(&key) else {
+            return LoopbackResponse::json(404, json!({
+                "error": {
+                    "message": format!("no recording of {} {} (key {}) in cassette {}", request.method, path, key, self.name),
+                    "type": "cassette_miss",
+                }
+            }));
+        };
+        let response = recorded[(*next).min(recorded.len() - 1)].clone();
+        *next += 1;
+        LoopbackResponse::new(response.status, response.headers, response.chunks)
+    }
+}
+
+fn load(path: &Path) -> Result<HashMap<String, (Vec<RecordedResponse>, usize)>, Error> {
+    let invalid = |reason: String| Error::Configuration(format!("invalid cassette {}: {}", path.display(), reason));
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| Error::Configuration(format!("could not read cassette {}: {}", path.display(), e)))?;
+    let cassette: Value = serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+    let interactions = cassette["interactions"]
+        .as_array()
+        .ok_or_else(|| invalid("missing interactions".to_string()))?;
+
+    let mut responses: HashMap<String, (Vec<RecordedResponse>, usize)> = HashMap::new();
+    for interaction in interactions {
+        // Keys are recomputed so that hand-edited requests still match.
+        let request = &interaction["request"];
+        let (Some(method), Some(path)) = (request["method"].as_str(), request["path"].as_str()) else {
+            return Err(invalid("interaction without request method and path".to_string()));
+        };
+        let body = match &request["body"] {
+            Value::String(body) => body.clone(),
+            body => body.to_string(),
+        };
+        let key = request_key(method, path, &body);
+        let response = &interaction["response"];
+        let headers = response["headers"]
+            .as_array()
+            .map(|headers| {
+                headers
+                    .iter()
+                    .filter_map(|header| Some((header[0].as_str()?.to_string(), header[1].as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let chunks = match (&response["body"], &response["body_base64"]) {
+            (Value::Array(chunks), _) => chunks
+                .iter()
+                .map(|chunk| chunk.as_str().map(|chunk| chunk.as_bytes().to_vec()))
+                .collect::<Option<Vec<_>>>(),
+            (_, Value::Array(chunks)) => chunks
+                .iter()
+                .map(|chunk| chunk.as_str().and_then(|chunk| STANDARD.decode(chunk).ok()))
+                .collect::<Option<Vec<_>>>(),
+            _ => None,
+        }
+        .ok_or_else(|| invalid(format!("invalid response body for {}", key)))?;
+        let status = response["status"].as_u64().unwrap_or(200) as u16;
+
+        responses
+            .entry(key)
+            .or_default()
+            .0
+            .push(RecordedResponse { status, headers, chunks });
+    }
+    Ok(responses)
+}
+
+/// Identifies a request independently of credentials, header order and JSON key order.
+fn request_key(method: &str, path: &str, body: &str) -> String {
+    let body = match serde_json::from_str::<Value>(body) {
+        Ok(json) => canonical_json(&json),
+        Err(_) => body.to_string(),
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{} {}\n{}", method, path, body));
+    hex::encode(hasher.finalize())
+}
+
+/// JSON with object keys sorted, since cassettes must not depend on serialisation order.
+fn canonical_json(value: &Value) -> String {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<_> = object.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            let entries: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| format!("{}:{}", Value::String(key.clone()), canonical_json(value)))
+                .collect();
+            format!("{{{}}}", entries.join(","))
+        }
+        Value::Array(items) => format!("[{}]", items.iter().map(canonical_json).collect::<Vec<_>>().join(",")),
+        other => other.to_string(),
+    }
+}
+
+/// The credentials in a request's headers and query string.
+fn secrets(request: &LoopbackRequest) -> Vec<String> {
+    let mut secrets = Vec::new();
+    for (name, value) in &request.headers {
+        if SECRET_HEADERS.contains(&name.as_str()) {
+            // Also catch the bare token of `Bearer <token>`, which responses sometimes echo.
+            if let Some((_, token)) = value.rsplit_once(' ') {
+                secrets.push(token.to_string());
+            }
+            secrets.push(value.clone());
+        }
+    }
+    if let Some((_, query)) = request.path.split_once('?') {
+        for (name, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            if SECRET_PARAMS.contains(&name) {
+                secrets.push(value.to_string());
+            }
+        }
+    }
+    // Replace longer secrets first so a token inside a header value is not half-redacted.
+    secrets.retain(|secret| !secret.is_empty());
+    secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    secrets
+}
+
+fn redact(text: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(text.to_string(), |text, secret| text.replace(secret.as_str(), REDACTED))
+}
+
+fn redact_bytes(bytes: &[u8], secrets: &[String]) -> Vec<u8> {
+    secrets.iter().fold(bytes.to_vec(), |bytes, secret| {
+        let secret = secret.as_bytes();
+        let mut redacted = Vec::with_capacity(bytes.len());
+        let mut rest = bytes.as_slice();
+        while !rest.is_empty() {
+            if rest.starts_with(secret) {
+                redacted.extend_from_slice(REDACTED.as_bytes());
+                rest = &rest[secret.len()..];
+            } else {
+                redacted.push(rest[0]);
+                rest = &rest[1..];
+            }
+        }
+        redacted
+    })
+}
+
+/// Joins the chunks of a response body wherever their boundary falls inside a secret or, in a
+/// UTF-8 body, inside a character, so that every chunk can be redacted and stored on its own.
+fn rechunk(chunks: &[Vec<u8>], secrets: &[String]) -> Vec<Vec<u8>> {
+    let body = chunks.concat();
+    let text = std::str::from_utf8(&body).ok();
+    let secret_spans: Vec<(usize, usize)> = secrets
+        .iter()
+        .map(String::as_bytes)
+        .flat_map(|secret| {
+            body.windows(secret.len())
+                .enumerate()
+                .filter(move |(_, window)| *window == secret)
+                .map(move |(start, _)| (start, start + secret.len()))
+        })
+        .collect();
+
+    let mut rechunked = Vec::new();
+    let (mut start, mut end) = (0, 0);
+    for chunk in chunks {
+        end += chunk.len();
+        let splits_character = text.is_some_and(|text| !text.is_char_boundary(end));
+        let splits_secret = secret_spans.iter().any(|&(from, to)| from < end && end < to);
+        if !splits_character && !splits_secret {
+            rechunked.push(body[start..end].to_vec());
+            start = end;
+        }
+    }
+    rechunked
+}
+
+fn redact_path(path: &str) -> String {
+    let Some((route, query)) = path.split_once('?') else {
+        return path.to_string();
+    };
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if SECRET_PARAMS.contains(&name) => format!("{}={}", name, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect();
+    format!("{}?{}", route, query.join("&"))
+}
+
+/// Headers as `[name, value]` pairs in the order they were sent, since a name such as
+/// `set-cookie` or `vary` may be repeated.
+fn redact_headers(headers: &[(String, String)], secrets: &[String]) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if SECRET_HEADERS.contains(&name.as_str()) || name == "set-cookie" {
+                REDACTED.to_string()
+            } else {
+                redact(value, secrets)
+            };
+            json!([name, value])
+        })
+        .collect()
+}
@@ -0,0 +1,212 @@
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Error, Message};
+use super::Provider;
+
+/// Wraps a provider and injects failures, to check that an application copes with a flaky API.
+///
+/// Every fault is drawn from a random number generator that can be [seeded](Self::seed), so a
+/// failing run can be reproduced exactly as long as the calls happen in the same order.
+///
+/// ```
+/// # use inklings::provider::{ChaosProvider, Latency, MockProvider};
+/// # use inklings::types::{ApiError, Error};
+/// # use std::time::Duration;
+/// # let overloaded = ApiError { status: Some(529), error_type: None, message: "Overloaded".into(), request_id: None, retry_after: None };
+/// let provider = ChaosProvider::new(MockProvider::new("Hello".to_string()))
+///     .seed(42)
+///     .error_rate(0.1, Error::Overloaded(overloaded))
+///     .latency(Latency::Uniform { min: Duration::from_millis(50), max: Duration::from_millis(500) })
+///     .cut_after(3, 0.05);
+/// ```
+pub struct ChaosProvider<P> {
+    inner: P,
+    errors: Vec<(f64, Error)>,
+    latency: Option<Latency>,
+    chunk_latency: Option<Latency>,
+    stall: Option<(usize, f64)>,
+    cut: Option<(usize, f64)>,
+    malformed_rate: f64,
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl<P> ChaosProvider<P> {
+    /// Wraps `inner` without injecting anything yet, with an unseeded generator.
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            errors: Vec::new(),
+            latency: None,
+            chunk_latency: None,
+            stall: None,
+            cut: None,
+            malformed_rate: 0.0,
+            rng: Arc::new(Mutex::new(StdRng::from_entropy())),
+        }
+    }
+
+    /// Makes the injected faults reproducible.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = Arc::new(Mutex::new(StdRng::seed_from_u64(seed)));
+        self
+    }
+
+    /// Fails a `rate` fraction of calls with `error` instead of passing them on. Rates of
+    /// several errors add up, so `error_rate(0.1, a).error_rate(0.2, b)` fails 30% of calls.
+    pub fn error_rate(mut self, rate: f64, error: Error) -> Self {
+        self.errors.push((rate.clamp(0.0, 1.0), error));
+        self
+    }
+
+    /// Delays every call by a duration drawn from `latency`.
+    pub fn latency(mut self, latency: Latency) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Delays every stream event by a duration drawn from `latency`.
+    pub fn chunk_latency(mut self, latency: Latency) -> Self {
+        self.chunk_latency = Some(latency);
+        self
+    }
+
+    /// Makes a `rate` fraction of streams hang forever after `chunks` events, as a stalled
+    /// connection does. Only a timeout on the caller's side ends them.
+    pub fn stall_after(mut self, chunks: usize, rate: f64) -> Self {
+        self.stall = Some((chunks, rate.clamp(0.0, 1.0)));
+        self
+    }
+
+    /// Makes a `rate` fraction of streams end after `chunks` events, as a dropped connection
+    /// does, which callers see as [`Error::StreamTruncated`].
+    pub fn cut_after(mut self, chunks: usize, rate: f64) -> Self {
+        self.cut = Some((chunks, rate.clamp(0.0, 1.0)));
+        self
+    }
+
+    /// Replaces a `rate` fraction of stream events with the error a provider reports for a
+    /// chunk it cannot parse, which ends the stream.
+    pub fn malformed_rate(mut self, rate: f64) -> Self {
+        self.malformed_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn inner(&self) -> &P {
+        &self.inner
+    }
+
+    /// Waits out the latency and decides whether the call fails.
+    async fn before_call(&self) -> Result<(), Error> {
+        let (delay, error) = {
+            let mut rng = self.rng.lock().unwrap();
+            let delay = self.latency.map(|latency| latency.sample(&mut rng));
+            let draw: f64 = rng.gen();
+            let mut threshold = 0.0;
+            let error = self.errors.iter().find_map(|(rate, error)| {
+                threshold += rate;
+                (draw < threshold).then(|| error.duplicate())
+            });
+            (delay, error)
+        };
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<P: Provider + Send + Sync> Provider for ChaosProvider<P> {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        self.before_call().await?;
+        self.inner.complete(prompt).await
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        self.before_call().await?;
+        self.inner.chat(messages, options).await
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        self.before_call().await?;
+        let events = self.inner.stream_chat(messages, options).await?;
+
+        let (stall, cut) = {
+            let mut rng = self.rng.lock().unwrap();
+            let mut strikes = |fault: Option<(usize, f64)>| {
+                fault.filter(|(_, rate)| rng.gen::<f64>() < *rate).map(|(after, _)| after)
+            };
+            (strikes(self.stall), strikes(self.cut))
+        };
+        let rng = self.rng.clone();
+        let chunk_latency = self.chunk_latency;
+        let malformed_rate = self.malformed_rate;
+        let events = events.then(move |event| {
+            let (delay, malformed) = {
+                let mut rng = rng.lock().unwrap();
+                let delay = chunk_latency.map(|latency| latency.sample(&mut rng));
+                (delay, malformed_rate > 0.0 && rng.gen::<f64>() < malformed_rate)
+            };
+            async move {
+                if let Some(delay) = delay {
+                    tokio::time::sleep(delay).await;
+                }
+                match event {
+                    Ok(_) if malformed => Err(malformed_chunk_error()),
+                    event => event,
+                }
+            }
+        });
+
+        // Whichever fault comes first wins; `guard` turns the early end of a cut stream into
+        // `StreamTruncated` and ends the stream after a malformed chunk.
+        let events = match (stall, cut) {
+            (Some(stall), cut) if cut.is_none_or(|cut| stall < cut) => events.take(stall).chain(stream::pending()).boxed(),
+            (_, Some(cut)) => events.take(cut).boxed(),
+            _ => events.boxed(),
+        };
+        Ok(crate::stream::guard(events))
+    }
+
+    fn unsupported_options(&self, options: &ChatOptions) -> Vec<&'static str> {
+        self.inner.unsupported_options(options)
+    }
+}
+
+/// The error providers report for a stream chunk that is not valid JSON.
+fn malformed_chunk_error() -> Error {
+    let error = serde_json::from_str::<serde_json::Value>(r#"{"choices":[{"delta":{"content":"#)
+        .expect_err("the chunk is cut short");
+    Error::ProviderError(error.to_string())
+}
+
+/// A distribution of added delays for [`ChaosProvider`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Latency {
+    Fixed(Duration),
+    /// Any duration between `min` and `max`, all equally likely.
+    Uniform { min: Duration, max: Duration },
+    /// Mostly short delays with a long tail, averaging `mean`, as observed from real APIs.
+    Exponential { mean: Duration },
+}
+
+impl Latency {
+    fn sample(&self, rng: &mut StdRng) -> Duration {
+        match *self {
+            Self::Fixed(delay) => delay,
+            Self::Uniform { min, max } if min < max => rng.gen_range(min..=max),
+            Self::Uniform { min, .. } => min,
+            Self::Exponential { mean } => {
+                let draw: f64 = rng.gen();
+                mean.mul_f64(-(1.0 - draw).ln())
+            }
+        }
+    }
+}
@@ -0,0 +1,409 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::json;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Error, FinishReason, Message, Role, StreamEvent, Tool, ToolCall, ToolCallDelta, ToolResult, Usage};
+use crate::stream;
+use super::Provider;
+use super::http::{HttpClient, HttpConfig};
+use super::ndjson;
+use super::normalize::{answered_call, normalize, RoleRules, SystemPlacement};
+
+const DEFAULT_BASE_URL: &str = "https://api.cohere.com/v1";
+const DEFAULT_MODEL: &str = "command-r";
+
+/// The system prompt becomes the `preamble`; history turns may come in any order.
+const ROLE_RULES: RoleRules = RoleRules {
+    system: SystemPlacement::TopLevel,
+    alternate: false,
+    leading_user: false,
+    drop_empty: true,
+};
+
+/// A provider for Cohere's v1 Chat API.
+///
+/// Cohere splits the conversation into the latest user `message` (or the `tool_results` it
+/// answers with) and the `chat_history` before it, with the system prompt sent as the
+/// `preamble`.
+pub struct CohereProvider {
+    api_key: String,
+    http: HttpClient,
+    model: String,
+}
+
+impl CohereProvider {
+    pub fn new(api_key: String, model: Option<String>) -> Self {
+        let mut builder = Self::builder(api_key);
+        if let Some(model) = model {
+            builder = builder.model(model);
+        }
+        builder.build().expect("default Cohere configuration is valid")
+    }
+
+    pub fn builder(api_key: impl Into<String>) -> CohereProviderBuilder {
+        CohereProviderBuilder {
+            api_key: api_key.into(),
+            model: DEFAULT_MODEL.to_string(),
+            http: HttpConfig::new(DEFAULT_BASE_URL),
+        }
+    }
+
+    /// Cohere identifies a tool result by repeating the call it answers, found among the
+    /// `earlier` messages.
+    fn convert_tool_results(results: &[ToolResult], earlier: &[Message]) -> Vec<serde_json::Value> {
+        results
+            .iter()
+            .map(|result| {
+                let call = answered_call(earlier, &result.tool_call_id);
+                let output = if result.is_error {
+                    json!({ "error": result.content })
+                } else {
+                    json!({ "output": result.content })
+                };
+                json!({
+                    "call": {
+                        "name": call.map(|call| call.name.as_str()).unwrap_or_default(),
+                        "parameters": call.map(|call| call.arguments.clone()).unwrap_or_else(|| json!({})),
+                    },
+                    "outputs": [output],
+                })
+            })
+            .collect()
+    }
+
+    fn convert_history_entry(m: &Message, earlier: &[Message]) -> Vec<serde_json::Value> {
+        let mut entries = Vec::new();
+        if !m.tool_results.is_empty() {
+            entries.push(json!({ "role": "TOOL", "tool_results": Self::convert_tool_results(&m.tool_results, earlier) }));
+        }
+        match m.role {
+            Role::Assistant => {
+                let mut entry = json!({ "role": "CHATBOT", "message": m.text() });
+                if !m.tool_calls.is_empty() {
+                    entry["tool_calls"] = m.tool_calls
+                        .iter()
+                        .map(|call| json!({ "name": call.name, "parameters": call.arguments }))
+                        .collect();
+                }
+                entries.push(entry);
+            }
+            Role::User | Role::System if !m.content.is_empty() => {
+                entries.push(json!({ "role": "USER", "message": m.text() }));
+            }
+            _ => {}
+        }
+        entries
+    }
+
+    fn build_body(&self, messages: Vec<Message>, options: &ChatOptions, stream: bool) -> Result<serde_json::Value, Error> {
+        // The v1 Chat API is text-only.
+        if messages.iter().any(Message::has_media) {
+            return Err(Error::Unsupported("Cohere does not accept images or documents".to_string()));
+        }
+        let normalized = normalize(messages, &ROLE_RULES);
+        let mut messages = normalized.messages;
+        // Cohere cannot continue a partial reply, so an empty assistant turn left to be filled in
+        // is simply dropped.
+        if messages.last().is_some_and(|m| m.role == Role::Assistant && m.content.is_empty() && m.tool_calls.is_empty()) {
+            messages.pop();
+        }
+        let last = match messages.last() {
+            Some(m) if m.role == Role::User => messages.pop(),
+            _ => None,
+        };
+
+        let history: Vec<serde_json::Value> = messages
+            .iter()
+            .enumerate()
+            .flat_map(|(i, m)| Self::convert_history_entry(m, &messages[..i]))
+            .collect();
+
+        let mut body = json!({
+            "model": self.model,
+            "message": last.as_ref().map(Message::text).unwrap_or_default(),
+            "stream": stream,
+        });
+        if !history.is_empty() {
+            body["chat_history"] = history.into();
+        }
+        if let Some(last) = last.filter(|m| !m.tool_results.is_empty()) {
+            body["tool_results"] = Self::convert_tool_results(&last.tool_results, &messages).into();
+        }
+        if let Some(system) = normalized.system {
+            body["preamble"] = system.into();
+        }
+        if !options.tools.is_empty() {
+            body["tools"] = Self::convert_tools(&options.tools).into();
+        }
+        if let Some(format) = &options.response_format {
+            body["response_format"] = json!({ "type": "json_object", "schema": format.schema });
+        }
+        if let Some(temperature) = options.temperature {
+            body["temperature"] = temperature.into();
+        }
+        if let Some(top_p) = options.top_p {
+            body["p"] = top_p.into();
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            body["max_tokens"] = max_tokens.into();
+        }
+        if !options.stop.is_empty() {
+            body["stop_sequences"] = options.stop.clone().into();
+        }
+        if let Some(seed) = options.seed {
+            body["seed"] = seed.into();
+        }
+        if let Some(presence_penalty) = options.presence_penalty {
+            body["presence_penalty"] = presence_penalty.into();
+        }
+        if let Some(frequency_penalty) = options.frequency_penalty {
+            body["frequency_penalty"] = frequency_penalty.into();
+        }
+        Ok(body)
+    }
+
+    /// Cohere describes parameters as a flat map rather than a JSON Schema, so only the
+    /// top-level properties carry over.
+    fn convert_tools(tools: &[Tool]) -> Vec<serde_json::Value> {
+        tools
+            .iter()
+            .map(|tool| {
+                let required: Vec<&str> = tool.parameters["required"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|name| name.as_str())
+                    .collect();
+                let definitions: serde_json::Map<String, serde_json::Value> = tool.parameters["properties"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(name, schema)| {
+                        let mut definition = json!({
+                            "type": Self::convert_type(schema["type"].as_str().unwrap_or("string")),
+                            "required": required.contains(&name.as_str()),
+                        });
+                        if let Some(description) = schema["description"].as_str() {
+                            definition["description"] = description.into();
+                        }
+                        (name.clone(), definition)
+                    })
+                    .collect();
+                json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameter_definitions": definitions,
+                })
+            })
+            .collect()
+    }
+
+    fn convert_type(schema_type: &str) -> &str {
+        match schema_type {
+            "string" => "str",
+            "integer" => "int",
+            "number" => "float",
+            "boolean" => "bool",
+            "array" => "list",
+            "object" => "dict",
+            other => other,
+        }
+    }
+
+    fn parse_response(&self, response: serde_json::Value) -> Result<ChatResponse, Error> {
+        let text = response["text"]
+            .as_str()
+            .ok_or_else(|| Error::ProviderError("Invalid response format".to_string()))?;
+
+        // Cohere does not assign ids to tool calls; number them within the response. The ids
+        // repeat from one response to the next, so results are matched to the latest call.
+        let tool_calls: Vec<ToolCall> = response["tool_calls"]
+            .as_array()
+            .map(|calls| calls.iter().enumerate().map(|(index, call)| ToolCall {
+                id: format!("call_{}", index),
+                name: call["name"].as_str().unwrap_or_default().to_string(),
+                arguments: call["parameters"].clone(),
+            }).collect())
+            .unwrap_or_default();
+
+        let finish_reason = match response["finish_reason"].as_str().unwrap_or("COMPLETE") {
+            "COMPLETE" if !tool_calls.is_empty() => FinishReason::ToolCalls,
+            reason => Self::convert_finish_reason(reason),
+        };
+        let message = Message {
+            tool_calls,
+            ..Message::assistant(text)
+        };
+        Ok(ChatResponse {
+            id: response["response_id"].as_str().map(str::to_string),
+            // The v1 API does not echo the model.
+            model: self.model.clone(),
+            provider: None,
+            message,
+            usage: Self::parse_usage(&response["meta"]),
+            finish_reason,
+            rate_limit: None,
+            raw: response,
+        })
+    }
+
+    fn parse_usage(meta: &serde_json::Value) -> Option<Usage> {
+        let units = &meta["billed_units"];
+        Some(Usage {
+            input_tokens: units["input_tokens"].as_f64()? as u32,
+            output_tokens: units["output_tokens"].as_f64()? as u32,
+        })
+    }
+
+    fn convert_finish_reason(reason: &str) -> FinishReason {
+        match reason {
+            "COMPLETE" | "STOP_SEQUENCE" => FinishReason::Stop,
+            "MAX_TOKENS" => FinishReason::Length,
+            "ERROR_TOXIC" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+}
+
+/// Configures a [`CohereProvider`]; created with [`CohereProvider::builder`].
+pub struct CohereProviderBuilder {
+    api_key: String,
+    model: String,
+    http: HttpConfig,
+}
+
+impl CohereProviderBuilder {
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// The API root, including the version segment. Defaults to `https://api.cohere.com/v1`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.http.base_url = base_url.into();
+        self
+    }
+
+    super::http::http_setters!();
+
+    pub fn build(self) -> Result<CohereProvider, Error> {
+        Ok(CohereProvider {
+            api_key: self.api_key,
+            http: self.http.build()?,
+            model: self.model,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for CohereProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let messages = vec![Message::user(prompt)];
+        Ok(self.chat(messages, &ChatOptions::default()).await?.content())
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        let response = self
+            .http
+            .post("/chat")
+            .bearer_auth(&self.api_key)
+            .json(&self.build_body(messages, options, false)?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
+
+        let response: serde_json::Value = response.json().await?;
+        self.parse_response(response)
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        let response = self
+            .http
+            .post("/chat")
+            .bearer_auth(&self.api_key)
+            .json(&self.build_body(messages, options, true)?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
+
+        let mut parser = StreamParser { model: self.model.clone(), tool_calls: 0 };
+        Ok(stream::guard(ndjson::lines(response.bytes_stream()).flat_map(move |line| {
+            let events = match line.and_then(|line| parser.parse(&line)) {
+                Ok(events) => events.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(events)
+        })))
+    }
+}
+
+/// Turns the v1 chat stream, one JSON event per line, into [`StreamEvent`]s.
+struct StreamParser {
+    model: String,
+    tool_calls: usize,
+}
+
+impl StreamParser {
+    fn parse(&mut self, line: &str) -> Result<Vec<StreamEvent>, Error> {
+        let json: serde_json::Value = serde_json::from_str(line)
+            .map_err(|e| Error::ProviderError(e.to_string()))?;
+
+        let mut events = Vec::new();
+        match json["event_type"].as_str().unwrap_or_default() {
+            "stream-start" => events.push(StreamEvent::Start {
+                id: json["generation_id"].as_str().map(str::to_string),
+                model: self.model.clone(),
+                provider: None,
+            }),
+            "text-generation" => {
+                if let Some(text) = json["text"].as_str().filter(|text| !text.is_empty()) {
+                    events.push(StreamEvent::TextDelta(text.to_string()));
+                }
+            }
+            "tool-calls-chunk" => {
+                // Chunks carry either the model's plan as text or a fragment of a call.
+                if let Some(text) = json["text"].as_str().filter(|text| !text.is_empty()) {
+                    events.push(StreamEvent::TextDelta(text.to_string()));
+                }
+                let delta = &json["tool_call_delta"];
+                if delta.is_object() {
+                    let index = delta["index"].as_u64().unwrap_or_default() as usize;
+                    let name = delta["name"].as_str().map(str::to_string);
+                    self.tool_calls = self.tool_calls.max(index + 1);
+                    events.push(StreamEvent::ToolCallDelta(ToolCallDelta {
+                        index,
+                        id: name.as_ref().map(|_| format!("call_{}", index)),
+                        name,
+                        arguments: delta["parameters"].as_str().unwrap_or_default().to_string(),
+                    }));
+                }
+            }
+            "stream-end" => {
+                // A generation that failed part-way still ends the stream normally.
+                let reason = json["finish_reason"].as_str().unwrap_or("COMPLETE");
+                if matches!(reason, "ERROR" | "ERROR_TOXIC" | "ERROR_LIMIT") {
+                    return Err(Error::ProviderError(format!("Cohere stopped generating with {}", reason)));
+                }
+                let response = &json["response"];
+                if let Some(usage) = CohereProvider::parse_usage(&response["meta"]) {
+                    events.push(StreamEvent::Usage(usage));
+                }
+                let reason = match reason {
+                    "COMPLETE" if self.tool_calls > 0 => FinishReason::ToolCalls,
+                    reason => CohereProvider::convert_finish_reason(reason),
+                };
+                events.push(StreamEvent::Stop(reason));
+                events.push(StreamEvent::Done);
+            }
+            // `tool-calls-generation` repeats the calls already streamed in chunks; search and
+            // citation events have no counterpart.
+            _ => {}
+        }
+        Ok(events)
+    }
+}
@@ -0,0 +1,110 @@
+//! Decoding the binary `application/vnd.amazon.eventstream` framing used by AWS streaming APIs.
+//!
+//! Each message is a 12-byte prelude (total length, headers length and a CRC32 of those eight
+//! bytes), the headers, the payload and a CRC32 of everything before it.
+
+use std::collections::HashMap;
+use crate::types::Error;
+
+const PRELUDE_LENGTH: usize = 12;
+const CHECKSUM_LENGTH: usize = 4;
+
+/// One decoded message. Only string-valued headers are kept; the streaming APIs use no others.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct EventMessage {
+    pub headers: HashMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+impl EventMessage {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+/// Buffers body chunks and splits them into messages.
+#[derive(Debug, Default)]
+pub(crate) struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// The next complete message, or `None` until more bytes arrive.
+    pub fn next_message(&mut self) -> Result<Option<EventMessage>, Error> {
+        if self.buffer.len() < PRELUDE_LENGTH {
+            return Ok(None);
+        }
+        let total_length = read_u32(&self.buffer[0..4]) as usize;
+        let headers_length = read_u32(&self.buffer[4..8]) as usize;
+        if crc32fast::hash(&self.buffer[0..8]) != read_u32(&self.buffer[8..12]) {
+            return Err(Error::ProviderError("event stream prelude checksum mismatch".to_string()));
+        }
+        if total_length < PRELUDE_LENGTH + headers_length + CHECKSUM_LENGTH {
+            return Err(Error::ProviderError("event stream message has an invalid length".to_string()));
+        }
+        if self.buffer.len() < total_length {
+            return Ok(None);
+        }
+
+        let message: Vec<u8> = self.buffer.drain(..total_length).collect();
+        let body_end = total_length - CHECKSUM_LENGTH;
+        if crc32fast::hash(&message[..body_end]) != read_u32(&message[body_end..]) {
+            return Err(Error::ProviderError("event stream message checksum mismatch".to_string()));
+        }
+
+        let headers_end = PRELUDE_LENGTH + headers_length;
+        Ok(Some(EventMessage {
+            headers: parse_headers(&message[PRELUDE_LENGTH..headers_end])?,
+            payload: message[headers_end..body_end].to_vec(),
+        }))
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn malformed() -> Error {
+    Error::ProviderError("malformed event stream header".to_string())
+}
+
+/// Splits `count` bytes off the front of `bytes`.
+fn take(bytes: &mut &[u8], count: usize) -> Result<Vec<u8>, Error> {
+    if bytes.len() < count {
+        return Err(malformed());
+    }
+    let (taken, rest) = bytes.split_at(count);
+    *bytes = rest;
+    Ok(taken.to_vec())
+}
+
+fn parse_headers(mut bytes: &[u8]) -> Result<HashMap<String, String>, Error> {
+    let mut headers = HashMap::new();
+    while !bytes.is_empty() {
+        let name_length = take(&mut bytes, 1)?[0] as usize;
+        let name = String::from_utf8_lossy(&take(&mut bytes, name_length)?).to_string();
+        let value_type = take(&mut bytes, 1)?[0];
+        match value_type {
+            // true and false carry no value.
+            0 | 1 => {}
+            2 => { take(&mut bytes, 1)?; }
+            3 => { take(&mut bytes, 2)?; }
+            4 => { take(&mut bytes, 4)?; }
+            5 | 8 => { take(&mut bytes, 8)?; }
+            9 => { take(&mut bytes, 16)?; }
+            6 | 7 => {
+                let length = take(&mut bytes, 2)?;
+                let value = take(&mut bytes, u16::from_be_bytes([length[0], length[1]]) as usize)?;
+                if value_type == 7 {
+                    headers.insert(name, String::from_utf8_lossy(&value).to_string());
+                }
+            }
+            _ => return Err(malformed()),
+        }
+    }
+    Ok(headers)
+}
@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use std::sync::Arc;
+use crate::rate_limit::{self, RateLimiter};
+use crate::stream::answered_by;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Error, Message};
+use super::Provider;
+
+/// Tries an ordered list of providers in turn, moving on to the next one when a provider fails
+/// with an error worth retrying elsewhere, e.g. to fall over from an overloaded Anthropic to
+/// OpenAI.
+///
+/// Errors that another provider would fail on just the same, such as an invalid request, are
+/// returned straight away, as is the error of the last provider once every one has failed.
+///
+/// ```
+/// # use inklings::provider::{FallbackProvider, MockProvider, Provider};
+/// let provider = FallbackProvider::new(vec![
+///     Box::new(MockProvider::new("primary".to_string())) as Box<dyn Provider + Send + Sync>,
+///     Box::new(MockProvider::new("secondary".to_string())),
+/// ])
+/// .names(["anthropic", "openai"]);
+/// ```
+pub struct FallbackProvider {
+    providers: Vec<Member>,
+    fallback_if: Arc<dyn Fn(&Error) -> bool + Send + Sync>,
+}
+
+struct Member {
+    name: String,
+    provider: Box<dyn Provider + Send + Sync>,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl FallbackProvider {
+    /// Falls back through `providers` in order on errors for which [`Error::is_retryable`] is
+    /// true. Providers are named by their position until [`names`](Self::names) is called.
+    pub fn new(providers: Vec<Box<dyn Provider + Send + Sync>>) -> Self {
+        Self {
+            providers: providers
+                .into_iter()
+                .enumerate()
+                .map(|(i, provider)| Member { name: i.to_string(), provider, rate_limiter: None })
+                .collect(),
+            fallback_if: Arc::new(Error::is_retryable),
+        }
+    }
+
+    /// Names the providers, in order, for [`ChatResponse::provider`].
+    pub fn names<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        for (member, name) in self.providers.iter_mut().zip(names) {
+            member.name = name.into();
+        }
+        self
+    }
+
+    /// Budgets for the providers, in order. A provider waits for its budget when its turn comes.
+    pub fn rate_limiters(mut self, rate_limiters: impl IntoIterator<Item = RateLimiter>) -> Self {
+        for (member, rate_limiter) in self.providers.iter_mut().zip(rate_limiters) {
+            member.rate_limiter = Some(rate_limiter);
+        }
+        self
+    }
+
+    /// Replaces the test deciding which errors move on to the next provider.
+    pub fn fallback_if(mut self, fallback_if: impl Fn(&Error) -> bool + Send + Sync + 'static) -> Self {
+        self.fallback_if = Arc::new(fallback_if);
+        self
+    }
+
+    /// Whether to give up with `error` rather than try the provider after the one at `index`.
+    fn gives_up(&self, index: usize, error: &Error) -> bool {
+        index + 1 == self.providers.len() || !(self.fallback_if)(error)
+    }
+}
+
+fn no_providers() -> Error {
+    Error::Configuration("FallbackProvider has no providers".to_string())
+}
+
+#[async_trait]
+impl Provider for FallbackProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        for (index, member) in self.providers.iter().enumerate() {
+            match rate_limit::complete(member.rate_limiter.as_ref(), &*member.provider, prompt).await {
+                Err(e) if !self.gives_up(index, &e) => continue,
+                result => return result,
+            }
+        }
+        Err(no_providers())
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        for (index, member) in self.providers.iter().enumerate() {
+            match rate_limit::chat(member.rate_limiter.as_ref(), &*member.provider, messages.clone(), options).await {
+                Ok(mut response) => {
+                    response.provider = Some(member.name.clone());
+                    return Ok(response);
+                }
+                Err(e) if !self.gives_up(index, &e) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(no_providers())
+    }
+
+    /// Waits for the first event of each stream before committing to its provider. Once an event
+    /// has been delivered the stream is never switched, so a failure after that reaches the
+    /// caller as it would from the provider itself. The provider is named on
+    /// [`StreamEvent::Start`](crate::types::StreamEvent::Start).
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        for (index, member) in self.providers.iter().enumerate() {
+            let mut events = match rate_limit::stream_chat(member.rate_limiter.as_ref(), &*member.provider, messages.clone(), options).await {
+                Ok(events) => events,
+                Err(e) if !self.gives_up(index, &e) => continue,
+                Err(e) => return Err(e),
+            };
+            match events.next().await {
+                Some(Err(e)) if !self.gives_up(index, &e) => continue,
+                Some(first) => return Ok(answered_by(stream::once(async { first }).chain(events).boxed(), &member.name)),
+                None => return Ok(events),
+            }
+        }
+        Err(no_providers())
+    }
+
+    /// Options that any of the providers would ignore, since any of them may end up answering.
+    fn unsupported_options(&self, options: &ChatOptions) -> Vec<&'static str> {
+        let mut unsupported = Vec::new();
+        for member in &self.providers {
+            for option in member.provider.unsupported_options(options) {
+                if !unsupported.contains(&option) {
+                    unsupported.push(option);
+                }
+            }
+        }
+        unsupported
+    }
+}
@@ -0,0 +1,391 @@
+use async_trait::async_trait;
+use serde_json::json;
+use crate::types::{ApiError, ChatOptions, ChatResponse, ChatStream, ContentPart, Error, FinishReason, MediaSource, Message, Role, StreamEvent, Tool, ToolCall, ToolCallDelta, Usage};
+use crate::stream;
+use super::Provider;
+use super::http::{HttpClient, HttpConfig};
+use super::normalize::{answered_call, normalize, RoleRules, SystemPlacement};
+use futures::StreamExt;
+use eventsource_stream::Eventsource;
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+const DEFAULT_MODEL: &str = "gemini-1.5-flash";
+
+/// Gemini takes the system prompt as `systemInstruction` and expects the conversation to
+/// alternate between `user` and `model`, starting with the user.
+const ROLE_RULES: RoleRules = RoleRules {
+    system: SystemPlacement::TopLevel,
+    alternate: true,
+    leading_user: true,
+    drop_empty: true,
+};
+
+pub struct GeminiProvider {
+    api_key: String,
+    http: HttpClient,
+    model: String,
+}
+
+impl GeminiProvider {
+    pub fn new(api_key: String, model: Option<String>) -> Self {
+        let mut builder = Self::builder(api_key);
+        if let Some(model) = model {
+            builder = builder.model(model);
+        }
+        builder.build().expect("default Gemini configuration is valid")
+    }
+
+    pub fn builder(api_key: impl Into<String>) -> GeminiProviderBuilder {
+        GeminiProviderBuilder {
+            api_key: api_key.into(),
+            model: DEFAULT_MODEL.to_string(),
+            http: HttpConfig::new(DEFAULT_BASE_URL),
+        }
+    }
+
+    fn convert_role(role: &Role) -> &'static str {
+        match role {
+            Role::Assistant => "model",
+            // System turns have been moved to `systemInstruction` by now.
+            Role::System | Role::User => "user",
+        }
+    }
+
+    fn convert_messages(messages: &[Message]) -> Result<Vec<serde_json::Value>, Error> {
+        messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let mut parts = Vec::new();
+                for result in &m.tool_results {
+                    let response = if result.is_error {
+                        json!({ "error": result.content })
+                    } else {
+                        json!({ "content": result.content })
+                    };
+                    parts.push(json!({
+                        "functionResponse": {
+                            // Function responses are matched to calls by name.
+                            "name": answered_call(&messages[..i], &result.tool_call_id).map_or("", |call| call.name.as_str()),
+                            "response": response,
+                        }
+                    }));
+                }
+                for part in &m.content {
+                    parts.push(match part {
+                        ContentPart::Text(text) if text.is_empty() => continue,
+                        ContentPart::Text(text) => json!({ "text": text }),
+                        ContentPart::Image(source) => Self::convert_source(source, None)?,
+                        ContentPart::Document { source, .. } => Self::convert_source(source, Some("application/pdf"))?,
+                    });
+                }
+                for call in &m.tool_calls {
+                    parts.push(json!({ "functionCall": { "name": call.name, "args": call.arguments } }));
+                }
+                Ok(json!({ "role": Self::convert_role(&m.role), "parts": parts }))
+            })
+            .collect()
+    }
+
+    /// Inline data, or a URL which must be a Files API or Cloud Storage URI. Gemini needs the
+    /// media type of a URL too; `default_media_type` stands in when the part does not give one.
+    fn convert_source(source: &MediaSource, default_media_type: Option<&str>) -> Result<serde_json::Value, Error> {
+        match source {
+            MediaSource::Base64 { media_type, data } => Ok(json!({ "inlineData": { "mimeType": media_type, "data": data } })),
+            MediaSource::Url { url, media_type } => {
+                let media_type = media_type.as_deref().or(default_media_type).ok_or_else(|| {
+                    Error::Unsupported("Gemini needs the media type of an image URL; set it with ContentPart::media_type".to_string())
+                })?;
+                Ok(json!({ "fileData": { "mimeType": media_type, "fileUri": url } }))
+            }
+        }
+    }
+
+    fn build_body(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<serde_json::Value, Error> {
+        let normalized = normalize(messages, &ROLE_RULES);
+        let mut body = json!({ "contents": Self::convert_messages(&normalized.messages)? });
+        if let Some(system) = normalized.system {
+            body["systemInstruction"] = json!({ "parts": [{ "text": system }] });
+        }
+        if !options.tools.is_empty() {
+            body["tools"] = json!([{ "functionDeclarations": Self::convert_tools(&options.tools) }]);
+        }
+
+        let mut config = serde_json::Map::new();
+        if let Some(temperature) = options.temperature {
+            config.insert("temperature".to_string(), temperature.into());
+        }
+        if let Some(top_p) = options.top_p {
+            config.insert("topP".to_string(), top_p.into());
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            config.insert("maxOutputTokens".to_string(), max_tokens.into());
+        }
+        if !options.stop.is_empty() {
+            config.insert("stopSequences".to_string(), options.stop.clone().into());
+        }
+        if let Some(seed) = options.seed {
+            config.insert("seed".to_string(), seed.into());
+        }
+        if let Some(presence_penalty) = options.presence_penalty {
+            config.insert("presencePenalty".to_string(), presence_penalty.into());
+        }
+        if let Some(frequency_penalty) = options.frequency_penalty {
+            config.insert("frequencyPenalty".to_string(), frequency_penalty.into());
+        }
+        if let Some(format) = &options.response_format {
+            config.insert("responseMimeType".to_string(), "application/json".into());
+            config.insert("responseJsonSchema".to_string(), format.schema.clone());
+        }
+        if !config.is_empty() {
+            body["generationConfig"] = config.into();
+        }
+        Ok(body)
+    }
+
+    fn convert_tools(tools: &[Tool]) -> Vec<serde_json::Value> {
+        tools
+            .iter()
+            .map(|tool| json!({
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.parameters,
+            }))
+            .collect()
+    }
+
+    fn parse_response(response: serde_json::Value) -> Result<ChatResponse, Error> {
+        Self::check_blocked(&response)?;
+        let candidate = &response["candidates"][0];
+        if !candidate.is_object() {
+            return Err(Error::ProviderError("Invalid response format".to_string()));
+        }
+
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for part in candidate["content"]["parts"].as_array().into_iter().flatten() {
+            if let Some(text) = part["text"].as_str() {
+                content.push_str(text);
+            }
+            if let Some(call) = part.get("functionCall") {
+                tool_calls.push(Self::parse_function_call(call, tool_calls.len()));
+            }
+        }
+
+        let finish_reason = match candidate["finishReason"].as_str().unwrap_or("STOP") {
+            "STOP" if !tool_calls.is_empty() => FinishReason::ToolCalls,
+            reason => Self::convert_finish_reason(reason),
+        };
+        let message = Message {
+            tool_calls,
+            ..Message::assistant(content)
+        };
+        Ok(ChatResponse {
+            id: response["responseId"].as_str().map(str::to_string),
+            model: response["modelVersion"].as_str().unwrap_or_default().to_string(),
+            provider: None,
+            message,
+            usage: Self::parse_usage(&response["usageMetadata"]),
+            finish_reason,
+            rate_limit: None,
+            raw: response,
+        })
+    }
+
+    /// Gemini does not always assign ids to function calls; those without one are numbered in the
+    /// order they appear in the response, so the ids are only unique within it.
+    fn parse_function_call(call: &serde_json::Value, index: usize) -> ToolCall {
+        ToolCall {
+            id: call["id"].as_str().map(str::to_string).unwrap_or_else(|| format!("call_{}", index)),
+            name: call["name"].as_str().unwrap_or_default().to_string(),
+            arguments: call["args"].clone(),
+        }
+    }
+
+    /// Turns a blocked prompt or a candidate stopped by the safety filters into
+    /// [`Error::ContentFiltered`].
+    fn check_blocked(response: &serde_json::Value) -> Result<(), Error> {
+        let reason = response["promptFeedback"]["blockReason"]
+            .as_str()
+            .or_else(|| response["candidates"][0]["finishReason"].as_str().filter(|reason| Self::is_blocked(reason)));
+        match reason {
+            Some(reason) => Err(Error::ContentFiltered(ApiError {
+                status: None,
+                error_type: Some(reason.to_string()),
+                message: format!("response blocked by Gemini ({})", reason),
+                request_id: None,
+                retry_after: None,
+            })),
+            None => Ok(()),
+        }
+    }
+
+    fn is_blocked(reason: &str) -> bool {
+        matches!(reason, "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" | "IMAGE_SAFETY")
+    }
+
+    fn parse_usage(usage: &serde_json::Value) -> Option<Usage> {
+        Some(Usage {
+            input_tokens: usage["promptTokenCount"].as_u64()? as u32,
+            // Omitted when the model produced nothing.
+            output_tokens: usage["candidatesTokenCount"].as_u64().unwrap_or_default() as u32,
+        })
+    }
+
+    fn convert_finish_reason(reason: &str) -> FinishReason {
+        match reason {
+            "STOP" => FinishReason::Stop,
+            "MAX_TOKENS" => FinishReason::Length,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+}
+
+/// Configures a [`GeminiProvider`]; created with [`GeminiProvider::builder`].
+pub struct GeminiProviderBuilder {
+    api_key: String,
+    model: String,
+    http: HttpConfig,
+}
+
+impl GeminiProviderBuilder {
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// The API root, including the version segment. Defaults to
+    /// `https://generativelanguage.googleapis.com/v1beta`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.http.base_url = base_url.into();
+        self
+    }
+
+    super::http::http_setters!();
+
+    pub fn build(self) -> Result<GeminiProvider, Error> {
+        Ok(GeminiProvider {
+            api_key: self.api_key,
+            http: self.http.build()?,
+            model: self.model,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for GeminiProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let messages = vec![Message::user(prompt)];
+        Ok(self.chat(messages, &ChatOptions::default()).await?.content())
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        let response = self
+            .http
+            .post(&format!("/models/{}:generateContent", self.model))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&self.build_body(messages, options)?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
+
+        let response: serde_json::Value = response.json().await?;
+        Self::parse_response(response)
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        let response = self
+            .http
+            .post(&format!("/models/{}:streamGenerateContent", self.model))
+            .query(&[("alt", "sse")])
+            .header("x-goog-api-key", &self.api_key)
+            .json(&self.build_body(messages, options)?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
+
+        let mut parser = StreamParser::default();
+        Ok(stream::guard(response
+            .bytes_stream()
+            .eventsource()
+            .flat_map(move |event| {
+                let events = match event {
+                    Ok(event) => match parser.parse(&event.data) {
+                        Ok(events) => events.into_iter().map(Ok).collect(),
+                        Err(e) => vec![Err(e)],
+                    },
+                    Err(e) => vec![Err(Error::ProviderError(e.to_string()))],
+                };
+                futures::stream::iter(events)
+            })))
+    }
+}
+
+/// Turns `streamGenerateContent` chunks into [`StreamEvent`]s.
+///
+/// Each chunk is a partial `GenerateContentResponse`. There is no end marker; the chunk carrying
+/// the finish reason is the last one.
+#[derive(Default)]
+struct StreamParser {
+    started: bool,
+    tool_calls: usize,
+}
+
+impl StreamParser {
+    fn parse(&mut self, data: &str) -> Result<Vec<StreamEvent>, Error> {
+        let json: serde_json::Value = serde_json::from_str(data)
+            .map_err(|e| Error::ProviderError(e.to_string()))?;
+
+        if json.get("error").is_some() {
+            return Err(Error::from_error_body(None, &json));
+        }
+        GeminiProvider::check_blocked(&json)?;
+
+        let mut events = Vec::new();
+        if !self.started {
+            self.started = true;
+            events.push(StreamEvent::Start {
+                id: json["responseId"].as_str().map(str::to_string),
+                model: json["modelVersion"].as_str().unwrap_or_default().to_string(),
+                provider: None,
+            });
+        }
+
+        let candidate = &json["candidates"][0];
+        for part in candidate["content"]["parts"].as_array().into_iter().flatten() {
+            if let Some(text) = part["text"].as_str().filter(|text| !text.is_empty()) {
+                events.push(StreamEvent::TextDelta(text.to_string()));
+            }
+            // Function calls arrive whole rather than in fragments.
+            if let Some(call) = part.get("functionCall") {
+                let call = GeminiProvider::parse_function_call(call, self.tool_calls);
+                events.push(StreamEvent::ToolCallDelta(ToolCallDelta {
+                    index: self.tool_calls,
+                    id: Some(call.id),
+                    name: Some(call.name),
+                    arguments: call.arguments.to_string(),
+                }));
+                self.tool_calls += 1;
+            }
+        }
+
+        if let Some(reason) = candidate["finishReason"].as_str() {
+            if let Some(usage) = GeminiProvider::parse_usage(&json["usageMetadata"]) {
+                events.push(StreamEvent::Usage(usage));
+            }
+            let reason = match reason {
+                "STOP" if self.tool_calls > 0 => FinishReason::ToolCalls,
+                reason => GeminiProvider::convert_finish_reason(reason),
+            };
+            events.push(StreamEvent::Stop(reason));
+            events.push(StreamEvent::Done);
+        }
+        Ok(events)
+    }
+}
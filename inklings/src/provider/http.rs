@@ -0,0 +1,140 @@
+//! HTTP settings shared by the provider builders.
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::time::Duration;
+use crate::types::Error;
+
+/// Connection settings collected by a provider builder.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpConfig {
+    pub base_url: String,
+    pub headers: Vec<(String, String)>,
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    pub proxy: Option<String>,
+    pub client: Option<reqwest::Client>,
+}
+
+impl HttpConfig {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            headers: Vec::new(),
+            timeout: None,
+            connect_timeout: None,
+            proxy: None,
+            client: None,
+        }
+    }
+
+    /// Validates the settings and builds the client. An injected `reqwest::Client` is used as
+    /// is, so `proxy` and `connect_timeout` must then be configured on it by the caller.
+    pub fn build(self) -> Result<HttpClient, Error> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::Configuration(format!("invalid header name {:?}: {}", name, e)))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|e| Error::Configuration(format!("invalid value for header {}: {}", name, e)))?;
+            headers.insert(name, value);
+        }
+
+        let client = match self.client {
+            Some(client) => client,
+            None => {
+                let mut builder = reqwest::Client::builder();
+                if let Some(connect_timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(connect_timeout);
+                }
+                if let Some(proxy) = &self.proxy {
+                    let proxy = reqwest::Proxy::all(proxy)
+                        .map_err(|e| Error::Configuration(format!("invalid proxy {:?}: {}", proxy, e)))?;
+                    builder = builder.proxy(proxy);
+                }
+                builder.build().map_err(|e| Error::Configuration(e.to_string()))?
+            }
+        };
+
+        Ok(HttpClient {
+            client,
+            base_url: self.base_url.trim_end_matches('/').to_string(),
+            headers,
+            timeout: self.timeout,
+        })
+    }
+}
+
+/// A configured client that resolves paths against the provider's base URL.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpClient {
+    client: reqwest::Client,
+    base_url: String,
+    headers: HeaderMap,
+    timeout: Option<Duration>,
+}
+
+impl HttpClient {
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    pub fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.request(reqwest::Method::POST, path)
+    }
+
+    pub fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self.client
+            .request(method, self.url(path))
+            .headers(self.headers.clone());
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        request
+    }
+}
+
+/// Defines the connection setters every provider builder offers, on a builder that keeps its
+/// [`HttpConfig`] in an `http` field. A doc comment passed in replaces the one on `header`.
+macro_rules! http_setters {
+    () => {
+        $crate::provider::http::http_setters!(
+            /// Adds a header to every request.
+        );
+    };
+    ($(#[$header_doc:meta])*) => {
+        $(#[$header_doc])*
+        pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+            self.http.headers.push((name.into(), value.into()));
+            self
+        }
+
+        /// Limits the total time of each request, including reading a streamed body.
+        pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+            self.http.timeout = Some(timeout);
+            self
+        }
+
+        pub fn connect_timeout(mut self, connect_timeout: std::time::Duration) -> Self {
+            self.http.connect_timeout = Some(connect_timeout);
+            self
+        }
+
+        /// Routes all requests through the proxy at `url`.
+        pub fn proxy(mut self, url: impl Into<String>) -> Self {
+            self.http.proxy = Some(url.into());
+            self
+        }
+
+        /// Sends requests with `client` instead of building one. `proxy` and `connect_timeout` are
+        /// then ignored and should be configured on `client` itself.
+        pub fn http_client(mut self, client: reqwest::Client) -> Self {
+            self.http.client = Some(client);
+            self
+        }
+    };
+}
+pub(crate) use http_setters;
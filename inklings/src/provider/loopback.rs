@@ -0,0 +1,171 @@
+//! A local HTTP/1.1 server that providers can be pointed at, so their traffic can be recorded,
+//! replayed or emulated without touching the provider code.
+
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream, StreamExt};
+use std::io;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use crate::types::Error;
+
+pub(crate) type Handler = Arc<dyn Fn(LoopbackRequest) -> BoxFuture<'static, LoopbackResponse> + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub(crate) struct LoopbackRequest {
+    pub method: String,
+    /// The path and query string.
+    pub path: String,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+pub(crate) struct LoopbackResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// Written to the connection chunk by chunk as the stream yields them. An error aborts the
+    /// response, which the client sees as a failed body rather than one that ended early.
+    pub body: BoxStream<'static, io::Result<Vec<u8>>>,
+}
+
+impl LoopbackResponse {
+    pub fn new(status: u16, headers: Vec<(String, String)>, chunks: Vec<Vec<u8>>) -> Self {
+        Self { status, headers, body: stream::iter(chunks.into_iter().map(Ok)).boxed() }
+    }
+
+    pub fn json(status: u16, body: serde_json::Value) -> Self {
+        let headers = vec![("content-type".to_string(), "application/json".to_string())];
+        Self::new(status, headers, vec![body.to_string().into_bytes()])
+    }
+}
+
+/// Serves one request per connection on an ephemeral port until dropped.
+pub(crate) struct LoopbackServer {
+    url: String,
+    task: JoinHandle<()>,
+}
+
+impl LoopbackServer {
+    pub async fn start(handler: Handler) -> Result<Self, Error> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| Error::Configuration(format!("could not start local server: {}", e)))?;
+        let address = listener
+            .local_addr()
+            .map_err(|e| Error::Configuration(format!("could not start local server: {}", e)))?;
+        let task = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                tokio::spawn(serve(socket, handler.clone()));
+            }
+        });
+        Ok(Self { url: format!("http://{}", address), task })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for LoopbackServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(mut socket: TcpStream, handler: Handler) {
+    let Some(request) = read_request(&mut socket).await else {
+        return;
+    };
+    let response = handler(request).await;
+
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nconnection: close\r\ntransfer-encoding: chunked\r\n",
+        response.status,
+        reason(response.status)
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    if socket.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+
+    // Chunked encoding passes chunks on as they are written, and lets the client tell a complete
+    // body from one cut off by dropping the connection before the final empty chunk.
+    let mut body = response.body;
+    while let Some(chunk) = body.next().await {
+        let Ok(chunk) = chunk else {
+            return;
+        };
+        if chunk.is_empty() {
+            continue;
+        }
+        let mut frame = format!("{:x}\r\n", chunk.len()).into_bytes();
+        frame.extend_from_slice(&chunk);
+        frame.extend_from_slice(b"\r\n");
+        if socket.write_all(&frame).await.is_err() || socket.flush().await.is_err() {
+            return;
+        }
+    }
+    let _ = socket.write_all(b"0\r\n\r\n").await;
+    let _ = socket.shutdown().await;
+}
+
+async fn read_request(socket: &mut TcpStream) -> Option<LoopbackRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let header_end = loop {
+        let read = socket.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    while buffer.len() < header_end + length {
+        let read = socket.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            break;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let body = buffer.split_off(header_end);
+
+    Some(LoopbackRequest { method, path, headers, body })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Status",
+    }
+}
@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Error, Message};
+use super::Provider;
+use super::http::{HttpClient, HttpConfig};
+use super::openai::OpenAIProvider;
+use super::openai_compatible::OpenAICompatibleQuirks;
+
+const DEFAULT_BASE_URL: &str = "https://api.mistral.ai/v1";
+const DEFAULT_MODEL: &str = "mistral-small-latest";
+
+/// La Plateforme follows the Chat Completions format but rejects `stream_options` (streams
+/// report usage unprompted) and calls the seed `random_seed`.
+const QUIRKS: OpenAICompatibleQuirks = OpenAICompatibleQuirks {
+    stream_options: false,
+    seed: false,
+    penalties: true,
+    tools: true,
+    images: true,
+    // Mistral takes documents as `document_url` parts rather than `file` parts.
+    documents: false,
+    response_format: true,
+};
+
+pub struct MistralProvider {
+    api_key: String,
+    http: HttpClient,
+    model: String,
+}
+
+impl MistralProvider {
+    pub fn new(api_key: String, model: Option<String>) -> Self {
+        let mut builder = Self::builder(api_key);
+        if let Some(model) = model {
+            builder = builder.model(model);
+        }
+        builder.build().expect("default Mistral configuration is valid")
+    }
+
+    pub fn builder(api_key: impl Into<String>) -> MistralProviderBuilder {
+        MistralProviderBuilder {
+            api_key: api_key.into(),
+            model: DEFAULT_MODEL.to_string(),
+            http: HttpConfig::new(DEFAULT_BASE_URL),
+        }
+    }
+
+    /// Lists the ids of the models available to this API key.
+    pub async fn list_models(&self) -> Result<Vec<String>, Error> {
+        OpenAIProvider::fetch_models(self.http.get("/models").bearer_auth(&self.api_key)).await
+    }
+
+    fn build_body(&self, messages: Vec<Message>, options: &ChatOptions, stream: bool) -> Result<serde_json::Value, Error> {
+        let mut body = OpenAIProvider::build_body(&self.model, messages, options, stream, &QUIRKS)?;
+        if let Some(seed) = options.seed {
+            body["random_seed"] = seed.into();
+        }
+        Ok(body)
+    }
+}
+
+/// Configures a [`MistralProvider`]; created with [`MistralProvider::builder`].
+pub struct MistralProviderBuilder {
+    api_key: String,
+    model: String,
+    http: HttpConfig,
+}
+
+impl MistralProviderBuilder {
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// The API root, including the version segment. Defaults to `https://api.mistral.ai/v1`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.http.base_url = base_url.into();
+        self
+    }
+
+    super::http::http_setters!();
+
+    pub fn build(self) -> Result<MistralProvider, Error> {
+        Ok(MistralProvider {
+            api_key: self.api_key,
+            http: self.http.build()?,
+            model: self.model,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for MistralProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let messages = vec![Message::user(prompt)];
+        Ok(self.chat(messages, &ChatOptions::default()).await?.content())
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        let body = self.build_body(messages, options, false)?;
+        OpenAIProvider::send_chat(self.http.post("/chat/completions").bearer_auth(&self.api_key).json(&body)).await
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        let body = self.build_body(messages, options, true)?;
+        OpenAIProvider::send_stream(self.http.post("/chat/completions").bearer_auth(&self.api_key).json(&body)).await
+    }
+}
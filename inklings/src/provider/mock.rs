@@ -1,69 +1,349 @@
 use async_trait::async_trait;
-use futures::Stream;
-use tokio::time::Duration;
-use tokio_stream::StreamExt;
-use std::pin::Pin;
-use crate::types::{Error, Message};
+use futures::StreamExt;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::stream;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Error, FinishReason, Message, Role, StreamEvent, ToolCallDelta};
 use super::Provider;
 
+type Matcher = Arc<dyn Fn(&MockRequest) -> bool + Send + Sync>;
+
+/// A provider that answers from a script instead of a model, for tests.
+///
+/// Each call is answered by the first matching rule added with [`MockProviderBuilder::when`],
+/// otherwise by the next reply in the queue, otherwise by the fallback reply. Every request is
+/// recorded and can be inspected with [`MockProvider::requests`].
 pub struct MockProvider {
-    response: String,
-    error: Option<String>,
-    stream_responses: Option<Vec<String>>,
+    rules: Vec<(Matcher, MockReply)>,
+    queue: Mutex<VecDeque<MockReply>>,
+    fallback: MockReply,
+    latency: Duration,
+    chunk_delay: Duration,
+    requests: Mutex<Vec<MockRequest>>,
 }
 
 impl MockProvider {
+    /// Answers every call with `response`.
     pub fn new(response: String) -> Self {
-        Self { 
-            response,
-            error: None,
-            stream_responses: None,
-        }
+        Self::builder().fallback(MockReply::text(response)).build()
     }
 
+    /// Answers every call with `responses`, streamed one chunk per event.
     pub fn with_stream_response(responses: Vec<String>) -> Self {
-        Self {
-            response: responses.join(""),
-            stream_responses: Some(responses),
-            error: None,
-        }
+        Self::builder().fallback(MockReply::chunks(responses)).build()
     }
 
+    /// Fails every call with [`Error::ProviderError`].
     pub fn with_error(error: String) -> Self {
-        Self {
-            response: String::new(),
-            error: Some(error),
-            stream_responses: None,
+        Self::builder().fallback(MockReply::error(Error::ProviderError(error))).build()
+    }
+
+    /// Fails the first calls with `failures`, in order, then answers with `response`.
+    pub fn with_failures(failures: Vec<Error>, response: String) -> Self {
+        Self::builder()
+            .replies(failures.into_iter().map(MockReply::error))
+            .fallback(MockReply::text(response))
+            .build()
+    }
+
+    /// Answers calls with `replies`, in order, then with an empty assistant turn. Replies
+    /// with tool calls finish with [`FinishReason::ToolCalls`], e.g. to drive an
+    /// [`Agent`](crate::agent::Agent) through several steps.
+    pub fn with_replies(replies: Vec<Message>) -> Self {
+        Self::builder().replies(replies.into_iter().map(MockReply::message)).build()
+    }
+
+    pub fn builder() -> MockProviderBuilder {
+        MockProviderBuilder {
+            rules: Vec::new(),
+            queue: VecDeque::new(),
+            fallback: MockReply::text(""),
+            latency: Duration::ZERO,
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    /// Adds `reply` to the end of the queue.
+    pub fn push_reply(&self, reply: MockReply) {
+        self.queue.lock().unwrap().push_back(reply);
+    }
+
+    /// How many requests this provider has received.
+    pub fn calls(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    pub fn last_request(&self) -> Option<MockRequest> {
+        self.requests.lock().unwrap().last().cloned()
+    }
+
+    /// Records the request, waits out the latency and picks the reply.
+    async fn respond(&self, request: MockRequest) -> MockReply {
+        let reply = self.rules
+            .iter()
+            .find(|(matches, _)| matches(&request))
+            .map(|(_, reply)| reply.clone())
+            .or_else(|| self.queue.lock().unwrap().pop_front())
+            .unwrap_or_else(|| self.fallback.clone());
+        self.requests.lock().unwrap().push(request);
+        if !self.latency.is_zero() {
+            tokio::time::sleep(self.latency).await;
         }
+        reply
     }
 }
 
-#[async_trait]
-impl Provider for MockProvider {
-    async fn complete(&self, _prompt: &str) -> Result<String, Error> {
-        if let Some(error) = &self.error {
-            return Err(Error::ProviderError(error.clone()));
+/// Configures a [`MockProvider`]; created with [`MockProvider::builder`].
+pub struct MockProviderBuilder {
+    rules: Vec<(Matcher, MockReply)>,
+    queue: VecDeque<MockReply>,
+    fallback: MockReply,
+    latency: Duration,
+    chunk_delay: Duration,
+}
+
+impl MockProviderBuilder {
+    /// Adds `reply` to the end of the queue.
+    pub fn reply(mut self, reply: MockReply) -> Self {
+        self.queue.push_back(reply);
+        self
+    }
+
+    pub fn replies(mut self, replies: impl IntoIterator<Item = MockReply>) -> Self {
+        self.queue.extend(replies);
+        self
+    }
+
+    /// Answers every request for which `matches` returns true with `reply`. Rules are checked
+    /// in the order they were added and take precedence over the queue.
+    pub fn when(mut self, matches: impl Fn(&MockRequest) -> bool + Send + Sync + 'static, reply: MockReply) -> Self {
+        self.rules.push((Arc::new(matches), reply));
+        self
+    }
+
+    /// Answers with `reply` whenever the last user message contains `text`.
+    pub fn when_last_user_contains(self, text: impl Into<String>, reply: MockReply) -> Self {
+        let text = text.into();
+        self.when(move |request| request.last_user_text().is_some_and(|last| last.contains(&text)), reply)
+    }
+
+    /// The reply used once the queue is empty and no rule matches. Defaults to an empty text
+    /// reply.
+    pub fn fallback(mut self, reply: MockReply) -> Self {
+        self.fallback = reply;
+        self
+    }
+
+    /// Waits this long before answering each call. Defaults to none.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Waits this long between stream events. Defaults to none.
+    pub fn chunk_delay(mut self, chunk_delay: Duration) -> Self {
+        self.chunk_delay = chunk_delay;
+        self
+    }
+
+    pub fn build(self) -> MockProvider {
+        MockProvider {
+            rules: self.rules,
+            queue: Mutex::new(self.queue),
+            fallback: self.fallback,
+            latency: self.latency,
+            chunk_delay: self.chunk_delay,
+            requests: Mutex::new(Vec::new()),
         }
-        Ok(self.response.clone())
     }
+}
 
-    async fn chat(&self, _messages: Vec<Message>) -> Result<String, Error> {
-        if let Some(error) = &self.error {
-            return Err(Error::ProviderError(error.clone()));
+/// Which [`Provider`] method a [`MockRequest`] came through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockCall {
+    Complete,
+    Chat,
+    StreamChat,
+}
+
+/// A request received by a [`MockProvider`]. Prompts passed to `complete` are recorded as a
+/// single user message.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub call: MockCall,
+    pub messages: Vec<Message>,
+    pub options: ChatOptions,
+}
+
+impl MockRequest {
+    /// The text of the last user message, if there is one.
+    pub fn last_user_text(&self) -> Option<String> {
+        self.messages.iter().rev().find(|m| m.role == Role::User).map(Message::text)
+    }
+}
+
+/// One scripted answer of a [`MockProvider`]. Every kind of reply works for every call:
+/// replies are split into stream events for `stream_chat`, and scripted events are collected
+/// into a response for `chat` and `complete`.
+#[derive(Debug)]
+pub enum MockReply {
+    Text(String),
+    /// Text streamed one chunk per event.
+    Chunks(Vec<String>),
+    /// An assistant turn, e.g. one with tool calls.
+    Message(Message),
+    /// A complete response, for control over its metadata.
+    Response(Box<ChatResponse>),
+    /// Exact stream events, including errors or a missing [`StreamEvent::Done`].
+    Events(Vec<Result<StreamEvent, Error>>),
+    /// Fails the call itself.
+    Error(Error),
+}
+
+impl MockReply {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    pub fn chunks<S: Into<String>>(chunks: impl IntoIterator<Item = S>) -> Self {
+        Self::Chunks(chunks.into_iter().map(Into::into).collect())
+    }
+
+    pub fn message(message: Message) -> Self {
+        Self::Message(message)
+    }
+
+    pub fn response(response: ChatResponse) -> Self {
+        Self::Response(Box::new(response))
+    }
+
+    pub fn events(events: Vec<Result<StreamEvent, Error>>) -> Self {
+        Self::Events(events)
+    }
+
+    pub fn error(error: Error) -> Self {
+        Self::Error(error)
+    }
+
+    async fn into_chat_response(self) -> Result<ChatResponse, Error> {
+        match self {
+            Self::Text(text) => Ok(assistant_response(Message::assistant(text))),
+            Self::Chunks(chunks) => Ok(assistant_response(Message::assistant(chunks.concat()))),
+            Self::Message(message) => Ok(assistant_response(message)),
+            Self::Response(response) => Ok(*response),
+            Self::Events(events) => stream::collect(Box::pin(futures::stream::iter(events))).await,
+            Self::Error(error) => Err(error),
         }
-        Ok(self.response.clone())
     }
 
-    async fn stream_chat(&self, _messages: Vec<Message>) -> Result<Pin<Box<dyn Stream<Item = Result<String, Error>> + Send>>, Error> {
-        if let Some(error) = &self.error {
-            return Err(Error::ProviderError(error.clone()));
+    fn into_events(self) -> Result<Vec<Result<StreamEvent, Error>>, Error> {
+        let events = match self {
+            Self::Text(text) => response_events(assistant_response(Message::assistant(text))),
+            Self::Chunks(chunks) => std::iter::once(StreamEvent::Start { id: None, model: "mock".to_string(), provider: None })
+                .chain(chunks.into_iter().map(StreamEvent::TextDelta))
+                .chain([StreamEvent::Stop(FinishReason::Stop), StreamEvent::Done])
+                .collect(),
+            Self::Message(message) => response_events(assistant_response(message)),
+            Self::Response(response) => response_events(*response),
+            Self::Events(events) => return Ok(events),
+            Self::Error(error) => return Err(error),
+        };
+        Ok(events.into_iter().map(Ok).collect())
+    }
+}
+
+impl Clone for MockReply {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Text(text) => Self::Text(text.clone()),
+            Self::Chunks(chunks) => Self::Chunks(chunks.clone()),
+            Self::Message(message) => Self::Message(message.clone()),
+            Self::Response(response) => Self::Response(response.clone()),
+            Self::Events(events) => Self::Events(events
+                .iter()
+                .map(|event| match event {
+                    Ok(event) => Ok(event.clone()),
+                    Err(error) => Err(error.duplicate()),
+                })
+                .collect()),
+            Self::Error(error) => Self::Error(error.duplicate()),
         }
+    }
+}
 
-        let stream_responses = self.stream_responses.clone()
-            .unwrap_or_else(|| vec![self.response.clone()]);
+fn assistant_response(message: Message) -> ChatResponse {
+    let finish_reason = if message.tool_calls.is_empty() { FinishReason::Stop } else { FinishReason::ToolCalls };
+    ChatResponse {
+        id: None,
+        model: "mock".to_string(),
+        provider: None,
+        message,
+        usage: None,
+        finish_reason,
+        rate_limit: None,
+        raw: serde_json::Value::Null,
+    }
+}
 
-        Ok(Box::pin(tokio_stream::iter(stream_responses)
-            .map(|s| Ok(s))
-            .throttle(Duration::from_millis(100))))
+/// The events a provider would stream for `response`.
+fn response_events(response: ChatResponse) -> Vec<StreamEvent> {
+    let mut events = vec![StreamEvent::Start {
+        id: response.id.clone(),
+        model: response.model.clone(),
+        provider: response.provider.clone(),
+    }];
+    let text = response.content();
+    if !text.is_empty() {
+        events.push(StreamEvent::TextDelta(text));
+    }
+    for (index, call) in response.message.tool_calls.into_iter().enumerate() {
+        events.push(StreamEvent::ToolCallDelta(ToolCallDelta {
+            index,
+            id: Some(call.id),
+            name: Some(call.name),
+            arguments: call.arguments.to_string(),
+        }));
+    }
+    if let Some(usage) = response.usage {
+        events.push(StreamEvent::Usage(usage));
+    }
+    events.push(StreamEvent::Stop(response.finish_reason));
+    events.push(StreamEvent::Done);
+    events
+}
+
+#[async_trait]
+impl Provider for MockProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let request = MockRequest {
+            call: MockCall::Complete,
+            messages: vec![Message::user(prompt)],
+            options: ChatOptions::default(),
+        };
+        Ok(self.respond(request).await.into_chat_response().await?.content())
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        let request = MockRequest { call: MockCall::Chat, messages, options: options.clone() };
+        self.respond(request).await.into_chat_response().await
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        let request = MockRequest { call: MockCall::StreamChat, messages, options: options.clone() };
+        let events = futures::stream::iter(self.respond(request).await.into_events()?);
+        if self.chunk_delay.is_zero() {
+            return Ok(Box::pin(events));
+        }
+        let chunk_delay = self.chunk_delay;
+        Ok(Box::pin(events.then(move |event| async move {
+            tokio::time::sleep(chunk_delay).await;
+            event
+        })))
     }
 }
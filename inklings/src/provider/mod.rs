@@ -1,16 +1,80 @@
-use futures::Stream;
-use std::pin::Pin;
 mod openai;
+mod openai_compatible;
+mod azure;
 mod anthropic;
+mod gemini;
+mod mistral;
+mod cohere;
+mod bedrock;
+mod aws;
+mod eventstream;
+mod ollama;
 mod mock;
+mod cassette;
+mod chaos;
+mod fallback;
+mod router;
+pub(crate) mod loopback;
+mod http;
+mod ndjson;
+pub mod normalize;
 
-pub use openai::OpenAIProvider;
-pub use anthropic::AnthropicProvider;
-pub use mock::MockProvider;
+pub use openai::{OpenAIProvider, OpenAIProviderBuilder};
+pub use openai_compatible::{OpenAICompatibleProvider, OpenAICompatibleProviderBuilder, OpenAICompatibleQuirks};
+pub use azure::{AzureOpenAIProvider, AzureOpenAIProviderBuilder, ContentFilterResult, ContentFilterResults, TokenCredential};
+pub use anthropic::{AnthropicProvider, AnthropicProviderBuilder};
+pub use gemini::{GeminiProvider, GeminiProviderBuilder};
+pub use mistral::{MistralProvider, MistralProviderBuilder};
+pub use cohere::{CohereProvider, CohereProviderBuilder};
+pub use bedrock::{BedrockProvider, BedrockProviderBuilder};
+pub use aws::AwsCredentials;
+pub use ollama::{OllamaProvider, OllamaProviderBuilder, PullProgress, PullStream};
+pub use mock::{MockCall, MockProvider, MockProviderBuilder, MockReply, MockRequest};
+pub use cassette::{RecordingProvider, ReplayProvider};
+pub use chaos::{ChaosProvider, Latency};
+pub use fallback::FallbackProvider;
+pub use router::{RouterProvider, RoutingStrategy};
 
 #[async_trait::async_trait]
 pub trait Provider {
     async fn complete(&self, prompt: &str) -> Result<String, crate::types::Error>;
-    async fn chat(&self, messages: Vec<crate::types::Message>) -> Result<String, crate::types::Error>;
-    async fn stream_chat(&self, messages: Vec<crate::types::Message>) -> Result<Pin<Box<dyn Stream<Item = Result<String, crate::types::Error>> + Send>>, crate::types::Error>;
+    /// Sends the conversation with the given generation options and returns the assistant turn.
+    async fn chat(&self, messages: Vec<crate::types::Message>, options: &crate::types::ChatOptions) -> Result<crate::types::ChatResponse, crate::types::Error>;
+    async fn stream_chat(&self, messages: Vec<crate::types::Message>, options: &crate::types::ChatOptions) -> Result<crate::types::ChatStream, crate::types::Error>;
+
+    /// Names of the fields set in `options` that this provider cannot honour and will ignore.
+    fn unsupported_options(&self, _options: &crate::types::ChatOptions) -> Vec<&'static str> {
+        Vec::new()
+    }
+}
+
+/// Lets a provider be shared, e.g. to keep a handle on a [`MockProvider`] given to a
+/// [`Client`](crate::Client).
+#[async_trait::async_trait]
+impl<P: Provider + Send + Sync + ?Sized> Provider for std::sync::Arc<P> {
+    async fn complete(&self, prompt: &str) -> Result<String, crate::types::Error> {
+        (**self).complete(prompt).await
+    }
+
+    async fn chat(&self, messages: Vec<crate::types::Message>, options: &crate::types::ChatOptions) -> Result<crate::types::ChatResponse, crate::types::Error> {
+        (**self).chat(messages, options).await
+    }
+
+    async fn stream_chat(&self, messages: Vec<crate::types::Message>, options: &crate::types::ChatOptions) -> Result<crate::types::ChatStream, crate::types::Error> {
+        (**self).stream_chat(messages, options).await
+    }
+
+    fn unsupported_options(&self, options: &crate::types::ChatOptions) -> Vec<&'static str> {
+        (**self).unsupported_options(options)
+    }
+}
+
+/// Converts a non-success HTTP response into the matching [`Error`](crate::types::Error) variant.
+pub(crate) async fn error_from_response(response: reqwest::Response) -> crate::types::Error {
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    match response.text().await {
+        Ok(body) => crate::types::Error::from_response_parts(status, &headers, &body),
+        Err(e) => e.into(),
+    }
 }
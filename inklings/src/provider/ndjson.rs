@@ -0,0 +1,42 @@
+//! Splitting newline-delimited JSON bodies into lines.
+
+use futures::{Stream, StreamExt};
+use crate::types::Error;
+
+/// Yields each non-blank line of `bytes`, however the body is split into chunks. A final line
+/// without a trailing newline is still reported.
+pub(crate) fn lines<S, B, E>(bytes: S) -> impl Stream<Item = Result<String, Error>> + Send
+where
+    S: Stream<Item = Result<B, E>> + Send + 'static,
+    B: AsRef<[u8]>,
+    E: Into<Error>,
+{
+    futures::stream::unfold(
+        (Box::pin(bytes), Vec::new(), false),
+        |(mut bytes, mut buffer, mut finished)| async move {
+            loop {
+                if let Some(end) = buffer.iter().position(|byte| *byte == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let line = String::from_utf8_lossy(&line).trim().to_string();
+                    if !line.is_empty() {
+                        return Some((Ok(line), (bytes, buffer, finished)));
+                    }
+                    continue;
+                }
+                if finished {
+                    let line = String::from_utf8_lossy(&buffer).trim().to_string();
+                    buffer.clear();
+                    return (!line.is_empty()).then(|| (Ok(line), (bytes, buffer, finished)));
+                }
+                match bytes.next().await {
+                    Some(Ok(chunk)) => buffer.extend_from_slice(chunk.as_ref()),
+                    Some(Err(e)) => {
+                        buffer.clear();
+                        return Some((Err(e.into()), (bytes, buffer, true)));
+                    }
+                    None => finished = true,
+                }
+            }
+        },
+    )
+}
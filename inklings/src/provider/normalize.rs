@@ -0,0 +1,140 @@
+//! Message normalisation shared by the providers.
+//!
+//! Each provider API has its own rules about where system prompts go and how turns may be
+//! ordered. Providers describe those rules with [`RoleRules`] and run [`normalize`] on the
+//! conversation before converting it to their wire format, so callers can send the same
+//! `Vec<Message>` to any provider.
+
+use crate::types::{ContentPart, Message, Role, ToolCall};
+
+/// Text inserted as the opening user turn when a provider requires one and the conversation
+/// starts with the assistant.
+pub const LEADING_USER_PLACEHOLDER: &str = "(continue)";
+
+/// Where system messages end up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemPlacement {
+    /// System messages stay in the message list where the caller put them.
+    Inline,
+    /// Every system message, including mid-conversation ones, is removed from the list and
+    /// their text is joined in order into a single top-level system prompt. Image and document
+    /// parts of system messages are dropped.
+    TopLevel,
+}
+
+/// The turn-ordering rules a provider enforces.
+#[derive(Debug, Clone, Copy)]
+pub struct RoleRules {
+    pub system: SystemPlacement,
+    /// Consecutive turns with the same role are merged so user and assistant strictly alternate.
+    pub alternate: bool,
+    /// The first non-system turn must come from the user.
+    pub leading_user: bool,
+    /// Turns without text or tool traffic are dropped, except for a trailing assistant turn,
+    /// which providers treat as a prefill.
+    pub drop_empty: bool,
+}
+
+impl Default for RoleRules {
+    /// Rules that leave the conversation untouched.
+    fn default() -> Self {
+        Self {
+            system: SystemPlacement::Inline,
+            alternate: false,
+            leading_user: false,
+            drop_empty: false,
+        }
+    }
+}
+
+/// A conversation rewritten to satisfy a provider's [`RoleRules`].
+#[derive(Debug, Clone)]
+pub struct Normalized {
+    /// The merged system prompt when [`SystemPlacement::TopLevel`] is used.
+    pub system: Option<String>,
+    pub messages: Vec<Message>,
+}
+
+pub fn normalize(messages: Vec<Message>, rules: &RoleRules) -> Normalized {
+    let mut system: Option<String> = None;
+    let mut turns = Vec::with_capacity(messages.len());
+
+    for message in messages {
+        if message.role == Role::System && rules.system == SystemPlacement::TopLevel {
+            let text = message.text();
+            if !text.is_empty() {
+                system = Some(join(system.unwrap_or_default(), &text));
+            }
+            continue;
+        }
+        turns.push(message);
+    }
+
+    if rules.drop_empty {
+        let last = turns.len().saturating_sub(1);
+        let mut index = 0;
+        turns.retain(|m| {
+            let keep = !is_empty(m) || (index == last && m.role == Role::Assistant);
+            index += 1;
+            keep
+        });
+    }
+
+    if rules.alternate {
+        turns = merge_consecutive(turns);
+    }
+
+    if rules.leading_user {
+        let first = turns.iter().position(|m| m.role != Role::System);
+        if let Some(first) = first.filter(|&i| turns[i].role != Role::User) {
+            turns.insert(first, Message::user(LEADING_USER_PLACEHOLDER));
+        }
+    }
+
+    Normalized { system, messages: turns }
+}
+
+/// The call that a tool result with `call_id` answers: the latest one with that id in `earlier`.
+/// Providers that number calls themselves start again with every response, so the same id may
+/// belong to calls of several turns.
+pub(crate) fn answered_call<'a>(earlier: &'a [Message], call_id: &str) -> Option<&'a ToolCall> {
+    earlier.iter().rev().flat_map(|m| &m.tool_calls).find(|call| call.id == call_id)
+}
+
+fn merge_consecutive(messages: Vec<Message>) -> Vec<Message> {
+    let mut merged: Vec<Message> = Vec::with_capacity(messages.len());
+    for message in messages {
+        match merged.last_mut() {
+            Some(previous) if previous.role == message.role => {
+                append_parts(&mut previous.content, message.content);
+                previous.tool_calls.extend(message.tool_calls);
+                previous.tool_results.extend(message.tool_results);
+            }
+            _ => merged.push(message),
+        }
+    }
+    merged
+}
+
+fn is_empty(message: &Message) -> bool {
+    message.content.is_empty() && message.tool_calls.is_empty() && message.tool_results.is_empty()
+}
+
+/// Appends `parts`, joining adjacent text parts the same way as system prompts.
+fn append_parts(content: &mut Vec<ContentPart>, parts: Vec<ContentPart>) {
+    let mut parts = parts.into_iter().peekable();
+    if let (Some(ContentPart::Text(previous)), Some(ContentPart::Text(_))) = (content.last_mut(), parts.peek()) {
+        if let Some(ContentPart::Text(text)) = parts.next() {
+            *previous = join(std::mem::take(previous), &text);
+        }
+    }
+    content.extend(parts);
+}
+
+fn join(mut text: String, addition: &str) -> String {
+    if !text.is_empty() && !addition.is_empty() {
+        text.push_str("\n\n");
+    }
+    text.push_str(addition);
+    text
+}
@@ -0,0 +1,451 @@
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde_json::json;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, ContentPart, Error, FinishReason, MediaSource, Message, Role, StreamEvent, Tool, ToolCall, ToolCallDelta, Usage};
+use crate::stream;
+use super::Provider;
+use super::http::{HttpClient, HttpConfig};
+use super::ndjson;
+use super::normalize::{normalize, RoleRules};
+use std::pin::Pin;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+const DEFAULT_MODEL: &str = "llama3.2";
+
+/// Progress reports from [`OllamaProvider::pull_model`].
+pub type PullStream = Pin<Box<dyn Stream<Item = Result<PullProgress, Error>> + Send>>;
+
+/// One status line from a model download.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullProgress {
+    /// What Ollama is doing, e.g. `pulling manifest`, `verifying sha256 digest` or `success`.
+    pub status: String,
+    /// The layer being downloaded, if any.
+    pub digest: Option<String>,
+    /// Size of the layer in bytes.
+    pub total: Option<u64>,
+    /// Bytes of the layer downloaded so far.
+    pub completed: Option<u64>,
+}
+
+impl PullProgress {
+    /// The downloaded fraction of the current layer, between 0 and 1.
+    pub fn fraction(&self) -> Option<f64> {
+        match (self.completed, self.total) {
+            (Some(completed), Some(total)) if total > 0 => Some(completed as f64 / total as f64),
+            _ => None,
+        }
+    }
+}
+
+/// A provider for a local Ollama server using its native API.
+///
+/// No API key is needed. Unlike the OpenAI-compatible endpoint, the native API accepts model
+/// `options` such as `num_ctx` or `mirostat`, controls how long the model stays loaded with
+/// `keep_alive`, and can download models.
+pub struct OllamaProvider {
+    http: HttpClient,
+    model: String,
+    keep_alive: Option<String>,
+    options: serde_json::Map<String, serde_json::Value>,
+}
+
+impl OllamaProvider {
+    pub fn new(model: Option<String>) -> Self {
+        let mut builder = Self::builder();
+        if let Some(model) = model {
+            builder = builder.model(model);
+        }
+        builder.build().expect("default Ollama configuration is valid")
+    }
+
+    pub fn builder() -> OllamaProviderBuilder {
+        OllamaProviderBuilder {
+            model: DEFAULT_MODEL.to_string(),
+            keep_alive: None,
+            options: serde_json::Map::new(),
+            http: HttpConfig::new(DEFAULT_BASE_URL),
+        }
+    }
+
+    /// Lists the names of the models available locally.
+    pub async fn list_models(&self) -> Result<Vec<String>, Error> {
+        let response = self.http.get("/api/tags").send().await?;
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
+
+        let response: serde_json::Value = response.json().await?;
+        let models = response["models"]
+            .as_array()
+            .ok_or_else(|| Error::ProviderError("Invalid response format".to_string()))?;
+        Ok(models
+            .iter()
+            .filter_map(|model| model["name"].as_str().map(str::to_string))
+            .collect())
+    }
+
+    /// Downloads `model` from the Ollama library, reporting progress as it goes. The stream ends
+    /// after the `success` status, or with an error if the pull fails.
+    pub async fn pull_model(&self, model: &str) -> Result<PullStream, Error> {
+        let response = self
+            .http
+            .post("/api/pull")
+            .json(&json!({ "model": model, "stream": true }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
+
+        Ok(Box::pin(ndjson::lines(response.bytes_stream()).map(|line| {
+            let json: serde_json::Value = serde_json::from_str(&line?)
+                .map_err(|e| Error::ProviderError(e.to_string()))?;
+            if json.get("error").is_some() {
+                return Err(Error::from_error_body(None, &json));
+            }
+            Ok(PullProgress {
+                status: json["status"].as_str().unwrap_or_default().to_string(),
+                digest: json["digest"].as_str().map(str::to_string),
+                total: json["total"].as_u64(),
+                completed: json["completed"].as_u64(),
+            })
+        })))
+    }
+
+    fn convert_role(role: &Role) -> &'static str {
+        match role {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+
+    fn convert_messages(messages: &[Message]) -> Result<Vec<serde_json::Value>, Error> {
+        let mut converted = Vec::with_capacity(messages.len());
+        for m in messages {
+            // Ollama matches results to calls by position, so only the content is sent.
+            for result in &m.tool_results {
+                converted.push(json!({ "role": "tool", "content": result.content }));
+            }
+            if !m.tool_results.is_empty() && m.content.is_empty() {
+                continue;
+            }
+
+            let mut message = json!({
+                "role": Self::convert_role(&m.role),
+                "content": m.text(),
+            });
+            let images = Self::convert_images(&m.content)?;
+            if !images.is_empty() {
+                message["images"] = images.into();
+            }
+            if !m.tool_calls.is_empty() {
+                message["tool_calls"] = m.tool_calls
+                    .iter()
+                    .map(|call| json!({
+                        "function": { "name": call.name, "arguments": call.arguments }
+                    }))
+                    .collect();
+            }
+            converted.push(message);
+        }
+        Ok(converted)
+    }
+
+    /// Ollama takes a message's images as a list of base64 strings next to its text.
+    fn convert_images(content: &[ContentPart]) -> Result<Vec<String>, Error> {
+        content
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text(_) => None,
+                ContentPart::Image(MediaSource::Base64 { data, .. }) => Some(Ok(data.clone())),
+                ContentPart::Image(MediaSource::Url { .. }) => {
+                    Some(Err(Error::Unsupported("Ollama only accepts images as inline data".to_string())))
+                }
+                ContentPart::Document { .. } => Some(Err(Error::Unsupported("Ollama does not accept documents".to_string()))),
+            })
+            .collect()
+    }
+
+    fn convert_tools(tools: &[Tool]) -> Vec<serde_json::Value> {
+        tools
+            .iter()
+            .map(|tool| json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters,
+                }
+            }))
+            .collect()
+    }
+
+    /// The configured model options overlaid with the per-call sampling settings.
+    fn build_options(&self, options: &ChatOptions) -> serde_json::Map<String, serde_json::Value> {
+        let mut merged = self.options.clone();
+        if let Some(temperature) = options.temperature {
+            merged.insert("temperature".to_string(), temperature.into());
+        }
+        if let Some(top_p) = options.top_p {
+            merged.insert("top_p".to_string(), top_p.into());
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            merged.insert("num_predict".to_string(), max_tokens.into());
+        }
+        if !options.stop.is_empty() {
+            merged.insert("stop".to_string(), options.stop.clone().into());
+        }
+        if let Some(seed) = options.seed {
+            merged.insert("seed".to_string(), seed.into());
+        }
+        if let Some(presence_penalty) = options.presence_penalty {
+            merged.insert("presence_penalty".to_string(), presence_penalty.into());
+        }
+        if let Some(frequency_penalty) = options.frequency_penalty {
+            merged.insert("frequency_penalty".to_string(), frequency_penalty.into());
+        }
+        merged
+    }
+
+    /// Adds the fields shared by `/api/chat` and `/api/generate`.
+    fn finish_body(&self, mut body: serde_json::Value, options: &ChatOptions, stream: bool) -> serde_json::Value {
+        body["model"] = self.model.clone().into();
+        body["stream"] = stream.into();
+        let format = options.response_format.as_ref().map(|format| format.schema.clone());
+        let options = self.build_options(options);
+        if !options.is_empty() {
+            body["options"] = options.into();
+        }
+        if let Some(keep_alive) = &self.keep_alive {
+            body["keep_alive"] = keep_alive.clone().into();
+        }
+        if let Some(format) = format {
+            body["format"] = format;
+        }
+        body
+    }
+
+    fn build_body(&self, messages: Vec<Message>, options: &ChatOptions, stream: bool) -> Result<serde_json::Value, Error> {
+        let normalized = normalize(messages, &RoleRules::default());
+        let mut body = json!({ "messages": Self::convert_messages(&normalized.messages)? });
+        if !options.tools.is_empty() {
+            body["tools"] = Self::convert_tools(&options.tools).into();
+        }
+        Ok(self.finish_body(body, options, stream))
+    }
+
+    fn parse_response(response: serde_json::Value) -> Result<ChatResponse, Error> {
+        let message = &response["message"];
+        if !message.is_object() {
+            return Err(Error::ProviderError("Invalid response format".to_string()));
+        }
+
+        // Ollama does not assign ids to tool calls; number them so results can refer back.
+        let tool_calls: Vec<ToolCall> = message["tool_calls"]
+            .as_array()
+            .map(|calls| calls.iter().enumerate().map(|(index, call)| ToolCall {
+                id: format!("call_{}", index),
+                name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                arguments: call["function"]["arguments"].clone(),
+            }).collect())
+            .unwrap_or_default();
+
+        let finish_reason = if tool_calls.is_empty() {
+            Self::convert_finish_reason(response["done_reason"].as_str().unwrap_or("stop"))
+        } else {
+            FinishReason::ToolCalls
+        };
+        let message = Message {
+            tool_calls,
+            ..Message::assistant(message["content"].as_str().unwrap_or_default())
+        };
+        Ok(ChatResponse {
+            id: None,
+            model: response["model"].as_str().unwrap_or_default().to_string(),
+            provider: None,
+            message,
+            usage: Self::parse_usage(&response),
+            finish_reason,
+            rate_limit: None,
+            raw: response,
+        })
+    }
+
+    fn parse_usage(response: &serde_json::Value) -> Option<Usage> {
+        Some(Usage {
+            input_tokens: response["prompt_eval_count"].as_u64()? as u32,
+            output_tokens: response["eval_count"].as_u64()? as u32,
+        })
+    }
+
+    fn convert_finish_reason(reason: &str) -> FinishReason {
+        match reason {
+            "stop" => FinishReason::Stop,
+            "length" => FinishReason::Length,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+}
+
+/// Configures an [`OllamaProvider`]; created with [`OllamaProvider::builder`].
+pub struct OllamaProviderBuilder {
+    model: String,
+    keep_alive: Option<String>,
+    options: serde_json::Map<String, serde_json::Value>,
+    http: HttpConfig,
+}
+
+impl OllamaProviderBuilder {
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// The server root. Defaults to `http://localhost:11434`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.http.base_url = base_url.into();
+        self
+    }
+
+    /// How long the model stays loaded after a request, in Ollama's duration format (`"10m"`,
+    /// `"1h"`, `"0"` to unload at once, `"-1"` to keep it loaded).
+    pub fn keep_alive(mut self, keep_alive: impl Into<String>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    /// Sets a model option such as `num_ctx`, `mirostat` or `repeat_penalty`. Sampling
+    /// settings from [`ChatOptions`] take precedence over options of the same name.
+    pub fn option(mut self, name: impl Into<String>, value: impl Into<serde_json::Value>) -> Self {
+        self.options.insert(name.into(), value.into());
+        self
+    }
+
+    super::http::http_setters!();
+
+    pub fn build(self) -> Result<OllamaProvider, Error> {
+        Ok(OllamaProvider {
+            http: self.http.build()?,
+            model: self.model,
+            keep_alive: self.keep_alive,
+            options: self.options,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for OllamaProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let body = self.finish_body(json!({ "prompt": prompt }), &ChatOptions::default(), false);
+        let response = self.http.post("/api/generate").json(&body).send().await?;
+
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
+
+        let response: serde_json::Value = response.json().await?;
+        response["response"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::ProviderError("Invalid response format".to_string()))
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        let response = self
+            .http
+            .post("/api/chat")
+            .json(&self.build_body(messages, options, false)?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
+
+        let response: serde_json::Value = response.json().await?;
+        Self::parse_response(response)
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        let response = self
+            .http
+            .post("/api/chat")
+            .json(&self.build_body(messages, options, true)?)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
+
+        let mut parser = StreamParser::default();
+        Ok(stream::guard(ndjson::lines(response.bytes_stream()).flat_map(move |line| {
+            let events = match line.and_then(|line| parser.parse(&line)) {
+                Ok(events) => events.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(events)
+        })))
+    }
+}
+
+/// Turns `/api/chat` stream lines into [`StreamEvent`]s.
+#[derive(Default)]
+struct StreamParser {
+    started: bool,
+    tool_calls: usize,
+}
+
+impl StreamParser {
+    fn parse(&mut self, line: &str) -> Result<Vec<StreamEvent>, Error> {
+        let json: serde_json::Value = serde_json::from_str(line)
+            .map_err(|e| Error::ProviderError(e.to_string()))?;
+
+        if json.get("error").is_some() {
+            return Err(Error::from_error_body(None, &json));
+        }
+
+        let mut events = Vec::new();
+        if !self.started {
+            self.started = true;
+            events.push(StreamEvent::Start {
+                id: None,
+                model: json["model"].as_str().unwrap_or_default().to_string(),
+                provider: None,
+            });
+        }
+
+        let message = &json["message"];
+        if let Some(text) = message["content"].as_str().filter(|text| !text.is_empty()) {
+            events.push(StreamEvent::TextDelta(text.to_string()));
+        }
+        // Tool calls arrive whole rather than in fragments.
+        for call in message["tool_calls"].as_array().into_iter().flatten() {
+            events.push(StreamEvent::ToolCallDelta(ToolCallDelta {
+                index: self.tool_calls,
+                id: Some(format!("call_{}", self.tool_calls)),
+                name: call["function"]["name"].as_str().map(str::to_string),
+                arguments: call["function"]["arguments"].to_string(),
+            }));
+            self.tool_calls += 1;
+        }
+
+        // The final line carries the counts and the reason generation ended.
+        if json["done"].as_bool() == Some(true) {
+            if let Some(usage) = OllamaProvider::parse_usage(&json) {
+                events.push(StreamEvent::Usage(usage));
+            }
+            let reason = if self.tool_calls > 0 {
+                FinishReason::ToolCalls
+            } else {
+                OllamaProvider::convert_finish_reason(json["done_reason"].as_str().unwrap_or("stop"))
+            };
+            events.push(StreamEvent::Stop(reason));
+            events.push(StreamEvent::Done);
+        }
+        Ok(events)
+    }
+}
@@ -1,26 +1,48 @@
 use async_trait::async_trait;
 use serde_json::json;
-use crate::types::{Error, Message, Role};
+use crate::types::{ChatOptions, ChatResponse, ChatStream, ContentPart, Error, FinishReason, MediaSource, Message, Role, StreamEvent, Tool, ToolCall, ToolCallDelta, Usage};
+use crate::rate_limit::RateLimitStatus;
+use crate::stream;
 use super::Provider;
-use futures::{Stream, StreamExt};
+use super::http::{HttpClient, HttpConfig};
+use super::normalize::{normalize, RoleRules};
+use super::openai_compatible::OpenAICompatibleQuirks;
+use futures::StreamExt;
 use eventsource_stream::Eventsource;
-use std::pin::Pin;
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
 
 pub struct OpenAIProvider {
     api_key: String,
-    client: reqwest::Client,
+    http: HttpClient,
     model: String,
 }
 
 impl OpenAIProvider {
     pub fn new(api_key: String, model: Option<String>) -> Self {
-        Self {
-            api_key,
-            client: reqwest::Client::new(),
-            model: model.unwrap_or_else(|| "gpt-4o-mini".to_string()),
+        let mut builder = Self::builder(api_key);
+        if let Some(model) = model {
+            builder = builder.model(model);
+        }
+        builder.build().expect("default OpenAI configuration is valid")
+    }
+
+    pub fn builder(api_key: impl Into<String>) -> OpenAIProviderBuilder {
+        OpenAIProviderBuilder {
+            api_key: api_key.into(),
+            model: DEFAULT_MODEL.to_string(),
+            organization: None,
+            project: None,
+            http: HttpConfig::new(DEFAULT_BASE_URL),
         }
     }
 
+    /// Lists the ids of the models available to this API key.
+    pub async fn list_models(&self) -> Result<Vec<String>, Error> {
+        Self::fetch_models(self.http.get("/models").bearer_auth(&self.api_key)).await
+    }
+
     fn convert_role(role: &Role) -> &'static str {
         match role {
             Role::System => "system",
@@ -28,103 +50,385 @@ impl OpenAIProvider {
             Role::Assistant => "assistant",
         }
     }
-}
 
-#[async_trait]
-impl Provider for OpenAIProvider {
-    async fn complete(&self, prompt: &str) -> Result<String, Error> {
-        let messages = vec![Message {
-            role: Role::User,
-            content: prompt.to_string(),
-        }];
-        self.chat(messages).await
+    fn convert_messages(messages: &[Message], quirks: &OpenAICompatibleQuirks) -> Result<Vec<serde_json::Value>, Error> {
+        let mut converted = Vec::with_capacity(messages.len());
+        for m in messages {
+            // OpenAI expects one `tool` message per result, ahead of any text in the same turn.
+            for result in &m.tool_results {
+                converted.push(json!({
+                    "role": "tool",
+                    "tool_call_id": result.tool_call_id,
+                    "content": result.content,
+                }));
+            }
+            if !m.tool_results.is_empty() && m.content.is_empty() {
+                continue;
+            }
+
+            let mut message = json!({
+                "role": Self::convert_role(&m.role),
+                "content": Self::convert_content(&m.content, quirks)?,
+            });
+            if !m.tool_calls.is_empty() {
+                message["tool_calls"] = m.tool_calls
+                    .iter()
+                    .map(|call| json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": {
+                            "name": call.name,
+                            "arguments": match &call.arguments {
+                                // Arguments that were not valid JSON are kept as the raw string.
+                                serde_json::Value::String(raw) => raw.clone(),
+                                arguments => arguments.to_string(),
+                            },
+                        }
+                    }))
+                    .collect();
+                if m.content.is_empty() {
+                    message["content"] = serde_json::Value::Null;
+                }
+            }
+            converted.push(message);
+        }
+        Ok(converted)
     }
 
-    async fn chat(&self, messages: Vec<Message>) -> Result<String, Error> {
-        let messages: Vec<serde_json::Value> = messages
+    /// Text-only content is sent as a plain string, anything else as an array of parts.
+    fn convert_content(content: &[ContentPart], quirks: &OpenAICompatibleQuirks) -> Result<serde_json::Value, Error> {
+        if content.iter().all(|part| part.as_text().is_some()) {
+            return Ok(content.iter().filter_map(ContentPart::as_text).collect::<String>().into());
+        }
+        content
             .iter()
-            .map(|m| {
-                json!({
-                    "role": Self::convert_role(&m.role),
-                    "content": m.content
-                })
+            .map(|part| match part {
+                ContentPart::Text(text) => Ok(json!({ "type": "text", "text": text })),
+                ContentPart::Image(_) if !quirks.images => {
+                    Err(Error::Unsupported("this model does not accept images".to_string()))
+                }
+                ContentPart::Image(source) => Ok(json!({ "type": "image_url", "image_url": { "url": source.to_url() } })),
+                ContentPart::Document { source: source @ MediaSource::Base64 { .. }, name } if quirks.documents => {
+                    Ok(json!({
+                        "type": "file",
+                        "file": {
+                            "filename": name.as_deref().unwrap_or("document.pdf"),
+                            "file_data": source.to_url(),
+                        }
+                    }))
+                }
+                ContentPart::Document { source: MediaSource::Url { .. }, .. } if quirks.documents => {
+                    Err(Error::Unsupported("Chat Completions only accepts documents as inline data".to_string()))
+                }
+                ContentPart::Document { .. } => {
+                    Err(Error::Unsupported("this model does not accept documents".to_string()))
+                }
             })
-            .collect();
-
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&json!({
-                "model": self.model,
-                "messages": messages,
+            .collect::<Result<Vec<_>, Error>>()
+            .map(Into::into)
+    }
+
+    /// Builds a Chat Completions request body, leaving out whatever `quirks` says the backend
+    /// rejects.
+    pub(crate) fn build_body(
+        model: &str,
+        messages: Vec<Message>,
+        options: &ChatOptions,
+        stream: bool,
+        quirks: &OpenAICompatibleQuirks,
+    ) -> Result<serde_json::Value, Error> {
+        // Chat Completions accepts system turns anywhere and any role order.
+        let normalized = normalize(messages, &RoleRules::default());
+        let mut body = json!({
+            "model": model,
+            "messages": Self::convert_messages(&normalized.messages, quirks)?,
+        });
+        if stream {
+            body["stream"] = true.into();
+            if quirks.stream_options {
+                body["stream_options"] = json!({ "include_usage": true });
+            }
+        }
+        if quirks.tools && !options.tools.is_empty() {
+            body["tools"] = Self::convert_tools(&options.tools).into();
+        }
+        if let Some(temperature) = options.temperature {
+            body["temperature"] = temperature.into();
+        }
+        if let Some(top_p) = options.top_p {
+            body["top_p"] = top_p.into();
+        }
+        if let Some(max_tokens) = options.max_tokens {
+            body["max_tokens"] = max_tokens.into();
+        }
+        if !options.stop.is_empty() {
+            body["stop"] = options.stop.clone().into();
+        }
+        if let Some(format) = options.response_format.as_ref().filter(|_| quirks.response_format) {
+            body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": { "name": format.name, "schema": format.schema, "strict": true },
+            });
+        }
+        if let Some(seed) = options.seed.filter(|_| quirks.seed) {
+            body["seed"] = seed.into();
+        }
+        if quirks.penalties {
+            if let Some(presence_penalty) = options.presence_penalty {
+                body["presence_penalty"] = presence_penalty.into();
+            }
+            if let Some(frequency_penalty) = options.frequency_penalty {
+                body["frequency_penalty"] = frequency_penalty.into();
+            }
+        }
+        Ok(body)
+    }
+
+    fn convert_tools(tools: &[Tool]) -> Vec<serde_json::Value> {
+        tools
+            .iter()
+            .map(|tool| json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters,
+                }
             }))
-            .send()
-            .await?;
+            .collect()
+    }
+
+    fn parse_response(response: serde_json::Value) -> Result<ChatResponse, Error> {
+        let message = &response["choices"][0]["message"];
+        if !message.is_object() {
+            return Err(Error::ProviderError("Invalid response format".to_string()));
+        }
+
+        let tool_calls = message["tool_calls"]
+            .as_array()
+            .map(|calls| calls.iter().map(|call| {
+                let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+                ToolCall {
+                    id: call["id"].as_str().unwrap_or_default().to_string(),
+                    name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                    // Models occasionally emit malformed JSON; keep the raw text rather than failing.
+                    arguments: serde_json::from_str(arguments)
+                        .unwrap_or_else(|_| serde_json::Value::String(arguments.to_string())),
+                }
+            }).collect())
+            .unwrap_or_default();
+
+        let message = Message {
+            tool_calls,
+            ..Message::assistant(message["content"].as_str().unwrap_or_default())
+        };
+        Ok(ChatResponse {
+            id: response["id"].as_str().map(str::to_string),
+            model: response["model"].as_str().unwrap_or_default().to_string(),
+            provider: None,
+            message,
+            usage: Self::parse_usage(&response["usage"]),
+            finish_reason: Self::convert_finish_reason(response["choices"][0]["finish_reason"].as_str().unwrap_or_default()),
+            rate_limit: None,
+            raw: response,
+        })
+    }
+
+    fn parse_usage(usage: &serde_json::Value) -> Option<Usage> {
+        Some(Usage {
+            input_tokens: usage["prompt_tokens"].as_u64()? as u32,
+            output_tokens: usage["completion_tokens"].as_u64()? as u32,
+        })
+    }
 
+    /// Reads the ids from a `GET /models` listing.
+    pub(crate) async fn fetch_models(request: reqwest::RequestBuilder) -> Result<Vec<String>, Error> {
+        let response = request.send().await?;
         if !response.status().is_success() {
-            return Err(Error::ProviderError(format!(
-                "OpenAI API error: {}",
-                response.text().await?
-            )));
+            return Err(super::error_from_response(response).await);
         }
 
         let response: serde_json::Value = response.json().await?;
-        Ok(response["choices"][0]["message"]["content"]
-            .as_str()
-            .ok_or_else(|| Error::ProviderError("Invalid response format".to_string()))?
-            .to_string())
+        let models = response["data"]
+            .as_array()
+            .ok_or_else(|| Error::ProviderError("Invalid response format".to_string()))?;
+        Ok(models
+            .iter()
+            .filter_map(|model| model["id"].as_str().map(str::to_string))
+            .collect())
     }
 
-    async fn stream_chat(&self, messages: Vec<Message>) -> Result<Pin<Box<dyn Stream<Item = Result<String, Error>> + Send>>, Error> {
-        let messages: Vec<serde_json::Value> = messages
-            .iter()
-            .map(|m| {
-                json!({
-                    "role": Self::convert_role(&m.role),
-                    "content": m.content
-                })
-            })
-            .collect();
-
-        let response = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&json!({
-                "model": self.model,
-                "messages": messages,
-                "stream": true,
-            }))
-            .send()
-            .await?;
+    /// Sends a Chat Completions request and parses the reply.
+    pub(crate) async fn send_chat(request: reqwest::RequestBuilder) -> Result<ChatResponse, Error> {
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(super::error_from_response(response).await);
+        }
 
+        let rate_limit = RateLimitStatus::from_headers(response.headers());
+        let response: serde_json::Value = response.json().await?;
+        Ok(ChatResponse { rate_limit, ..Self::parse_response(response)? })
+    }
+
+    /// Sends a streaming Chat Completions request and turns the server-sent events into a
+    /// [`ChatStream`].
+    pub(crate) async fn send_stream(request: reqwest::RequestBuilder) -> Result<ChatStream, Error> {
+        let response = request.send().await?;
         if !response.status().is_success() {
-            return Err(Error::ProviderError(format!(
-                "OpenAI API error: {}",
-                response.text().await?
-            )));
+            return Err(super::error_from_response(response).await);
         }
 
-        Ok(Box::pin(response
+        let mut parser = StreamParser::default();
+        Ok(stream::guard(response
             .bytes_stream()
             .eventsource()
-            .map(|event| {
-                event
-                    .map_err(|e| Error::ProviderError(e.to_string()))
-                    .and_then(|event| {
-                        let data = event.data;
-                        if data == "[DONE]" {
-                            return Ok("".to_string());
-                        }
-                        let json: serde_json::Value = serde_json::from_str(&data)
-                            .map_err(|e| Error::ProviderError(e.to_string()))?;
-                        Ok(json["choices"][0]["delta"]["content"]
-                            .as_str()
-                            .unwrap_or("")
-                            .to_string())
-                    })
-            })
-            .filter(|result| futures::future::ready(!matches!(result, Ok(s) if s.is_empty())))))
+            .flat_map(move |event| {
+                let events = match event {
+                    Ok(event) => match parser.parse(&event.data) {
+                        Ok(events) => events.into_iter().map(Ok).collect(),
+                        Err(e) => vec![Err(e)],
+                    },
+                    Err(e) => vec![Err(Error::ProviderError(e.to_string()))],
+                };
+                futures::stream::iter(events)
+            })))
+    }
+
+    fn convert_finish_reason(reason: &str) -> FinishReason {
+        match reason {
+            "stop" => FinishReason::Stop,
+            // Mistral reports `model_length` when the context window rather than `max_tokens` ran out.
+            "length" | "model_length" => FinishReason::Length,
+            "tool_calls" | "function_call" => FinishReason::ToolCalls,
+            "content_filter" => FinishReason::ContentFilter,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
+}
+
+/// Configures an [`OpenAIProvider`]; created with [`OpenAIProvider::builder`].
+pub struct OpenAIProviderBuilder {
+    api_key: String,
+    model: String,
+    organization: Option<String>,
+    project: Option<String>,
+    http: HttpConfig,
+}
+
+impl OpenAIProviderBuilder {
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// The API root, including the version segment. Defaults to `https://api.openai.com/v1`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.http.base_url = base_url.into();
+        self
+    }
+
+    /// Sent as the `OpenAI-Organization` header.
+    pub fn organization(mut self, organization: impl Into<String>) -> Self {
+        self.organization = Some(organization.into());
+        self
+    }
+
+    /// Sent as the `OpenAI-Project` header.
+    pub fn project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    super::http::http_setters!();
+
+    pub fn build(mut self) -> Result<OpenAIProvider, Error> {
+        if let Some(organization) = self.organization {
+            self.http.headers.push(("OpenAI-Organization".to_string(), organization));
+        }
+        if let Some(project) = self.project {
+            self.http.headers.push(("OpenAI-Project".to_string(), project));
+        }
+        Ok(OpenAIProvider {
+            api_key: self.api_key,
+            http: self.http.build()?,
+            model: self.model,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAIProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let messages = vec![Message::user(prompt)];
+        Ok(self.chat(messages, &ChatOptions::default()).await?.content())
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        let body = Self::build_body(&self.model, messages, options, false, &OpenAICompatibleQuirks::default())?;
+        Self::send_chat(self.http.post("/chat/completions").bearer_auth(&self.api_key).json(&body)).await
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        let body = Self::build_body(&self.model, messages, options, true, &OpenAICompatibleQuirks::default())?;
+        Self::send_stream(self.http.post("/chat/completions").bearer_auth(&self.api_key).json(&body)).await
+    }
+}
+
+/// Turns Chat Completions chunks into [`StreamEvent`]s.
+#[derive(Default)]
+struct StreamParser {
+    started: bool,
+}
+
+impl StreamParser {
+    fn parse(&mut self, data: &str) -> Result<Vec<StreamEvent>, Error> {
+        if data == "[DONE]" {
+            return Ok(vec![StreamEvent::Done]);
+        }
+        let json: serde_json::Value = serde_json::from_str(data)
+            .map_err(|e| Error::ProviderError(e.to_string()))?;
+
+        if json.get("error").is_some() {
+            return Err(Error::from_error_body(None, &json));
+        }
+
+        // Azure opens with a chunk carrying only the prompt's content filter results.
+        let no_choices = json["choices"].as_array().is_some_and(Vec::is_empty);
+        if no_choices && json.get("prompt_filter_results").is_some() {
+            return Ok(Vec::new());
+        }
+
+        let mut events = Vec::new();
+
+        // Every chunk repeats the id and model; report them once.
+        if !self.started {
+            self.started = true;
+            events.push(StreamEvent::Start {
+                id: json["id"].as_str().map(str::to_string),
+                model: json["model"].as_str().unwrap_or_default().to_string(),
+                provider: None,
+            });
+        }
+
+        let choice = &json["choices"][0];
+        if let Some(text) = choice["delta"]["content"].as_str().filter(|text| !text.is_empty()) {
+            events.push(StreamEvent::TextDelta(text.to_string()));
+        }
+        for call in choice["delta"]["tool_calls"].as_array().into_iter().flatten() {
+            events.push(StreamEvent::ToolCallDelta(ToolCallDelta {
+                index: call["index"].as_u64().unwrap_or_default() as usize,
+                id: call["id"].as_str().map(str::to_string),
+                name: call["function"]["name"].as_str().map(str::to_string),
+                arguments: call["function"]["arguments"].as_str().unwrap_or_default().to_string(),
+            }));
+        }
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            events.push(StreamEvent::Stop(OpenAIProvider::convert_finish_reason(reason)));
+        }
+        // With `include_usage`, the last chunk before `[DONE]` carries the totals and no choices.
+        if let Some(usage) = OpenAIProvider::parse_usage(&json["usage"]) {
+            events.push(StreamEvent::Usage(usage));
+        }
+        Ok(events)
     }
 }
@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Error, Message};
+use super::Provider;
+use super::http::{HttpClient, HttpConfig};
+use super::openai::OpenAIProvider;
+
+/// Which optional Chat Completions features a backend accepts.
+///
+/// Servers that speak the OpenAI protocol (vLLM, LM Studio, llama.cpp, Ollama, Groq, Together,
+/// ...) often reject or ignore parts of it. Every field defaults to `true`; switching one off
+/// leaves the corresponding request field out and reports the option through
+/// [`Provider::unsupported_options`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenAICompatibleQuirks {
+    /// Send `stream_options: {"include_usage": true}` so streams end with token usage.
+    pub stream_options: bool,
+    pub seed: bool,
+    /// Send `presence_penalty` and `frequency_penalty`.
+    pub penalties: bool,
+    pub tools: bool,
+    /// Accept `image_url` content parts. Switch off for text-only models to get
+    /// [`Error::Unsupported`] instead of a server error.
+    pub images: bool,
+    /// Accept PDFs as `file` content parts.
+    pub documents: bool,
+    /// Send `response_format` with a strict JSON Schema.
+    pub response_format: bool,
+}
+
+impl Default for OpenAICompatibleQuirks {
+    fn default() -> Self {
+        Self {
+            stream_options: true,
+            seed: true,
+            penalties: true,
+            tools: true,
+            images: true,
+            documents: true,
+            response_format: true,
+        }
+    }
+}
+
+/// A provider for any server implementing the OpenAI Chat Completions API.
+pub struct OpenAICompatibleProvider {
+    api_key: Option<String>,
+    http: HttpClient,
+    model: String,
+    quirks: OpenAICompatibleQuirks,
+}
+
+impl OpenAICompatibleProvider {
+    /// A provider for `model` served at `base_url`, without authentication.
+    pub fn new(base_url: impl Into<String>, model: impl Into<String>) -> Self {
+        Self::builder(base_url, model)
+            .build()
+            .expect("default OpenAI-compatible configuration is valid")
+    }
+
+    /// `base_url` is the API root the `/chat/completions` and `/models` paths are appended to,
+    /// e.g. `http://localhost:8000/v1`.
+    pub fn builder(base_url: impl Into<String>, model: impl Into<String>) -> OpenAICompatibleProviderBuilder {
+        OpenAICompatibleProviderBuilder {
+            api_key: None,
+            model: model.into(),
+            quirks: OpenAICompatibleQuirks::default(),
+            http: HttpConfig::new(&base_url.into()),
+        }
+    }
+
+    /// Lists the ids of the models the server offers.
+    pub async fn list_models(&self) -> Result<Vec<String>, Error> {
+        OpenAIProvider::fetch_models(self.authorize(self.http.get("/models"))).await
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+}
+
+/// Configures an [`OpenAICompatibleProvider`]; created with [`OpenAICompatibleProvider::builder`].
+pub struct OpenAICompatibleProviderBuilder {
+    api_key: Option<String>,
+    model: String,
+    quirks: OpenAICompatibleQuirks,
+    http: HttpConfig,
+}
+
+impl OpenAICompatibleProviderBuilder {
+    /// Sent as a bearer token. Without one, requests carry no `Authorization` header.
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn quirks(mut self, quirks: OpenAICompatibleQuirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    super::http::http_setters!();
+
+    pub fn build(self) -> Result<OpenAICompatibleProvider, Error> {
+        Ok(OpenAICompatibleProvider {
+            api_key: self.api_key,
+            http: self.http.build()?,
+            model: self.model,
+            quirks: self.quirks,
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAICompatibleProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let messages = vec![Message::user(prompt)];
+        Ok(self.chat(messages, &ChatOptions::default()).await?.content())
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        let body = OpenAIProvider::build_body(&self.model, messages, options, false, &self.quirks)?;
+        OpenAIProvider::send_chat(self.authorize(self.http.post("/chat/completions")).json(&body)).await
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        let body = OpenAIProvider::build_body(&self.model, messages, options, true, &self.quirks)?;
+        OpenAIProvider::send_stream(self.authorize(self.http.post("/chat/completions")).json(&body)).await
+    }
+
+    fn unsupported_options(&self, options: &ChatOptions) -> Vec<&'static str> {
+        let mut unsupported = Vec::new();
+        if !self.quirks.tools && !options.tools.is_empty() {
+            unsupported.push("tools");
+        }
+        if !self.quirks.seed && options.seed.is_some() {
+            unsupported.push("seed");
+        }
+        if !self.quirks.response_format && options.response_format.is_some() {
+            unsupported.push("response_format");
+        }
+        if !self.quirks.penalties {
+            if options.presence_penalty.is_some() {
+                unsupported.push("presence_penalty");
+            }
+            if options.frequency_penalty.is_some() {
+                unsupported.push("frequency_penalty");
+            }
+        }
+        unsupported
+    }
+}
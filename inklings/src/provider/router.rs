@@ -0,0 +1,359 @@
+use async_trait::async_trait;
+use futures::StreamExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use crate::rate_limit::{self, RateLimiter};
+use crate::stream::answered_by;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, Error, Message};
+use super::Provider;
+
+/// Weight given to the latest call when updating a member's average latency.
+const LATENCY_SMOOTHING: f64 = 0.3;
+/// Longest a member is ejected for, whatever the cooldown or the provider's `retry-after` says.
+const MAX_EJECTION: Duration = Duration::from_secs(24 * 3600);
+
+/// How [`RouterProvider`] picks the member that handles a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoutingStrategy {
+    /// Each member in turn.
+    #[default]
+    RoundRobin,
+    /// Each member in turn, as often as its [weight](RouterProvider::weights) says, interleaved
+    /// so that a heavy member does not get its share in one burst.
+    Weighted,
+    /// The member with the fewest requests in progress, streams included until they are dropped.
+    LeastInFlight,
+    /// The member with the lowest average response time. Members not yet measured go first.
+    LowestLatency,
+}
+
+/// Spreads requests across a pool of providers, e.g. several API keys or deployments with
+/// separate rate limits.
+///
+/// A member that fails with a rate-limit or server error is ejected from the pool for a
+/// cooldown, or for as long as the provider's `retry-after` asks if that is longer, and
+/// re-admitted once it has passed. The request that failed is not sent elsewhere; combine the
+/// router with [`Client::with_retry`](crate::Client::with_retry) so the retry goes to another
+/// member. If every member is ejected, the one due back first is used anyway.
+///
+/// ```
+/// # use inklings::provider::{MockProvider, Provider, RouterProvider, RoutingStrategy};
+/// # use std::time::Duration;
+/// let provider = RouterProvider::new(vec![
+///     Box::new(MockProvider::new("first key".to_string())) as Box<dyn Provider + Send + Sync>,
+///     Box::new(MockProvider::new("second key".to_string())),
+/// ])
+/// .names(["key-1", "key-2"])
+/// .strategy(RoutingStrategy::Weighted)
+/// .weights([3, 1])
+/// .cooldown(Duration::from_secs(60));
+/// ```
+pub struct RouterProvider {
+    members: Vec<Arc<Member>>,
+    strategy: RoutingStrategy,
+    cooldown: Duration,
+    eject_if: Arc<dyn Fn(&Error) -> bool + Send + Sync>,
+    next: AtomicUsize,
+    /// Running totals for smooth weighted round-robin, one per member.
+    credits: Mutex<Vec<i64>>,
+}
+
+struct Member {
+    name: String,
+    provider: Box<dyn Provider + Send + Sync>,
+    weight: u32,
+    rate_limiter: Option<RateLimiter>,
+    in_flight: AtomicUsize,
+    health: Mutex<Health>,
+}
+
+#[derive(Default)]
+struct Health {
+    ejected_until: Option<Instant>,
+    latency: Option<Duration>,
+}
+
+impl Member {
+    fn ejected_until(&self, now: Instant) -> Option<Instant> {
+        self.health.lock().unwrap().ejected_until.filter(|until| *until > now)
+    }
+
+    /// Takes the member out of the pool for `cooldown`, or for as long as `error` asks to wait if
+    /// that is longer, up to a day.
+    fn eject(&self, error: &Error, cooldown: Duration) {
+        let cooldown = error.retry_after().map_or(cooldown, |retry_after| retry_after.max(cooldown));
+        self.health.lock().unwrap().ejected_until = Some(Instant::now() + cooldown.min(MAX_EJECTION));
+    }
+
+    fn record_latency(&self, elapsed: Duration) {
+        let mut health = self.health.lock().unwrap();
+        health.latency = Some(match health.latency {
+            Some(average) => average.mul_f64(1.0 - LATENCY_SMOOTHING) + elapsed.mul_f64(LATENCY_SMOOTHING),
+            None => elapsed,
+        });
+    }
+}
+
+/// Times the calls made to a member's provider, leaving out the wait for its rate limiter.
+struct Timed<'a> {
+    provider: &'a (dyn Provider + Send + Sync),
+    started: Mutex<Option<Instant>>,
+}
+
+impl<'a> Timed<'a> {
+    fn new(provider: &'a (dyn Provider + Send + Sync)) -> Self {
+        Self { provider, started: Mutex::new(None) }
+    }
+
+    fn start(&self) {
+        *self.started.lock().unwrap() = Some(Instant::now());
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started.lock().unwrap().map(|started| started.elapsed()).unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Provider for Timed<'_> {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        self.start();
+        self.provider.complete(prompt).await
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        self.start();
+        self.provider.chat(messages, options).await
+    }
+
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        self.start();
+        self.provider.stream_chat(messages, options).await
+    }
+}
+
+/// Counts a request against its member for as long as it is alive.
+struct InFlight(Arc<Member>);
+
+impl InFlight {
+    fn new(member: Arc<Member>) -> Self {
+        member.in_flight.fetch_add(1, Ordering::SeqCst);
+        Self(member)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl RouterProvider {
+    /// Routes round-robin across `providers`, each with weight 1, ejecting members for 30
+    /// seconds. Members are named by their position until [`names`](Self::names) is called.
+    pub fn new(providers: Vec<Box<dyn Provider + Send + Sync>>) -> Self {
+        let members: Vec<_> = providers
+            .into_iter()
+            .enumerate()
+            .map(|(i, provider)| {
+                Arc::new(Member {
+                    name: i.to_string(),
+                    provider,
+                    weight: 1,
+                    rate_limiter: None,
+                    in_flight: AtomicUsize::new(0),
+                    health: Mutex::new(Health::default()),
+                })
+            })
+            .collect();
+        Self {
+            credits: Mutex::new(vec![0; members.len()]),
+            members,
+            strategy: RoutingStrategy::RoundRobin,
+            cooldown: Duration::from_secs(30),
+            eject_if: Arc::new(|e| matches!(e, Error::RateLimited(_) | Error::Overloaded(_) | Error::ServerError(_))),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Names the members, in order, for [`ChatResponse::provider`] and [`ejected`](Self::ejected).
+    pub fn names<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        for (member, name) in self.members.iter_mut().zip(names) {
+            Arc::get_mut(member).expect("members are not shared before the router is used").name = name.into();
+        }
+        self
+    }
+
+    pub fn strategy(mut self, strategy: RoutingStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Relative shares of the members, in order, for [`RoutingStrategy::Weighted`]. A weight of
+    /// zero keeps a member out of rotation unless every other member is ejected.
+    pub fn weights(mut self, weights: impl IntoIterator<Item = u32>) -> Self {
+        for (member, weight) in self.members.iter_mut().zip(weights) {
+            Arc::get_mut(member).expect("members are not shared before the router is used").weight = weight;
+        }
+        self
+    }
+
+    /// Budgets for the members, in order, for members with rate limits of their own such as
+    /// separate API keys. A member waits for its budget once it has been picked.
+    pub fn rate_limiters(mut self, rate_limiters: impl IntoIterator<Item = RateLimiter>) -> Self {
+        for (member, rate_limiter) in self.members.iter_mut().zip(rate_limiters) {
+            Arc::get_mut(member).expect("members are not shared before the router is used").rate_limiter = Some(rate_limiter);
+        }
+        self
+    }
+
+    /// How long an ejected member stays out of the pool. Ejections last a day at most.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Replaces the test deciding which errors eject a member. By default rate limits, overloads
+    /// and server errors do.
+    pub fn eject_if(mut self, eject_if: impl Fn(&Error) -> bool + Send + Sync + 'static) -> Self {
+        self.eject_if = Arc::new(eject_if);
+        self
+    }
+
+    /// Names of the members currently ejected from the pool.
+    pub fn ejected(&self) -> Vec<String> {
+        let now = Instant::now();
+        self.members
+            .iter()
+            .filter(|member| member.ejected_until(now).is_some())
+            .map(|member| member.name.clone())
+            .collect()
+    }
+
+    fn pick(&self) -> Result<Arc<Member>, Error> {
+        let now = Instant::now();
+        let ejected: Vec<_> = self.members.iter().map(|member| member.ejected_until(now)).collect();
+        let mut admitted: Vec<usize> = (0..self.members.len()).filter(|&i| ejected[i].is_none()).collect();
+        if admitted.is_empty() {
+            let soonest = (0..self.members.len()).min_by_key(|&i| ejected[i]);
+            admitted.extend(soonest);
+        }
+        if admitted.is_empty() {
+            return Err(Error::Configuration("RouterProvider has no providers".to_string()));
+        }
+
+        let index = match self.strategy {
+            RoutingStrategy::RoundRobin => admitted[self.next.fetch_add(1, Ordering::Relaxed) % admitted.len()],
+            RoutingStrategy::Weighted => self.pick_weighted(&admitted),
+            RoutingStrategy::LeastInFlight => {
+                *admitted.iter().min_by_key(|&&i| self.members[i].in_flight.load(Ordering::SeqCst)).unwrap()
+            }
+            RoutingStrategy::LowestLatency => {
+                *admitted.iter().min_by_key(|&&i| self.members[i].health.lock().unwrap().latency.unwrap_or_default()).unwrap()
+            }
+        };
+        Ok(self.members[index].clone())
+    }
+
+    /// Smooth weighted round-robin: every admitted member earns its weight in credit, the richest
+    /// is picked and pays back the total.
+    fn pick_weighted(&self, admitted: &[usize]) -> usize {
+        let weighted: Vec<usize> = admitted.iter().copied().filter(|&i| self.members[i].weight > 0).collect();
+        if weighted.is_empty() {
+            return admitted[0];
+        }
+        let mut credits = self.credits.lock().unwrap();
+        let total: i64 = weighted.iter().map(|&i| i64::from(self.members[i].weight)).sum();
+        for &i in &weighted {
+            credits[i] += i64::from(self.members[i].weight);
+        }
+        let chosen = *weighted.iter().max_by_key(|&&i| (credits[i], std::cmp::Reverse(i))).unwrap();
+        credits[chosen] -= total;
+        chosen
+    }
+
+    /// Ejects `member` if `error` calls for it.
+    fn failed(&self, member: &Member, error: &Error) {
+        if (self.eject_if)(error) {
+            member.eject(error, self.cooldown);
+        }
+    }
+}
+
+#[async_trait]
+impl Provider for RouterProvider {
+    async fn complete(&self, prompt: &str) -> Result<String, Error> {
+        let member = self.pick()?;
+        let _in_flight = InFlight::new(member.clone());
+        let timed = Timed::new(&*member.provider);
+        let result = rate_limit::complete(member.rate_limiter.as_ref(), &timed, prompt).await;
+        match &result {
+            Ok(_) => member.record_latency(timed.elapsed()),
+            Err(e) => self.failed(&member, e),
+        }
+        result
+    }
+
+    async fn chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatResponse, Error> {
+        let member = self.pick()?;
+        let _in_flight = InFlight::new(member.clone());
+        let timed = Timed::new(&*member.provider);
+        match rate_limit::chat(member.rate_limiter.as_ref(), &timed, messages, options).await {
+            Ok(mut response) => {
+                member.record_latency(timed.elapsed());
+                response.provider = Some(member.name.clone());
+                Ok(response)
+            }
+            Err(e) => {
+                self.failed(&member, &e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Latency is measured up to the start of the stream, leaving out any wait for the member's
+    /// rate limiter. Errors in the middle of the stream
+    /// eject the member just as errors starting it do. The member is named on
+    /// [`StreamEvent::Start`](crate::types::StreamEvent::Start).
+    async fn stream_chat(&self, messages: Vec<Message>, options: &ChatOptions) -> Result<ChatStream, Error> {
+        let member = self.pick()?;
+        let in_flight = InFlight::new(member.clone());
+        let timed = Timed::new(&*member.provider);
+        let events = match rate_limit::stream_chat(member.rate_limiter.as_ref(), &timed, messages, options).await {
+            Ok(events) => events,
+            Err(e) => {
+                self.failed(&member, &e);
+                return Err(e);
+            }
+        };
+        member.record_latency(timed.elapsed());
+
+        let eject_if = self.eject_if.clone();
+        let cooldown = self.cooldown;
+        Ok(answered_by(events, &member.name)
+            .inspect(move |event| {
+                let InFlight(member) = &in_flight;
+                if let Err(e) = event {
+                    if eject_if(e) {
+                        member.eject(e, cooldown);
+                    }
+                }
+            })
+            .boxed())
+    }
+
+    /// Options that any of the members would ignore, since any of them may end up answering.
+    fn unsupported_options(&self, options: &ChatOptions) -> Vec<&'static str> {
+        let mut unsupported = Vec::new();
+        for member in &self.members {
+            for option in member.provider.unsupported_options(options) {
+                if !unsupported.contains(&option) {
+                    unsupported.push(option);
+                }
+            }
+        }
+        unsupported
+    }
+}
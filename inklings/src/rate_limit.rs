@@ -0,0 +1,482 @@
+//! Client-side rate limiting against request and token budgets.
+
+use reqwest::header::HeaderMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+use futures::StreamExt;
+use crate::provider::Provider;
+use crate::types::{ChatOptions, ChatResponse, ChatStream, ContentPart, Error, Message, StreamEvent, Usage};
+
+/// Rough number of characters per token in English text and code.
+const CHARS_PER_TOKEN: usize = 4;
+/// Tokens for the role and framing of each message.
+const MESSAGE_TOKENS: u32 = 4;
+/// Tokens for an image or document, whose real cost depends on its size.
+const MEDIA_TOKENS: u32 = 1_000;
+/// Longest the queue is paused for a reset or `retry-after`, however far off the provider says.
+const MAX_PAUSE: Duration = Duration::from_secs(3600);
+
+/// Keeps a [`Client`](crate::Client) within a provider's requests-per-minute and
+/// tokens-per-minute limits by queueing calls until the budget allows them, instead of letting
+/// them fail with [`Error::RateLimited`].
+///
+/// Budgets refill continuously, so a limit of 600 requests per minute lets a request through
+/// every 100ms once the initial burst is spent. Calls wait their turn in the order they arrived.
+/// Input tokens are [estimated](estimate_tokens) before sending and the charge corrected to the
+/// reported usage afterwards. Clones share the same budget, so one limiter per API key and model
+/// can be handed to every client using them. The members of a
+/// [`RouterProvider`](crate::provider::RouterProvider) or
+/// [`FallbackProvider`](crate::provider::FallbackProvider) each have limits of their own; give
+/// them a limiter each with `rate_limiters` rather than one to the client.
+///
+/// The limits reported in OpenAI's `x-ratelimit-*` and Anthropic's `anthropic-ratelimit-*`
+/// response headers are applied too: a lower limit replaces the configured one, the remaining
+/// budget is never taken to be more than the provider says, and an exhausted budget pauses the
+/// queue until it resets. A rate-limit error with a `retry-after` delay pauses it as well. Pauses
+/// last an hour at most.
+/// Headers are only available for [`chat`](crate::Client::chat) responses; streams settle their
+/// token charge from the usage they report.
+///
+/// ```
+/// # use inklings::rate_limit::RateLimiter;
+/// let limiter = RateLimiter::new()
+///     .requests_per_minute(500)
+///     .tokens_per_minute(30_000);
+/// ```
+#[derive(Clone)]
+pub struct RateLimiter {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// Callers wait here in arrival order; tokio's mutex is fair.
+    queue: tokio::sync::Mutex<()>,
+    budget: Mutex<Budget>,
+}
+
+#[derive(Debug)]
+struct Budget {
+    requests: Bucket,
+    tokens: Bucket,
+    paused_until: Option<Instant>,
+    adapt_to_headers: bool,
+}
+
+/// A per-minute budget that refills continuously.
+#[derive(Debug)]
+struct Bucket {
+    configured: Option<u64>,
+    /// The limit the provider last reported.
+    reported: Option<u64>,
+    /// May go negative when a call turns out to cost more than was charged for it.
+    available: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new() -> Self {
+        Self { configured: None, reported: None, available: 0.0, updated: Instant::now() }
+    }
+
+    fn limit(&self) -> Option<f64> {
+        let limit = match (self.configured, self.reported) {
+            (Some(configured), Some(reported)) => Some(configured.min(reported)),
+            (configured, reported) => configured.or(reported),
+        };
+        limit.map(|limit| limit as f64)
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if let Some(limit) = self.limit() {
+            let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+            self.available = (self.available + limit * elapsed / 60.0).min(limit);
+        }
+        self.updated = now;
+    }
+
+    /// How long until `amount` is available. Amounts above the limit only wait for a full bucket.
+    fn wait(&self, amount: f64) -> Duration {
+        match self.limit() {
+            Some(limit) if limit > 0.0 => {
+                let missing = amount.min(limit) - self.available;
+                Duration::from_secs_f64(missing.max(0.0) * 60.0 / limit)
+            }
+            Some(_) => Duration::from_secs(60),
+            None => Duration::ZERO,
+        }
+    }
+
+    fn configure(&mut self, limit: u64) {
+        self.configured = Some(limit);
+        self.available = limit as f64;
+    }
+
+    /// Applies what the provider reported, after a [`refill`](Self::refill).
+    fn observe(&mut self, limit: Option<u64>, remaining: Option<u64>) {
+        if limit.is_some() {
+            let was_unlimited = self.limit().is_none();
+            self.reported = limit;
+            if was_unlimited {
+                self.available = self.limit().unwrap_or_default();
+            }
+        }
+        if let (Some(remaining), Some(_)) = (remaining, self.limit()) {
+            self.available = self.available.min(remaining as f64);
+        }
+    }
+}
+
+impl Budget {
+    /// Takes one request and `tokens` from the budget, or says how long to wait before asking
+    /// again.
+    fn take(&mut self, tokens: u32) -> Result<(), Duration> {
+        let now = Instant::now();
+        if let Some(paused_until) = self.paused_until.filter(|until| *until > now) {
+            return Err(paused_until - now);
+        }
+        self.requests.refill(now);
+        self.tokens.refill(now);
+        let wait = self.requests.wait(1.0).max(self.tokens.wait(f64::from(tokens)));
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        self.requests.available -= 1.0;
+        self.tokens.available -= f64::from(tokens);
+        Ok(())
+    }
+
+    fn pause(&mut self, duration: Duration) {
+        let until = Instant::now() + duration.min(MAX_PAUSE);
+        self.paused_until = Some(self.paused_until.map_or(until, |paused_until| paused_until.max(until)));
+    }
+}
+
+impl RateLimiter {
+    /// A limiter without budgets of its own, which only follows the limits the provider reports.
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                queue: tokio::sync::Mutex::new(()),
+                budget: Mutex::new(Budget {
+                    requests: Bucket::new(),
+                    tokens: Bucket::new(),
+                    paused_until: None,
+                    adapt_to_headers: true,
+                }),
+            }),
+        }
+    }
+
+    pub fn requests_per_minute(self, requests_per_minute: u32) -> Self {
+        self.shared.budget.lock().unwrap().requests.configure(u64::from(requests_per_minute));
+        self
+    }
+
+    /// Budget for input and output tokens together.
+    pub fn tokens_per_minute(self, tokens_per_minute: u32) -> Self {
+        self.shared.budget.lock().unwrap().tokens.configure(u64::from(tokens_per_minute));
+        self
+    }
+
+    /// Whether to follow the limits reported in response headers. Defaults to true.
+    pub fn adapt_to_headers(self, adapt_to_headers: bool) -> Self {
+        self.shared.budget.lock().unwrap().adapt_to_headers = adapt_to_headers;
+        self
+    }
+
+    /// Waits until the budget allows a request estimated at `tokens` and charges for it.
+    async fn acquire(&self, tokens: u32) {
+        let _turn = self.shared.queue.lock().await;
+        loop {
+            let taken = self.shared.budget.lock().unwrap().take(tokens);
+            match taken {
+                Ok(()) => return,
+                Err(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+
+    /// Corrects the `charged` token estimate to the `usage` the provider reported.
+    fn settle(&self, charged: u32, usage: Usage) {
+        let used = usage.input_tokens.saturating_add(usage.output_tokens);
+        let mut budget = self.shared.budget.lock().unwrap();
+        let tokens = &mut budget.tokens;
+        tokens.available += f64::from(charged) - f64::from(used);
+        if let Some(limit) = tokens.limit() {
+            tokens.available = tokens.available.min(limit);
+        }
+    }
+
+    /// Settles a call charged `charged` tokens that returned `response`, and adapts to the
+    /// limits it reported.
+    fn record_response(&self, charged: u32, response: &ChatResponse) {
+        if let Some(usage) = response.usage {
+            self.settle(charged, usage);
+        }
+        let Some(status) = &response.rate_limit else {
+            return;
+        };
+        let mut budget = self.shared.budget.lock().unwrap();
+        if !budget.adapt_to_headers {
+            return;
+        }
+        let now = Instant::now();
+        budget.requests.refill(now);
+        budget.tokens.refill(now);
+        budget.requests.observe(status.requests_limit, status.requests_remaining);
+        budget.tokens.observe(status.tokens_limit, status.tokens_remaining);
+        for (remaining, reset) in [
+            (status.requests_remaining, status.requests_reset),
+            (status.tokens_remaining, status.tokens_reset),
+        ] {
+            if let (Some(0), Some(reset)) = (remaining, reset) {
+                budget.pause(reset);
+            }
+        }
+    }
+
+    /// Refunds a call charged `charged` tokens that failed with `error`, pausing the queue if the
+    /// provider asked to wait.
+    fn record_error(&self, charged: u32, error: &Error) {
+        self.settle(charged, Usage::default());
+        if let (Error::RateLimited(_), Some(retry_after)) = (error, error.retry_after()) {
+            self.shared.budget.lock().unwrap().pause(retry_after);
+        }
+    }
+}
+
+/// Sends `prompt` to `provider`, once `rate_limiter`'s budget allows it if there is one. The
+/// estimate stays charged, as completions report no usage.
+pub(crate) async fn complete<P>(rate_limiter: Option<&RateLimiter>, provider: &P, prompt: &str) -> Result<String, Error>
+where
+    P: Provider + Send + Sync + ?Sized,
+{
+    let Some(rate_limiter) = rate_limiter else {
+        return provider.complete(prompt).await;
+    };
+    let estimate = estimate_tokens(&[Message::user(prompt)], &ChatOptions::default());
+    rate_limiter.acquire(estimate).await;
+    let result = provider.complete(prompt).await;
+    if let Err(e) = &result {
+        rate_limiter.record_error(estimate, e);
+    }
+    result
+}
+
+/// Sends `messages` to `provider`, once `rate_limiter`'s budget allows it if there is one, and
+/// settles the charge.
+pub(crate) async fn chat<P>(
+    rate_limiter: Option<&RateLimiter>,
+    provider: &P,
+    messages: Vec<Message>,
+    options: &ChatOptions,
+) -> Result<ChatResponse, Error>
+where
+    P: Provider + Send + Sync + ?Sized,
+{
+    let Some(rate_limiter) = rate_limiter else {
+        return provider.chat(messages, options).await;
+    };
+    let estimate = estimate_tokens(&messages, options);
+    rate_limiter.acquire(estimate).await;
+    let result = provider.chat(messages, options).await;
+    match &result {
+        Ok(response) => rate_limiter.record_response(estimate, response),
+        Err(e) => rate_limiter.record_error(estimate, e),
+    }
+    result
+}
+
+/// Starts a stream from `provider`, once `rate_limiter`'s budget allows it if there is one, and
+/// settles the charge as usage is reported.
+pub(crate) async fn stream_chat<P>(
+    rate_limiter: Option<&RateLimiter>,
+    provider: &P,
+    messages: Vec<Message>,
+    options: &ChatOptions,
+) -> Result<ChatStream, Error>
+where
+    P: Provider + Send + Sync + ?Sized,
+{
+    let Some(rate_limiter) = rate_limiter else {
+        return provider.stream_chat(messages, options).await;
+    };
+    let estimate = estimate_tokens(&messages, options);
+    rate_limiter.acquire(estimate).await;
+    let events = match provider.stream_chat(messages, options).await {
+        Ok(events) => events,
+        Err(e) => {
+            rate_limiter.record_error(estimate, &e);
+            return Err(e);
+        }
+    };
+
+    // Usage events report running totals, so each one settles the difference from the last.
+    let rate_limiter = rate_limiter.clone();
+    let mut charged = estimate;
+    Ok(events
+        .inspect(move |event| {
+            if let Ok(StreamEvent::Usage(usage)) = event {
+                rate_limiter.settle(charged, *usage);
+                charged = usage.input_tokens.saturating_add(usage.output_tokens);
+            }
+        })
+        .boxed())
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let budget = self.shared.budget.lock().unwrap();
+        f.debug_struct("RateLimiter")
+            .field("requests_per_minute", &budget.requests.limit())
+            .field("tokens_per_minute", &budget.tokens.limit())
+            .field("adapt_to_headers", &budget.adapt_to_headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Estimates the input tokens of a request from the length of its text, for budgeting before
+/// the provider reports the real count. Images and documents are counted at a flat rate.
+pub fn estimate_tokens(messages: &[Message], options: &ChatOptions) -> u32 {
+    let mut chars = 0;
+    let mut tokens = 0;
+    for message in messages {
+        tokens += MESSAGE_TOKENS;
+        for part in &message.content {
+            match part {
+                ContentPart::Text(text) => chars += text.len(),
+                ContentPart::Image(_) | ContentPart::Document { .. } => tokens += MEDIA_TOKENS,
+            }
+        }
+        for call in &message.tool_calls {
+            chars += call.name.len() + call.arguments.to_string().len();
+        }
+        for result in &message.tool_results {
+            chars += result.content.len();
+        }
+    }
+    for tool in &options.tools {
+        chars += tool.name.len() + tool.description.len() + tool.parameters.to_string().len();
+    }
+    tokens.saturating_add(chars.div_ceil(CHARS_PER_TOKEN).try_into().unwrap_or(u32::MAX))
+}
+
+/// Rate limits reported in a provider's response headers: OpenAI's `x-ratelimit-*` and
+/// Anthropic's `anthropic-ratelimit-*`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub requests_limit: Option<u64>,
+    pub requests_remaining: Option<u64>,
+    /// Time until the request budget is back to its limit.
+    pub requests_reset: Option<Duration>,
+    pub tokens_limit: Option<u64>,
+    pub tokens_remaining: Option<u64>,
+    pub tokens_reset: Option<Duration>,
+}
+
+impl RateLimitStatus {
+    /// Reads the rate-limit headers, if the response has any.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::trim);
+        let number = |name: &str| header(name).and_then(|value| value.parse().ok());
+
+        // OpenAI reports resets as durations such as `6m0s`, Anthropic as RFC 3339 timestamps.
+        let openai = Self {
+            requests_limit: number("x-ratelimit-limit-requests"),
+            requests_remaining: number("x-ratelimit-remaining-requests"),
+            requests_reset: header("x-ratelimit-reset-requests").and_then(parse_duration),
+            tokens_limit: number("x-ratelimit-limit-tokens"),
+            tokens_remaining: number("x-ratelimit-remaining-tokens"),
+            tokens_reset: header("x-ratelimit-reset-tokens").and_then(parse_duration),
+        };
+        let reset = |name: &str| {
+            header(name)
+                .and_then(parse_timestamp)
+                .map(|reset| reset.duration_since(SystemTime::now()).unwrap_or_default())
+        };
+        let anthropic = Self {
+            requests_limit: number("anthropic-ratelimit-requests-limit"),
+            requests_remaining: number("anthropic-ratelimit-requests-remaining"),
+            requests_reset: reset("anthropic-ratelimit-requests-reset"),
+            tokens_limit: number("anthropic-ratelimit-tokens-limit"),
+            tokens_remaining: number("anthropic-ratelimit-tokens-remaining"),
+            tokens_reset: reset("anthropic-ratelimit-tokens-reset"),
+        };
+        [openai, anthropic].into_iter().find(|status| *status != Self::default())
+    }
+}
+
+/// Parses a duration such as `1s`, `6m0s`, `1h30m` or `20ms`.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut seconds = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let unit_end = rest[number_end..]
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .map_or(rest.len(), |end| number_end + end);
+        let value: f64 = rest[..number_end].parse().ok()?;
+        seconds += value * match &rest[number_end..unit_end] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        rest = &rest[unit_end..];
+    }
+    if text.is_empty() {
+        return None;
+    }
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Parses an RFC 3339 timestamp such as `2024-06-01T12:00:30Z` or `2024-06-01T14:00:30.5+02:00`.
+fn parse_timestamp(text: &str) -> Option<SystemTime> {
+    let (date, time) = text.split_once(['T', 't', ' '])?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (time, zone) = time.split_at(time.find(['Z', 'z', '+', '-'])?);
+    let mut time = time.splitn(3, ':');
+    let (hour, minute) = (time.next()?.parse::<i64>().ok()?, time.next()?.parse::<i64>().ok()?);
+    let second: f64 = time.next()?.parse().ok()?;
+    let offset = match zone {
+        "Z" | "z" => 0,
+        _ => {
+            let (hours, minutes) = zone[1..].split_once(':')?;
+            let (hours, minutes) = (hours.parse::<i64>().ok()?, minutes.parse::<i64>().ok()?);
+            if !(0..=23).contains(&hours) || !(0..=59).contains(&minutes) {
+                return None;
+            }
+            let offset = hours * 3600 + minutes * 60;
+            if zone.starts_with('-') { -offset } else { offset }
+        }
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || !(0..=23).contains(&hour) || !(0..=59).contains(&minute)
+        || !(0.0..61.0).contains(&second)
+    {
+        return None;
+    }
+
+    // Converts a civil date to days since 1970-01-01 (Howard Hinnant's algorithm).
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era.checked_mul(146_097)?.checked_add(day_of_era - 719_468)?;
+
+    let seconds = days.checked_mul(86_400)?.checked_add(hour * 3600 + minute * 60 - offset)? as f64 + second;
+    if seconds < 0.0 {
+        return None;
+    }
+    UNIX_EPOCH.checked_add(Duration::try_from_secs_f64(seconds).ok()?)
+}
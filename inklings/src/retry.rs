@@ -0,0 +1,150 @@
+//! Retrying transient provider failures.
+
+use rand::Rng;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::types::Error;
+
+/// How [`Client`](crate::Client) retries failed requests.
+///
+/// The delay before retry `n` is `initial_backoff * multiplier^(n - 1)`, capped at
+/// `max_backoff` and reduced by up to `jitter` of itself at random so that many clients do not
+/// retry in lockstep. When the provider says how long to wait (`retry-after` or
+/// `retry-after-ms`), that delay is used instead, up to `max_retry_after`.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_retry_after: Duration,
+    multiplier: f64,
+    jitter: f64,
+    respect_retry_after: bool,
+    retry_if: Arc<dyn Fn(&Error) -> bool + Send + Sync>,
+}
+
+impl RetryPolicy {
+    /// A policy that makes up to `max_attempts` attempts in total, retrying errors for which
+    /// [`Error::is_retryable`] is true.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_retry_after: Duration::from_secs(300),
+            multiplier: 2.0,
+            jitter: 0.2,
+            respect_retry_after: true,
+            retry_if: Arc::new(Error::is_retryable),
+        }
+    }
+
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self::new(1)
+    }
+
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Longest wait a provider's `retry-after` is honoured for. Defaults to five minutes.
+    pub fn max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Factor each delay grows by over the one before. Values below 1 are taken as 1.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Fraction of each delay, between 0 and 1, that may be randomly shaved off.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = if jitter.is_nan() { 0.0 } else { jitter.clamp(0.0, 1.0) };
+        self
+    }
+
+    pub fn respect_retry_after(mut self, respect_retry_after: bool) -> Self {
+        self.respect_retry_after = respect_retry_after;
+        self
+    }
+
+    /// Replaces the test deciding which errors are worth retrying.
+    pub fn retry_if(mut self, retry_if: impl Fn(&Error) -> bool + Send + Sync + 'static) -> Self {
+        self.retry_if = Arc::new(retry_if);
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// The delay before retrying after `error` failed attempt number `attempt` (starting at 1).
+    pub fn delay(&self, attempt: u32, error: &Error) -> Duration {
+        if self.respect_retry_after {
+            if let Some(retry_after) = error.retry_after() {
+                return retry_after.min(self.max_retry_after);
+            }
+        }
+
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+        let jitter = if self.jitter > 0.0 {
+            rand::thread_rng().gen_range(0.0..self.jitter)
+        } else {
+            0.0
+        };
+        Duration::try_from_secs_f64(backoff * (1.0 - jitter)).map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+
+    /// Runs `operation` until it succeeds, fails with an error that should not be retried, or
+    /// the attempts run out.
+    pub(crate) async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Err(e) if attempt < self.max_attempts && (self.retry_if)(&e) => {
+                    tokio::time::sleep(self.delay(attempt, &e)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts with exponential backoff starting at 500ms.
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("max_retry_after", &self.max_retry_after)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .field("respect_retry_after", &self.respect_retry_after)
+            .finish_non_exhaustive()
+    }
+}
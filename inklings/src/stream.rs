@@ -0,0 +1,119 @@
+//! Helpers for consuming [`ChatStream`]s.
+
+use futures::{Stream, StreamExt};
+use std::collections::BTreeMap;
+use crate::types::{ChatResponse, ChatStream, Error, FinishReason, Message, StreamEvent, ToolCall, Usage};
+
+/// Folds [`StreamEvent`]s into a [`ChatResponse`], for callers that want to render a stream as
+/// it arrives and still end up with the complete reply.
+#[derive(Debug, Default)]
+pub struct StreamAccumulator {
+    id: Option<String>,
+    model: String,
+    provider: Option<String>,
+    content: String,
+    tool_calls: BTreeMap<usize, ToolCall>,
+    tool_arguments: BTreeMap<usize, String>,
+    usage: Option<Usage>,
+    finish_reason: Option<FinishReason>,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: &StreamEvent) {
+        match event {
+            StreamEvent::Start { id, model, provider } => {
+                self.id = id.clone();
+                self.model = model.clone();
+                self.provider = provider.clone();
+            }
+            StreamEvent::TextDelta(text) => self.content.push_str(text),
+            StreamEvent::ToolCallDelta(delta) => {
+                let call = self.tool_calls.entry(delta.index).or_insert_with(|| ToolCall {
+                    id: String::new(),
+                    name: String::new(),
+                    arguments: serde_json::Value::Null,
+                });
+                if let Some(id) = &delta.id {
+                    call.id = id.clone();
+                }
+                if let Some(name) = &delta.name {
+                    call.name.push_str(name);
+                }
+                self.tool_arguments.entry(delta.index).or_default().push_str(&delta.arguments);
+            }
+            StreamEvent::Usage(usage) => self.usage = Some(*usage),
+            StreamEvent::Stop(reason) => self.finish_reason = Some(reason.clone()),
+            StreamEvent::Done => {}
+        }
+    }
+
+    pub fn finish(mut self) -> ChatResponse {
+        let tool_calls = std::mem::take(&mut self.tool_calls)
+            .into_iter()
+            .map(|(index, mut call)| {
+                let arguments = self.tool_arguments.remove(&index).unwrap_or_default();
+                call.arguments = if arguments.is_empty() {
+                    serde_json::json!({})
+                } else {
+                    serde_json::from_str(&arguments).unwrap_or(serde_json::Value::String(arguments))
+                };
+                call
+            })
+            .collect();
+
+        ChatResponse {
+            id: self.id,
+            model: self.model,
+            provider: self.provider,
+            message: Message {
+                tool_calls,
+                ..Message::assistant(self.content)
+            },
+            usage: self.usage,
+            finish_reason: self.finish_reason.unwrap_or(FinishReason::Stop),
+            rate_limit: None,
+            raw: serde_json::Value::Null,
+        }
+    }
+}
+
+/// Drains `stream` and returns the complete response.
+pub async fn collect(mut stream: ChatStream) -> Result<ChatResponse, Error> {
+    let mut accumulator = StreamAccumulator::new();
+    while let Some(event) = stream.next().await {
+        accumulator.push(&event?);
+    }
+    Ok(accumulator.finish())
+}
+
+/// Names `provider` as the one answering in the [`StreamEvent::Start`] events of `stream`.
+pub(crate) fn answered_by(stream: ChatStream, provider: &str) -> ChatStream {
+    let provider = provider.to_string();
+    stream
+        .map(move |event| match event {
+            Ok(StreamEvent::Start { id, model, .. }) => Ok(StreamEvent::Start { id, model, provider: Some(provider.clone()) }),
+            event => event,
+        })
+        .boxed()
+}
+
+/// Ends `stream` after its first error and turns an end without [`StreamEvent::Done`] into
+/// [`Error::StreamTruncated`], so a dropped connection is never mistaken for a complete reply.
+pub fn guard(stream: impl Stream<Item = Result<StreamEvent, Error>> + Send + 'static) -> ChatStream {
+    Box::pin(futures::stream::unfold((Box::pin(stream), false), |(mut stream, finished)| async move {
+        if finished {
+            return None;
+        }
+        let item = match stream.next().await {
+            Some(Ok(StreamEvent::Done)) => return Some((Ok(StreamEvent::Done), (stream, true))),
+            Some(Ok(event)) => return Some((Ok(event), (stream, false))),
+            Some(Err(e)) => Err(e),
+            None => Err(Error::StreamTruncated),
+        };
+        Some((item, (stream, true)))
+    }))
+}
@@ -0,0 +1,145 @@
+//! Structured output: JSON Schemas generated from Rust types and validation of the replies.
+//!
+//! [`Client::chat_structured`](crate::Client::chat_structured) is the usual entry point;
+//! [`response_format`] is exposed for callers that want to set
+//! [`ChatOptions::response_format`](crate::types::ChatOptions::response_format) themselves.
+
+use jsonschema::JSONSchema;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde_json::{json, Value};
+use crate::types::{ChatResponse, ResponseFormat};
+
+/// Property the reply is wrapped in when `T` is not an object, since providers only accept
+/// object schemas.
+const WRAPPER_PROPERTY: &str = "value";
+
+/// The schema for `T`, rewritten for OpenAI's strict mode.
+pub fn response_format<T: JsonSchema>() -> ResponseFormat {
+    Schema::new::<T>().format
+}
+
+/// A response format together with what is needed to check replies against it.
+pub(crate) struct Schema {
+    pub format: ResponseFormat,
+    /// Whether the schema of `T` was wrapped in an object under [`WRAPPER_PROPERTY`].
+    wrapped: bool,
+    validator: JSONSchema,
+}
+
+impl Schema {
+    pub fn new<T: JsonSchema>() -> Self {
+        let settings = SchemaSettings::draft07().with(|settings| {
+            // Strict mode has limited `$ref` support, so only recursive types use definitions.
+            settings.inline_subschemas = true;
+            settings.meta_schema = None;
+        });
+        let root = settings.into_generator().into_root_schema_for::<T>();
+        let mut schema = serde_json::to_value(root).expect("schemas serialise to JSON");
+        let object = schema.as_object_mut().expect("schemas are objects");
+        object.remove("title");
+
+        let wrapped = object.get("type") != Some(&json!("object"));
+        if wrapped {
+            let definitions = object.remove("definitions");
+            schema = json!({
+                "type": "object",
+                "properties": { WRAPPER_PROPERTY: schema },
+            });
+            if let Some(definitions) = definitions {
+                schema["definitions"] = definitions;
+            }
+        }
+        make_strict(&mut schema);
+
+        let validator = JSONSchema::compile(&schema).expect("generated schemas are valid");
+        Self {
+            format: ResponseFormat::new(schema_name(&T::schema_name()), schema),
+            wrapped,
+            validator,
+        }
+    }
+
+    /// The JSON the model answered with: the arguments of the forced tool call, or else the
+    /// text of the reply with any Markdown code fence removed.
+    pub fn extract(&self, response: &ChatResponse) -> (String, Result<Value, Vec<String>>) {
+        if let Some(call) = response.tool_calls().iter().find(|call| call.name == self.format.name) {
+            return (call.arguments.to_string(), Ok(call.arguments.clone()));
+        }
+        let text = response.content();
+        let json = strip_code_fence(&text);
+        let value = serde_json::from_str(json).map_err(|e| vec![format!("reply is not valid JSON: {}", e)]);
+        (text, value)
+    }
+
+    /// Checks `value` against the schema and unwraps it if necessary.
+    pub fn validate(&self, mut value: Value) -> Result<Value, Vec<String>> {
+        if let Err(errors) = self.validator.validate(&value) {
+            return Err(errors
+                .map(|error| match error.instance_path.to_string() {
+                    path if path.is_empty() => error.to_string(),
+                    path => format!("{}: {}", path, error),
+                })
+                .collect());
+        }
+        if self.wrapped {
+            value = value[WRAPPER_PROPERTY].take();
+        }
+        Ok(value)
+    }
+}
+
+/// Restricts a schema name to the characters and length OpenAI accepts.
+fn schema_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .take(64)
+        .collect()
+}
+
+/// Strict mode needs every property listed as required (optional ones are nullable instead) and
+/// `additionalProperties: false`, and rejects schemars' numeric `format`s. Required properties are
+/// listed in sorted order, whatever order the map keeps its keys in.
+fn make_strict(schema: &mut Value) {
+    let Some(object) = schema.as_object_mut() else {
+        return;
+    };
+    if let Some(Value::Object(properties)) = object.get_mut("properties") {
+        let mut required: Vec<String> = properties.keys().cloned().collect();
+        required.sort();
+        properties.values_mut().for_each(make_strict);
+        object.insert("required".to_string(), required.into());
+        object.insert("additionalProperties".to_string(), false.into());
+    }
+    let numeric_format = object.get("format").and_then(Value::as_str).is_some_and(|format| {
+        ["int", "uint", "float", "double"].iter().any(|prefix| format.starts_with(prefix))
+    });
+    if numeric_format {
+        object.remove("format");
+    }
+    if let Some(Value::Object(definitions)) = object.get_mut("definitions") {
+        definitions.values_mut().for_each(make_strict);
+    }
+    for keyword in ["items", "additionalProperties", "not"] {
+        if let Some(subschema) = object.get_mut(keyword) {
+            match subschema {
+                Value::Array(items) => items.iter_mut().for_each(make_strict),
+                subschema => make_strict(subschema),
+            }
+        }
+    }
+    for keyword in ["anyOf", "oneOf", "allOf"] {
+        if let Some(Value::Array(subschemas)) = object.get_mut(keyword) {
+            subschemas.iter_mut().for_each(make_strict);
+        }
+    }
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let text = text.trim();
+    match text.strip_prefix("```").and_then(|rest| rest.strip_suffix("```")) {
+        // Drop the language tag on the opening line.
+        Some(fenced) => fenced.split_once('\n').map_or(fenced, |(_, body)| body).trim(),
+        None => text,
+    }
+}
@@ -0,0 +1,644 @@
+//! Test support: an in-process HTTP server that emulates the OpenAI and Anthropic chat APIs, so
+//! the real providers, including their request building and stream parsing, can be exercised
+//! end to end without network access. Raw JSON, SSE and byte bodies are served on any path, for
+//! the providers it does not emulate. Enabled by the `testing` feature.
+//!
+//! ```no_run
+//! # async fn example() {
+//! use inklings::{Client, testing::{TestResponse, TestServer}, types::Message};
+//!
+//! let server = TestServer::start().await;
+//! server.enqueue(TestResponse::text("Hello there").chunk_size(5));
+//! let client = Client::new(Box::new(server.openai()));
+//! let reply = client.chat(vec![Message::user("Hi")]).await.unwrap();
+//! assert_eq!(reply.content(), "Hello there");
+//! assert_eq!(server.last_request().unwrap().json()["messages"][0]["content"], "Hi");
+//! # }
+//! ```
+
+use futures::future::FutureExt;
+use futures::stream::{self, StreamExt};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::provider::loopback::{Handler, LoopbackRequest, LoopbackResponse, LoopbackServer};
+use crate::provider::{AnthropicProvider, OpenAIProvider};
+use crate::types::{FinishReason, ToolCall, Usage};
+
+/// Serves queued [`TestResponse`]s in the wire format of the endpoint that was called:
+/// `…/chat/completions` is answered like OpenAI and `…/messages` like Anthropic, as JSON or as
+/// an SSE stream depending on the request's `stream` flag. Raw bodies such as
+/// [`TestResponse::json`] are served as they are on any path.
+///
+/// Every request is recorded for later assertions. Once the queue is empty the fallback
+/// response is served, which by default is a 500 error.
+pub struct TestServer {
+    server: LoopbackServer,
+    state: Arc<State>,
+}
+
+struct State {
+    queue: Mutex<VecDeque<TestResponse>>,
+    fallback: Mutex<TestResponse>,
+    requests: Mutex<Vec<TestRequest>>,
+    served: AtomicUsize,
+}
+
+impl TestServer {
+    /// Starts a server on an ephemeral local port.
+    ///
+    /// # Panics
+    ///
+    /// If no local port can be bound.
+    pub async fn start() -> Self {
+        let state = Arc::new(State {
+            queue: Mutex::new(VecDeque::new()),
+            fallback: Mutex::new(TestResponse::error(500, "TestServer has no response queued for this request")),
+            requests: Mutex::new(Vec::new()),
+            served: AtomicUsize::new(0),
+        });
+        let handler: Handler = {
+            let state = state.clone();
+            Arc::new(move |request| state.clone().respond(request).boxed())
+        };
+        let server = LoopbackServer::start(handler).await.expect("could not start TestServer");
+        Self { server, state }
+    }
+
+    /// The base URL to configure providers with.
+    pub fn url(&self) -> &str {
+        self.server.url()
+    }
+
+    /// An [`OpenAIProvider`] that talks to this server.
+    pub fn openai(&self) -> OpenAIProvider {
+        OpenAIProvider::builder("test-key").base_url(self.url()).build().expect("valid test configuration")
+    }
+
+    /// An [`AnthropicProvider`] that talks to this server.
+    pub fn anthropic(&self) -> AnthropicProvider {
+        AnthropicProvider::builder("test-key").base_url(self.url()).build().expect("valid test configuration")
+    }
+
+    /// Adds `response` to the end of the queue.
+    pub fn enqueue(&self, response: TestResponse) {
+        self.state.queue.lock().unwrap().push_back(response);
+    }
+
+    /// Sets the response served once the queue is empty.
+    pub fn fallback(&self, response: TestResponse) {
+        *self.state.fallback.lock().unwrap() = response;
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<TestRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// The most recent request, if any has been received.
+    pub fn last_request(&self) -> Option<TestRequest> {
+        self.state.requests.lock().unwrap().last().cloned()
+    }
+}
+
+/// A request received by a [`TestServer`].
+#[derive(Debug, Clone)]
+pub struct TestRequest {
+    pub method: String,
+    /// The path and query string.
+    pub path: String,
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl TestRequest {
+    /// The value of the header `name`, whatever its case, if it was sent.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_lowercase();
+        self.headers.iter().find(|(n, _)| *n == name).map(|(_, value)| value.as_str())
+    }
+
+    /// The body parsed as JSON, or `Value::Null` if it is not JSON.
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap_or_default()
+    }
+}
+
+/// A scripted answer of a [`TestServer`], together with how it is delivered.
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    body: Body,
+    headers: Vec<(String, String)>,
+    delay: Duration,
+    chunk_size: Option<usize>,
+    chunk_delay: Duration,
+    stall_after: Option<usize>,
+    cut_after: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Body {
+    /// A reply rendered in the dialect of the endpoint.
+    Reply { text: String, tool_calls: Vec<ToolCall>, usage: Usage, finish_reason: Option<FinishReason> },
+    /// An error rendered in the dialect of the endpoint.
+    Error { status: u16, error_type: Option<String>, message: String },
+    Json { status: u16, body: Value },
+    Sse(Vec<(String, String)>),
+    Raw { status: u16, content_type: String, chunks: Vec<Vec<u8>> },
+}
+
+impl TestResponse {
+    fn new(body: Body) -> Self {
+        Self {
+            body,
+            headers: Vec::new(),
+            delay: Duration::ZERO,
+            chunk_size: None,
+            chunk_delay: Duration::ZERO,
+            stall_after: None,
+            cut_after: None,
+        }
+    }
+
+    /// A reply with `text`, streamed one word per event. Reports 10 input and 5 output tokens
+    /// unless [`usage`](Self::usage) says otherwise.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::new(Body::Reply {
+            text: text.into(),
+            tool_calls: Vec::new(),
+            usage: Usage { input_tokens: 10, output_tokens: 5 },
+            finish_reason: None,
+        })
+    }
+
+    /// A reply that calls a tool.
+    pub fn tool_call(call: ToolCall) -> Self {
+        Self::text("").with_tool_call(call)
+    }
+
+    /// Adds a tool call to a reply. The finish reason becomes [`FinishReason::ToolCalls`]
+    /// unless set explicitly.
+    pub fn with_tool_call(mut self, call: ToolCall) -> Self {
+        if let Body::Reply { tool_calls, .. } = &mut self.body {
+            tool_calls.push(call);
+        }
+        self
+    }
+
+    /// The token counts the reply reports.
+    pub fn usage(mut self, usage: Usage) -> Self {
+        if let Body::Reply { usage: reply_usage, .. } = &mut self.body {
+            *reply_usage = usage;
+        }
+        self
+    }
+
+    /// Why the reply says the model stopped. Defaults to [`FinishReason::Stop`], or
+    /// [`FinishReason::ToolCalls`] for replies with tool calls.
+    pub fn finish_reason(mut self, finish_reason: FinishReason) -> Self {
+        if let Body::Reply { finish_reason: reply_finish_reason, .. } = &mut self.body {
+            *reply_finish_reason = Some(finish_reason);
+        }
+        self
+    }
+
+    /// An error response with the provider's usual error type for `status`, e.g.
+    /// `rate_limit_error` for a 429 from Anthropic.
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::new(Body::Error { status, error_type: None, message: message.into() })
+    }
+
+    /// Overrides the error type of an [`error`](Self::error) response, e.g. to send
+    /// `context_length_exceeded`.
+    pub fn error_type(mut self, error_type: impl Into<String>) -> Self {
+        if let Body::Error { error_type: body_error_type, .. } = &mut self.body {
+            *body_error_type = Some(error_type.into());
+        }
+        self
+    }
+
+    /// `body` as is, whatever endpoint was called.
+    pub fn json(status: u16, body: Value) -> Self {
+        Self::new(Body::Json { status, body })
+    }
+
+    /// A `text/event-stream` body with one event per `(event, data)` pair, whatever endpoint was
+    /// called. An empty event name omits the `event:` line; string data is sent verbatim.
+    pub fn sse(events: &[(&str, Value)]) -> Self {
+        let events = events
+            .iter()
+            .map(|(event, data)| {
+                let data = match data {
+                    Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                (event.to_string(), data)
+            })
+            .collect();
+        Self::new(Body::Sse(events))
+    }
+
+    /// An `application/x-ndjson` body with one line per chunk, whatever endpoint was called.
+    pub fn ndjson(lines: &[Value]) -> Self {
+        let chunks = lines.iter().map(|line| format!("{}\n", line).into_bytes()).collect();
+        Self::raw(200, "application/x-ndjson", chunks)
+    }
+
+    /// `chunks` as they are, whatever endpoint was called, e.g. a binary event stream.
+    pub fn raw(status: u16, content_type: impl Into<String>, chunks: Vec<Vec<u8>>) -> Self {
+        Self::new(Body::Raw { status, content_type: content_type.into(), chunks })
+    }
+
+    /// Sends a header, in place of any the server would send by that name.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Waits this long before sending the response headers.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Splits the body into chunks of `size` bytes, regardless of event boundaries. By default
+    /// each SSE event is one chunk and a JSON body is a single chunk.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = Some(size.max(1));
+        self
+    }
+
+    /// Waits this long before each chunk.
+    pub fn chunk_delay(mut self, chunk_delay: Duration) -> Self {
+        self.chunk_delay = chunk_delay;
+        self
+    }
+
+    /// Keeps the connection open without sending anything after the first `chunks` chunks.
+    pub fn stall_after(mut self, chunks: usize) -> Self {
+        self.stall_after = Some(chunks);
+        self
+    }
+
+    /// Closes the connection after the first `chunks` chunks.
+    pub fn cut_after(mut self, chunks: usize) -> Self {
+        self.cut_after = Some(chunks);
+        self
+    }
+}
+
+/// The API whose wire format an endpoint uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    OpenAI,
+    Anthropic,
+}
+
+impl State {
+    async fn respond(self: Arc<Self>, request: LoopbackRequest) -> LoopbackResponse {
+        let request = TestRequest {
+            method: request.method,
+            path: request.path,
+            headers: request.headers,
+            body: String::from_utf8_lossy(&request.body).to_string(),
+        };
+        self.requests.lock().unwrap().push(request.clone());
+        let response = self.queue.lock().unwrap().pop_front();
+        let response = response.unwrap_or_else(|| self.fallback.lock().unwrap().clone());
+        let id = self.served.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let route = request.path.split('?').next().unwrap_or_default();
+        let dialect = if route.ends_with("/chat/completions") {
+            Some(Dialect::OpenAI)
+        } else if route.ends_with("/messages") {
+            Some(Dialect::Anthropic)
+        } else {
+            None
+        };
+        let needs_dialect = matches!(response.body, Body::Reply { .. } | Body::Error { .. });
+        if dialect.is_none() && needs_dialect {
+            return LoopbackResponse::json(404, json!({
+                "error": { "message": format!("TestServer does not emulate {}", route), "type": "invalid_request_error" }
+            }));
+        }
+
+        if !response.delay.is_zero() {
+            tokio::time::sleep(response.delay).await;
+        }
+        let (status, mut headers, mut chunks) = render(&response.body, dialect, &request.json(), id);
+        headers.retain(|(name, _)| !response.headers.iter().any(|(set, _)| set.eq_ignore_ascii_case(name)));
+        headers.extend(response.headers.iter().cloned());
+
+        if let Some(size) = response.chunk_size {
+            chunks = chunks.concat().chunks(size).map(<[u8]>::to_vec).collect();
+        }
+        if let Some(cut_after) = response.cut_after {
+            chunks.truncate(cut_after);
+        }
+        let stalled = response.stall_after.is_some_and(|stall_after| {
+            chunks.truncate(stall_after);
+            true
+        });
+
+        let chunk_delay = response.chunk_delay;
+        let mut body = stream::iter(chunks)
+            .then(move |chunk| async move {
+                if !chunk_delay.is_zero() {
+                    tokio::time::sleep(chunk_delay).await;
+                }
+                Ok(chunk)
+            })
+            .boxed();
+        if stalled {
+            body = body.chain(stream::pending()).boxed();
+        }
+        LoopbackResponse { status, headers, body }
+    }
+}
+
+/// Renders `body` as the status, headers and chunks the endpoint would send. Raw bodies are sent
+/// as they are, without a request id.
+fn render(body: &Body, dialect: Option<Dialect>, request: &Value, id: usize) -> (u16, Vec<(String, String)>, Vec<Vec<u8>>) {
+    let mut headers = match (body, dialect) {
+        (Body::Reply { .. } | Body::Error { .. }, Some(Dialect::OpenAI)) => {
+            vec![("x-request-id".to_string(), format!("req_test{}", id))]
+        }
+        (Body::Reply { .. } | Body::Error { .. }, Some(Dialect::Anthropic)) => {
+            vec![("request-id".to_string(), format!("req_test{}", id))]
+        }
+        _ => Vec::new(),
+    };
+    // Replies and errors on routes without a dialect were turned away before rendering.
+    let dialect = dialect.unwrap_or(Dialect::OpenAI);
+    let json = |headers: &mut Vec<(String, String)>, body: &Value| {
+        headers.push(("content-type".to_string(), "application/json".to_string()));
+        vec![body.to_string().into_bytes()]
+    };
+
+    match body {
+        Body::Reply { text, tool_calls, usage, finish_reason } => {
+            let reply = Reply {
+                id,
+                model: request["model"].as_str().unwrap_or("test-model"),
+                text,
+                tool_calls,
+                usage: *usage,
+                finish_reason: finish_reason.clone().unwrap_or(match tool_calls.is_empty() {
+                    true => FinishReason::Stop,
+                    false => FinishReason::ToolCalls,
+                }),
+            };
+            if request["stream"] == true {
+                headers.push(("content-type".to_string(), "text/event-stream".to_string()));
+                let events = match dialect {
+                    Dialect::OpenAI => reply.openai_events(request["stream_options"]["include_usage"] == true),
+                    Dialect::Anthropic => reply.anthropic_events(),
+                };
+                (200, headers, events.into_iter().map(|(event, data)| sse_chunk(&event, &data)).collect())
+            } else {
+                let body = match dialect {
+                    Dialect::OpenAI => reply.openai_json(),
+                    Dialect::Anthropic => reply.anthropic_json(),
+                };
+                let chunks = json(&mut headers, &body);
+                (200, headers, chunks)
+            }
+        }
+        Body::Error { status, error_type, message } => {
+            let body = match dialect {
+                Dialect::OpenAI => {
+                    let (default_type, code) = match status {
+                        401 => ("invalid_request_error", Some("invalid_api_key")),
+                        429 => ("requests", Some("rate_limit_exceeded")),
+                        500.. => ("server_error", None),
+                        _ => ("invalid_request_error", None),
+                    };
+                    let (error_type, code) = match error_type {
+                        Some(error_type) => (error_type.as_str(), Some(error_type.as_str())),
+                        None => (default_type, code),
+                    };
+                    json!({ "error": { "message": message, "type": error_type, "param": null, "code": code } })
+                }
+                Dialect::Anthropic => {
+                    let default_type = match status {
+                        400 => "invalid_request_error",
+                        401 => "authentication_error",
+                        403 => "permission_error",
+                        404 => "not_found_error",
+                        413 => "request_too_large",
+                        429 => "rate_limit_error",
+                        529 => "overloaded_error",
+                        _ => "api_error",
+                    };
+                    let error_type = error_type.as_deref().unwrap_or(default_type);
+                    json!({ "type": "error", "error": { "type": error_type, "message": message } })
+                }
+            };
+            let chunks = json(&mut headers, &body);
+            (*status, headers, chunks)
+        }
+        Body::Json { status, body } => {
+            let chunks = json(&mut headers, body);
+            (*status, headers, chunks)
+        }
+        Body::Sse(events) => {
+            headers.push(("content-type".to_string(), "text/event-stream".to_string()));
+            (200, headers, events.iter().map(|(event, data)| sse_chunk(event, data)).collect())
+        }
+        Body::Raw { status, content_type, chunks } => {
+            headers.push(("content-type".to_string(), content_type.clone()));
+            (*status, headers, chunks.clone())
+        }
+    }
+}
+
+fn sse_chunk(event: &str, data: &str) -> Vec<u8> {
+    match event {
+        "" => format!("data: {}\n\n", data).into_bytes(),
+        event => format!("event: {}\ndata: {}\n\n", event, data).into_bytes(),
+    }
+}
+
+struct Reply<'a> {
+    id: usize,
+    model: &'a str,
+    text: &'a str,
+    tool_calls: &'a [ToolCall],
+    usage: Usage,
+    finish_reason: FinishReason,
+}
+
+impl Reply<'_> {
+    /// The text split after each space, so streams carry several text deltas.
+    fn pieces(&self) -> impl Iterator<Item = &str> {
+        self.text.split_inclusive(' ')
+    }
+
+    fn openai_json(&self) -> Value {
+        let tool_calls: Vec<Value> = self.tool_calls
+            .iter()
+            .map(|call| json!({
+                "id": call.id,
+                "type": "function",
+                "function": { "name": call.name, "arguments": call.arguments.to_string() }
+            }))
+            .collect();
+        let mut message = json!({
+            "role": "assistant",
+            "content": if self.text.is_empty() { Value::Null } else { self.text.into() },
+        });
+        if !tool_calls.is_empty() {
+            message["tool_calls"] = tool_calls.into();
+        }
+        json!({
+            "id": format!("chatcmpl-test{}", self.id),
+            "object": "chat.completion",
+            "created": 0,
+            "model": self.model,
+            "choices": [{ "index": 0, "message": message, "finish_reason": openai_finish_reason(&self.finish_reason) }],
+            "usage": {
+                "prompt_tokens": self.usage.input_tokens,
+                "completion_tokens": self.usage.output_tokens,
+                "total_tokens": self.usage.total_tokens(),
+            }
+        })
+    }
+
+    fn openai_events(&self, include_usage: bool) -> Vec<(String, String)> {
+        let chunk = |choices: Value| json!({
+            "id": format!("chatcmpl-test{}", self.id),
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": self.model,
+            "choices": choices,
+        });
+        let delta = |delta: Value| chunk(json!([{ "index": 0, "delta": delta, "finish_reason": null }]));
+
+        let mut events = vec![delta(json!({ "role": "assistant", "content": "" }))];
+        events.extend(self.pieces().map(|piece| delta(json!({ "content": piece }))));
+        for (index, call) in self.tool_calls.iter().enumerate() {
+            events.push(delta(json!({ "tool_calls": [{
+                "index": index,
+                "id": call.id,
+                "type": "function",
+                "function": { "name": call.name, "arguments": "" }
+            }] })));
+            events.push(delta(json!({ "tool_calls": [{
+                "index": index,
+                "function": { "arguments": call.arguments.to_string() }
+            }] })));
+        }
+        events.push(chunk(json!([{ "index": 0, "delta": {}, "finish_reason": openai_finish_reason(&self.finish_reason) }])));
+        if include_usage {
+            let mut usage = chunk(json!([]));
+            usage["usage"] = json!({
+                "prompt_tokens": self.usage.input_tokens,
+                "completion_tokens": self.usage.output_tokens,
+                "total_tokens": self.usage.total_tokens(),
+            });
+            events.push(usage);
+        }
+
+        let mut events: Vec<(String, String)> = events.into_iter().map(|event| (String::new(), event.to_string())).collect();
+        events.push((String::new(), "[DONE]".to_string()));
+        events
+    }
+
+    fn anthropic_json(&self) -> Value {
+        let mut content = Vec::new();
+        if !self.text.is_empty() {
+            content.push(json!({ "type": "text", "text": self.text }));
+        }
+        content.extend(self.tool_calls.iter().map(|call| json!({
+            "type": "tool_use",
+            "id": call.id,
+            "name": call.name,
+            "input": call.arguments,
+        })));
+        json!({
+            "id": format!("msg_test{}", self.id),
+            "type": "message",
+            "role": "assistant",
+            "model": self.model,
+            "content": content,
+            "stop_reason": anthropic_stop_reason(&self.finish_reason),
+            "stop_sequence": null,
+            "usage": { "input_tokens": self.usage.input_tokens, "output_tokens": self.usage.output_tokens }
+        })
+    }
+
+    fn anthropic_events(&self) -> Vec<(String, String)> {
+        let mut events = vec![json!({
+            "type": "message_start",
+            "message": {
+                "id": format!("msg_test{}", self.id),
+                "type": "message",
+                "role": "assistant",
+                "model": self.model,
+                "content": [],
+                "stop_reason": null,
+                "stop_sequence": null,
+                "usage": { "input_tokens": self.usage.input_tokens, "output_tokens": 1 }
+            }
+        })];
+        let mut index = 0;
+        if !self.text.is_empty() {
+            events.push(json!({ "type": "content_block_start", "index": index, "content_block": { "type": "text", "text": "" } }));
+            events.extend(self.pieces().map(|piece| json!({
+                "type": "content_block_delta",
+                "index": index,
+                "delta": { "type": "text_delta", "text": piece }
+            })));
+            events.push(json!({ "type": "content_block_stop", "index": index }));
+            index += 1;
+        }
+        for call in self.tool_calls {
+            events.push(json!({
+                "type": "content_block_start",
+                "index": index,
+                "content_block": { "type": "tool_use", "id": call.id, "name": call.name, "input": {} }
+            }));
+            events.push(json!({
+                "type": "content_block_delta",
+                "index": index,
+                "delta": { "type": "input_json_delta", "partial_json": call.arguments.to_string() }
+            }));
+            events.push(json!({ "type": "content_block_stop", "index": index }));
+            index += 1;
+        }
+        events.push(json!({
+            "type": "message_delta",
+            "delta": { "stop_reason": anthropic_stop_reason(&self.finish_reason), "stop_sequence": null },
+            "usage": { "output_tokens": self.usage.output_tokens }
+        }));
+        events.push(json!({ "type": "message_stop" }));
+
+        events
+            .into_iter()
+            .map(|event| (event["type"].as_str().unwrap_or_default().to_string(), event.to_string()))
+            .collect()
+    }
+}
+
+fn openai_finish_reason(reason: &FinishReason) -> &str {
+    match reason {
+        FinishReason::Stop => "stop",
+        FinishReason::Length => "length",
+        FinishReason::ToolCalls => "tool_calls",
+        FinishReason::ContentFilter => "content_filter",
+        FinishReason::Other(reason) => reason,
+    }
+}
+
+fn anthropic_stop_reason(reason: &FinishReason) -> &str {
+    match reason {
+        FinishReason::Stop => "end_turn",
+        FinishReason::Length => "max_tokens",
+        FinishReason::ToolCalls => "tool_use",
+        FinishReason::ContentFilter => "refusal",
+        FinishReason::Other(reason) => reason,
+    }
+}
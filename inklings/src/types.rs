@@ -1,20 +1,688 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use futures::Stream;
+use reqwest::header::HeaderMap;
+use std::fmt;
+use std::pin::Pin;
+use std::time::Duration;
+
 #[derive(Debug, Clone)]
 pub struct Message {
     pub role: Role,
-    pub content: String,
+    /// Text, images and documents, in the order the model should see them.
+    pub content: Vec<ContentPart>,
+    /// Tool invocations requested by the assistant in this turn.
+    pub tool_calls: Vec<ToolCall>,
+    /// Results of earlier tool calls, sent back to the model in a user turn.
+    pub tool_results: Vec<ToolResult>,
 }
 
-#[derive(Debug, Clone)]
+impl Message {
+    /// A text-only message. Empty text gives a message without content parts.
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        let content: String = content.into();
+        Self::with_parts(role, if content.is_empty() { Vec::new() } else { vec![ContentPart::Text(content)] })
+    }
+
+    /// A message made of several parts, e.g. a question followed by a screenshot.
+    pub fn with_parts(role: Role, content: Vec<ContentPart>) -> Self {
+        Self {
+            role,
+            content,
+            tool_calls: Vec::new(),
+            tool_results: Vec::new(),
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(Role::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(Role::Assistant, content)
+    }
+
+    /// Builds the user turn that answers the tool calls of the previous assistant turn.
+    pub fn tool_results(results: Vec<ToolResult>) -> Self {
+        Self {
+            tool_results: results,
+            ..Self::new(Role::User, "")
+        }
+    }
+
+    /// The text parts joined together, ignoring images and documents.
+    pub fn text(&self) -> String {
+        self.content.iter().filter_map(ContentPart::as_text).collect()
+    }
+
+    /// Whether the message has any image or document parts.
+    pub fn has_media(&self) -> bool {
+        self.content.iter().any(|part| part.as_text().is_none())
+    }
+}
+
+/// One piece of a message's content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentPart {
+    Text(String),
+    Image(MediaSource),
+    /// A document such as a PDF. `name` is used by providers that require one.
+    Document { source: MediaSource, name: Option<String> },
+}
+
+impl ContentPart {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    /// An image from its raw bytes, e.g. the contents of a PNG file.
+    pub fn image_bytes(media_type: impl Into<String>, bytes: &[u8]) -> Self {
+        Self::Image(MediaSource::from_bytes(media_type, bytes))
+    }
+
+    /// An image from base64-encoded data.
+    pub fn image_base64(media_type: impl Into<String>, data: impl Into<String>) -> Self {
+        Self::Image(MediaSource::Base64 { media_type: media_type.into(), data: data.into() })
+    }
+
+    /// An image the provider fetches itself.
+    pub fn image_url(url: impl Into<String>) -> Self {
+        Self::Image(MediaSource::url(url))
+    }
+
+    /// A PDF from its raw bytes.
+    pub fn pdf(bytes: &[u8]) -> Self {
+        Self::document(MediaSource::from_bytes("application/pdf", bytes))
+    }
+
+    /// A document the provider fetches itself.
+    pub fn document_url(url: impl Into<String>) -> Self {
+        Self::document(MediaSource::url(url))
+    }
+
+    pub fn document(source: MediaSource) -> Self {
+        Self::Document { source, name: None }
+    }
+
+    /// Sets the name of a document part; other parts are returned unchanged.
+    pub fn named(self, name: impl Into<String>) -> Self {
+        match self {
+            Self::Document { source, .. } => Self::Document { source, name: Some(name.into()) },
+            other => other,
+        }
+    }
+
+    /// Sets the media type of an image or document fetched from a URL, which Gemini needs to
+    /// know; other parts are returned unchanged.
+    pub fn media_type(self, media_type: impl Into<String>) -> Self {
+        let typed = |source| match source {
+            MediaSource::Url { url, .. } => MediaSource::Url { url, media_type: Some(media_type.into()) },
+            inline => inline,
+        };
+        match self {
+            Self::Image(source) => Self::Image(typed(source)),
+            Self::Document { source, name } => Self::Document { source: typed(source), name },
+            text => text,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl From<&str> for ContentPart {
+    fn from(text: &str) -> Self {
+        Self::text(text)
+    }
+}
+
+impl From<String> for ContentPart {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+/// Where the data of an image or document part comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaSource {
+    /// Inline data with its media type, e.g. `image/png` or `application/pdf`.
+    Base64 { media_type: String, data: String },
+    /// A URL the provider downloads, with its media type if known. Not every provider accepts
+    /// URLs.
+    Url { url: String, media_type: Option<String> },
+}
+
+impl MediaSource {
+    pub fn from_bytes(media_type: impl Into<String>, bytes: &[u8]) -> Self {
+        Self::Base64 { media_type: media_type.into(), data: STANDARD.encode(bytes) }
+    }
+
+    /// A URL of unknown media type.
+    pub fn url(url: impl Into<String>) -> Self {
+        Self::Url { url: url.into(), media_type: None }
+    }
+
+    /// A `data:` URL for inline data, or the URL itself.
+    pub fn to_url(&self) -> String {
+        match self {
+            Self::Base64 { media_type, data } => format!("data:{};base64,{}", media_type, data),
+            Self::Url { url, .. } => url.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Role {
     System,
     User,
     Assistant,
 }
 
+/// A function the model may call, described by a JSON Schema for its arguments.
+#[derive(Debug, Clone)]
+pub struct Tool {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+impl Tool {
+    pub fn new(name: impl Into<String>, description: impl Into<String>, parameters: serde_json::Value) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+        }
+    }
+}
+
+/// A request from the model to invoke one of the supplied tools.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+/// The outcome of executing a [`ToolCall`], keyed by the call id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolResult {
+    pub tool_call_id: String,
+    pub content: String,
+    pub is_error: bool,
+}
+
+impl ToolResult {
+    pub fn new(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            tool_call_id: tool_call_id.into(),
+            content: content.into(),
+            is_error: false,
+        }
+    }
+
+    pub fn error(tool_call_id: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            is_error: true,
+            ..Self::new(tool_call_id, content)
+        }
+    }
+}
+
+/// Per-request generation settings. Unset fields fall back to the provider's defaults.
+///
+/// Providers skip fields their API has no equivalent for; use
+/// [`Provider::unsupported_options`](crate::provider::Provider::unsupported_options) to find out
+/// which ones those are.
+#[derive(Debug, Clone, Default)]
+pub struct ChatOptions {
+    /// Tools the model may call in its reply.
+    pub tools: Vec<Tool>,
+    pub temperature: Option<f64>,
+    pub top_p: Option<f64>,
+    pub max_tokens: Option<u32>,
+    pub stop: Vec<String>,
+    pub seed: Option<u64>,
+    pub presence_penalty: Option<f64>,
+    pub frequency_penalty: Option<f64>,
+    /// Constrains the reply to JSON matching a schema. Usually set through
+    /// [`Client::chat_structured`](crate::Client::chat_structured).
+    pub response_format: Option<ResponseFormat>,
+}
+
+impl ChatOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn top_p(mut self, top_p: f64) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn stop(mut self, stop: Vec<String>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn presence_penalty(mut self, presence_penalty: f64) -> Self {
+        self.presence_penalty = Some(presence_penalty);
+        self
+    }
+
+    pub fn frequency_penalty(mut self, frequency_penalty: f64) -> Self {
+        self.frequency_penalty = Some(frequency_penalty);
+        self
+    }
+
+    pub fn response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+}
+
+/// A JSON Schema the reply must match.
+///
+/// OpenAI-style providers send it as a strict `json_schema` response format and Anthropic as a
+/// tool the model is forced to call. The schema must describe an object and follow the rules of
+/// OpenAI's strict mode: every property required and no additional properties.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseFormat {
+    /// Identifies the schema to the model; letters, digits, `_` and `-` only.
+    pub name: String,
+    pub schema: serde_json::Value,
+}
+
+impl ResponseFormat {
+    pub fn new(name: impl Into<String>, schema: serde_json::Value) -> Self {
+        Self { name: name.into(), schema }
+    }
+}
+
+/// The assistant turn returned by [`Provider::chat`](crate::provider::Provider::chat), together
+/// with the metadata the provider reported about it.
+#[derive(Debug, Clone)]
+pub struct ChatResponse {
+    /// Provider-assigned response id, when one is returned.
+    pub id: Option<String>,
+    /// The model that actually answered, which may differ from the one requested.
+    pub model: String,
+    /// Name of the provider that answered, set by
+    /// [`FallbackProvider`](crate::provider::FallbackProvider) and
+    /// [`RouterProvider`](crate::provider::RouterProvider), which have several to choose from.
+    pub provider: Option<String>,
+    pub message: Message,
+    pub usage: Option<Usage>,
+    pub finish_reason: FinishReason,
+    /// Rate limits reported in the response headers, by providers that send them.
+    pub rate_limit: Option<crate::rate_limit::RateLimitStatus>,
+    /// The unmodified response body, for fields this crate does not model.
+    pub raw: serde_json::Value,
+}
+
+impl ChatResponse {
+    /// The text of the reply.
+    pub fn content(&self) -> String {
+        self.message.text()
+    }
+
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        &self.message.tool_calls
+    }
+
+    /// Whether generation stopped because the output token limit was reached.
+    pub fn is_truncated(&self) -> bool {
+        self.finish_reason == FinishReason::Length
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl Usage {
+    pub fn total_tokens(&self) -> u32 {
+        self.input_tokens.saturating_add(self.output_tokens)
+    }
+}
+
+/// Why the model stopped generating, normalised across providers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FinishReason {
+    /// Natural end of the turn or a stop sequence was hit.
+    Stop,
+    /// The output token limit was reached.
+    Length,
+    /// The model is waiting for the results of the tool calls it made.
+    ToolCalls,
+    /// Output was withheld or cut short by the provider's content filter.
+    ContentFilter,
+    /// A provider-specific reason this crate does not recognise.
+    Other(String),
+}
+
+/// The stream returned by [`Provider::stream_chat`](crate::provider::Provider::stream_chat).
+pub type ChatStream = Pin<Box<dyn Stream<Item = Result<StreamEvent, Error>> + Send>>;
+
+/// An incremental update from a streaming chat response.
+///
+/// Errors reported by the provider mid-stream arrive as `Err` items, after which the stream
+/// ends. Use [`StreamAccumulator`](crate::stream::StreamAccumulator) or
+/// [`stream::collect`](crate::stream::collect) to fold the events back into a [`ChatResponse`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// Response metadata, sent before any content. `provider` names the member of a
+    /// [`FallbackProvider`](crate::provider::FallbackProvider) or
+    /// [`RouterProvider`](crate::provider::RouterProvider) that answered, as
+    /// [`ChatResponse::provider`] does.
+    Start { id: Option<String>, model: String, provider: Option<String> },
+    TextDelta(String),
+    ToolCallDelta(ToolCallDelta),
+    /// Token counts so far; later events supersede earlier ones.
+    Usage(Usage),
+    Stop(FinishReason),
+    /// The provider signalled the end of the stream.
+    Done,
+}
+
+/// A fragment of a tool call. Fragments with the same `index` belong to the same call; `id` and
+/// `name` arrive with the first fragment and `arguments` is a piece of the JSON argument text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub arguments: String,
+}
+
+/// Details of an error reported by a provider API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    /// HTTP status of the response; `None` for errors reported in the middle of a stream.
+    pub status: Option<u16>,
+    /// The provider's own error type or code, e.g. `overloaded_error` or `context_length_exceeded`.
+    pub error_type: Option<String>,
+    pub message: String,
+    pub request_id: Option<String>,
+    /// How long the provider asked us to wait before retrying, from `retry-after` or `retry-after-ms`.
+    pub retry_after: Option<Duration>,
+}
+
+/// Why [`Client::chat_structured`](crate::Client::chat_structured) gave up on the model's replies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructuredOutputError {
+    /// The last reply, as returned by the model.
+    pub output: String,
+    /// What was wrong with it: invalid JSON, schema violations or a failed deserialisation.
+    pub errors: Vec<String>,
+    /// How many replies were requested in total.
+    pub attempts: u32,
+}
+
+impl fmt::Display for StructuredOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (after {} attempt", self.errors.join("; "), self.attempts)?;
+        if self.attempts != 1 {
+            write!(f, "s")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        let mut details = Vec::new();
+        if let Some(status) = self.status {
+            details.push(format!("status {}", status));
+        }
+        if let Some(error_type) = &self.error_type {
+            details.push(format!("type {}", error_type));
+        }
+        if let Some(request_id) = &self.request_id {
+            details.push(format!("request id {}", request_id));
+        }
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("API request failed: {0}")]
     RequestError(#[from] reqwest::Error),
     #[error("Provider error: {0}")]
     ProviderError(String),
+    /// A provider was built with invalid settings, such as a malformed header or proxy URL.
+    #[error("Invalid configuration: {0}")]
+    Configuration(String),
+    /// The API key is missing, invalid or lacks permission for the request.
+    #[error("Authentication failed: {0}")]
+    Authentication(ApiError),
+    #[error("Rate limited: {0}")]
+    RateLimited(ApiError),
+    /// The account has run out of credits or hit a hard spending limit; waiting will not help.
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(ApiError),
+    #[error("Context length exceeded: {0}")]
+    ContextLengthExceeded(ApiError),
+    #[error("Content filtered: {0}")]
+    ContentFiltered(ApiError),
+    #[error("Provider overloaded: {0}")]
+    Overloaded(ApiError),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(ApiError),
+    #[error("Provider server error: {0}")]
+    ServerError(ApiError),
+    /// The request uses a feature the provider or model does not offer, such as an image part
+    /// sent to a text-only API.
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+    /// The model's reply did not match the requested schema, even after re-prompting.
+    #[error("Invalid structured output: {0}")]
+    StructuredOutput(StructuredOutputError),
+    /// An [`Agent`](crate::Agent) run hit its iteration limit while the model was still calling
+    /// tools.
+    #[error("Agent stopped after {0} iterations without a final answer")]
+    MaxIterationsExceeded(usize),
+    /// The stream ended without the provider signalling completion.
+    #[error("Stream ended before the provider signalled completion")]
+    StreamTruncated,
+}
+
+impl Error {
+    /// Builds the error for a non-success HTTP response from its status, headers and body.
+    ///
+    /// Understands the `{"error": {"type", "code", "message"}}` bodies used by OpenAI, Anthropic
+    /// and most OpenAI-compatible servers, and falls back to the raw body otherwise.
+    pub fn from_response_parts(status: u16, headers: &HeaderMap, body: &str) -> Self {
+        let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+        // Delays that are not finite or do not fit in a `Duration` are ignored.
+        let seconds = |text: &str, scale: f64| {
+            let value = text.trim().parse::<f64>().ok().filter(|value| value.is_finite())?;
+            Duration::try_from_secs_f64(value.max(0.0) / scale).ok()
+        };
+        let retry_after = header("retry-after-ms")
+            .and_then(|ms| seconds(ms, 1000.0))
+            .or_else(|| header("retry-after").and_then(|secs| seconds(secs, 1.0)));
+        let request_id = header("x-request-id")
+            .or_else(|| header("request-id"))
+            .or_else(|| header("x-amzn-requestid"))
+            .map(str::to_string);
+
+        let json: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+        let mut error = Self::from_error_body(Some(status), &json);
+        if let Some(details) = error.api_error_mut() {
+            details.request_id = request_id.or_else(|| json["request_id"].as_str().map(str::to_string));
+            details.retry_after = retry_after;
+            if details.message.is_empty() {
+                details.message = body.to_string();
+            }
+        }
+        error
+    }
+
+    /// Classifies an error object found in a response body or a stream event. `json` may be
+    /// the object itself or a document holding it under `error`.
+    pub(crate) fn from_error_body(status: Option<u16>, json: &serde_json::Value) -> Self {
+        let error = json.get("error").unwrap_or(json);
+        let (message, error_type, code) = match error {
+            serde_json::Value::String(message) => (message.clone(), None, None),
+            _ => (
+                error["message"].as_str().unwrap_or_default().to_string(),
+                // Google APIs put a numeric code in `code` and the gRPC status name in `status`.
+                error["type"].as_str().or(error["status"].as_str()).map(str::to_string),
+                error["code"].as_str().map(str::to_string),
+            ),
+        };
+        let kind = code.as_deref().or(error_type.as_deref()).unwrap_or_default();
+        let lowercase = message.to_lowercase();
+
+        let details = ApiError {
+            status,
+            // The code is the more specific of the two where both are given.
+            error_type: code.clone().or(error_type.clone()),
+            message,
+            request_id: None,
+            retry_after: None,
+        };
+
+        if kind == "context_length_exceeded"
+            || lowercase.contains("prompt is too long")
+            || lowercase.contains("maximum context length")
+            || lowercase.contains("exceeds the maximum number of tokens")
+            || lowercase.contains("input is too long")
+        {
+            return Self::ContextLengthExceeded(details);
+        }
+        if kind == "content_filter" || kind == "content_policy_violation" {
+            return Self::ContentFiltered(details);
+        }
+        if kind == "insufficient_quota" || kind == "billing_error" {
+            return Self::QuotaExceeded(details);
+        }
+        match (status, error_type.as_deref().unwrap_or(kind)) {
+            (Some(401 | 403), _)
+            | (_, "authentication_error" | "permission_error" | "UNAUTHENTICATED" | "PERMISSION_DENIED"
+                | "AccessDeniedException" | "UnrecognizedClientException") => Self::Authentication(details),
+            (Some(429), _)
+            | (_, "rate_limit_error" | "rate_limit_exceeded" | "RESOURCE_EXHAUSTED" | "ThrottlingException") => Self::RateLimited(details),
+            (Some(503 | 529), _)
+            | (_, "overloaded_error" | "UNAVAILABLE" | "ServiceUnavailableException") => Self::Overloaded(details),
+            (Some(status), _) if status >= 500 => Self::ServerError(details),
+            (Some(_), _)
+            | (_, "invalid_request_error" | "not_found_error" | "request_too_large" | "INVALID_ARGUMENT" | "NOT_FOUND"
+                | "ValidationException" | "ResourceNotFoundException") => Self::InvalidRequest(details),
+            // Anything else reported mid-stream is on the provider's side.
+            (None, _) => Self::ServerError(details),
+        }
+    }
+
+    /// Whether the same request may succeed if sent again later.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RequestError(e) => e.is_timeout() || e.is_connect(),
+            Self::RateLimited(_) | Self::Overloaded(_) | Self::ServerError(_) | Self::StreamTruncated => true,
+            _ => false,
+        }
+    }
+
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            Self::Authentication(details)
+            | Self::RateLimited(details)
+            | Self::QuotaExceeded(details)
+            | Self::ContextLengthExceeded(details)
+            | Self::ContentFiltered(details)
+            | Self::Overloaded(details)
+            | Self::InvalidRequest(details)
+            | Self::ServerError(details) => Some(details),
+            _ => None,
+        }
+    }
+
+    fn api_error_mut(&mut self) -> Option<&mut ApiError> {
+        match self {
+            Self::Authentication(details)
+            | Self::RateLimited(details)
+            | Self::QuotaExceeded(details)
+            | Self::ContextLengthExceeded(details)
+            | Self::ContentFiltered(details)
+            | Self::Overloaded(details)
+            | Self::InvalidRequest(details)
+            | Self::ServerError(details) => Some(details),
+            _ => None,
+        }
+    }
+
+    pub fn status(&self) -> Option<u16> {
+        self.api_error().and_then(|details| details.status)
+            .or_else(|| match self {
+                Self::RequestError(e) => e.status().map(|status| status.as_u16()),
+                _ => None,
+            })
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.api_error().and_then(|details| details.retry_after)
+    }
+
+    pub fn request_id(&self) -> Option<&str> {
+        self.api_error().and_then(|details| details.request_id.as_deref())
+    }
+
+    /// A copy of the error, for wrappers that hand out the same scripted error repeatedly.
+    /// `Error` cannot be `Clone` because of the wrapped `reqwest::Error`, which is copied as a
+    /// [`Error::ProviderError`] with the same text.
+    pub(crate) fn duplicate(&self) -> Self {
+        match self {
+            Self::RequestError(e) => Self::ProviderError(e.to_string()),
+            Self::ProviderError(message) => Self::ProviderError(message.clone()),
+            Self::Configuration(message) => Self::Configuration(message.clone()),
+            Self::Authentication(details) => Self::Authentication(details.clone()),
+            Self::RateLimited(details) => Self::RateLimited(details.clone()),
+            Self::QuotaExceeded(details) => Self::QuotaExceeded(details.clone()),
+            Self::ContextLengthExceeded(details) => Self::ContextLengthExceeded(details.clone()),
+            Self::ContentFiltered(details) => Self::ContentFiltered(details.clone()),
+            Self::Overloaded(details) => Self::Overloaded(details.clone()),
+            Self::InvalidRequest(details) => Self::InvalidRequest(details.clone()),
+            Self::ServerError(details) => Self::ServerError(details.clone()),
+            Self::Unsupported(message) => Self::Unsupported(message.clone()),
+            Self::StructuredOutput(details) => Self::StructuredOutput(details.clone()),
+            Self::MaxIterationsExceeded(iterations) => Self::MaxIterationsExceeded(*iterations),
+            Self::StreamTruncated => Self::StreamTruncated,
+        }
+    }
 }
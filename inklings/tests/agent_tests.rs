@@ -0,0 +1,177 @@
+use inklings::{
+    Agent, Client,
+    agent::AgentStep,
+    provider::{MockProvider, MockReply},
+    types::{ChatResponse, Error, FinishReason, Message, Role, StreamEvent, Tool, ToolCall, ToolResult, Usage},
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+fn call(id: &str, name: &str, arguments: serde_json::Value) -> ToolCall {
+    ToolCall { id: id.to_string(), name: name.to_string(), arguments }
+}
+
+fn tool_turn(calls: Vec<ToolCall>) -> Message {
+    Message { tool_calls: calls, ..Message::assistant("") }
+}
+
+fn weather_tool() -> Tool {
+    Tool::new("get_weather", "Get the weather in a city", json!({
+        "type": "object",
+        "properties": { "city": { "type": "string" } },
+        "required": ["city"]
+    }))
+}
+
+async fn weather(arguments: serde_json::Value) -> Result<String, String> {
+    match arguments["city"].as_str() {
+        Some("Paris") => Ok("Sunny, 24°C".to_string()),
+        Some("London") => Ok("Rain, 14°C".to_string()),
+        _ => Err("Unknown city".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_agent_runs_tools_until_final_answer() {
+    let provider = MockProvider::with_replies(vec![
+        tool_turn(vec![
+            call("call_1", "get_weather", json!({ "city": "Paris" })),
+            call("call_2", "get_weather", json!({ "city": "London" })),
+        ]),
+        Message::assistant("Paris is sunny and London is rainy."),
+    ]);
+    let agent = Agent::new(Client::new(Box::new(provider))).tool(weather_tool(), weather);
+
+    let run = agent.run(vec![Message::new(Role::User, "Weather in Paris and London?")]).await.unwrap();
+
+    assert_eq!(run.response.content(), "Paris is sunny and London is rainy.");
+    assert_eq!(run.iterations(), 2);
+    let results: Vec<&ToolResult> = run.tool_calls().map(|(_, result)| result).collect();
+    assert_eq!(results, vec![&ToolResult::new("call_1", "Sunny, 24°C"), &ToolResult::new("call_2", "Rain, 14°C")]);
+
+    assert_eq!(run.messages.len(), 4);
+    assert_eq!(run.messages[1].tool_calls.len(), 2);
+    assert_eq!(run.messages[2].tool_results, vec![ToolResult::new("call_1", "Sunny, 24°C"), ToolResult::new("call_2", "Rain, 14°C")]);
+    assert_eq!(run.messages[3].text(), "Paris is sunny and London is rainy.");
+
+    assert!(matches!(&run.steps[0], AgentStep::Response(response) if response.tool_calls().len() == 2));
+    assert!(matches!(&run.steps[1], AgentStep::ToolCall { call, .. } if call.id == "call_1"));
+    assert!(matches!(&run.steps[2], AgentStep::ToolCall { call, .. } if call.id == "call_2"));
+    assert!(matches!(&run.steps[3], AgentStep::Response(_)));
+}
+
+#[tokio::test]
+async fn test_agent_reports_tool_failures_to_the_model() {
+    let provider = MockProvider::with_replies(vec![
+        tool_turn(vec![
+            call("call_1", "get_weather", json!({ "city": "Atlantis" })),
+            call("call_2", "get_stock_price", json!({ "ticker": "ACME" })),
+        ]),
+        Message::assistant("I could not find that."),
+    ]);
+    let agent = Agent::new(Client::new(Box::new(provider))).tool(weather_tool(), weather);
+
+    let run = agent.run(vec![Message::new(Role::User, "Weather in Atlantis?")]).await.unwrap();
+
+    assert_eq!(run.messages[2].tool_results, vec![
+        ToolResult::error("call_1", "Unknown city"),
+        ToolResult::error("call_2", "Unknown tool: get_stock_price"),
+    ]);
+    assert_eq!(run.response.content(), "I could not find that.");
+}
+
+/// Registers a tool that tracks how many of its calls are running at once.
+fn concurrency_agent(parallel: bool, peak: Arc<AtomicUsize>) -> Agent {
+    let provider = MockProvider::with_replies(vec![
+        tool_turn((1..=3).map(|i| call(&format!("call_{}", i), "slow", json!({}))).collect()),
+        Message::assistant("Done"),
+    ]);
+    let running = Arc::new(AtomicUsize::new(0));
+    Agent::new(Client::new(Box::new(provider)))
+        .parallel_tool_calls(parallel)
+        .tool(Tool::new("slow", "Takes a while", json!({ "type": "object" })), move |_| {
+            let running = running.clone();
+            let peak = peak.clone();
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok("ok".to_string())
+            }
+        })
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_agent_parallel_tool_calls() {
+    let peak = Arc::new(AtomicUsize::new(0));
+    let run = concurrency_agent(true, peak.clone()).run(vec![Message::user("Go")]).await.unwrap();
+    assert_eq!(peak.load(Ordering::SeqCst), 3);
+    let ids: Vec<&str> = run.tool_calls().map(|(call, _)| call.id.as_str()).collect();
+    assert_eq!(ids, vec!["call_1", "call_2", "call_3"]);
+
+    let peak = Arc::new(AtomicUsize::new(0));
+    concurrency_agent(false, peak.clone()).run(vec![Message::user("Go")]).await.unwrap();
+    assert_eq!(peak.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_agent_stops_at_max_iterations() {
+    let replies = (0..5).map(|i| tool_turn(vec![call(&format!("call_{}", i), "get_weather", json!({ "city": "Paris" }))])).collect();
+    let provider = Arc::new(MockProvider::with_replies(replies));
+    let observed = Arc::new(Mutex::new(Vec::new()));
+    let agent = Agent::new(Client::new(Box::new(provider.clone())))
+        .tool(weather_tool(), weather)
+        .max_iterations(3)
+        .on_step({
+            let observed = observed.clone();
+            move |step| observed.lock().unwrap().push(matches!(step, AgentStep::Response(_)))
+        });
+
+    let error = agent.run(vec![Message::user("Weather?")]).await.unwrap_err();
+
+    assert!(matches!(error, Error::MaxIterationsExceeded(3)));
+    assert_eq!(provider.calls(), 3);
+    assert_eq!(*observed.lock().unwrap(), vec![true, false, true, false, true, false]);
+}
+
+#[tokio::test]
+async fn test_agent_propagates_model_errors() {
+    let provider = MockProvider::with_error("boom".to_string());
+    let agent = Agent::new(Client::new(Box::new(provider))).tool(weather_tool(), weather);
+
+    let error = agent.run(vec![Message::user("Weather?")]).await.unwrap_err();
+
+    assert!(matches!(error, Error::ProviderError(message) if message == "boom"));
+}
+
+#[tokio::test]
+async fn test_agent_usage_saturates() {
+    let tool_turn = ChatResponse {
+        id: None,
+        model: "mock".to_string(),
+        provider: None,
+        message: tool_turn(vec![call("call_1", "get_weather", json!({ "city": "Paris" }))]),
+        usage: Some(Usage { input_tokens: u32::MAX - 1, output_tokens: 3 }),
+        finish_reason: FinishReason::ToolCalls,
+        rate_limit: None,
+        raw: serde_json::Value::Null,
+    };
+    let provider = MockProvider::builder()
+        .reply(MockReply::response(tool_turn))
+        .reply(MockReply::events(vec![
+            Ok(StreamEvent::TextDelta("Sunny.".to_string())),
+            Ok(StreamEvent::Usage(Usage { input_tokens: 5, output_tokens: u32::MAX })),
+            Ok(StreamEvent::Stop(FinishReason::Stop)),
+            Ok(StreamEvent::Done),
+        ]))
+        .build();
+    let agent = Agent::new(Client::new(Box::new(provider))).tool(weather_tool(), weather);
+
+    let run = agent.run(vec![Message::new(Role::User, "Weather in Paris?")]).await.unwrap();
+
+    assert_eq!(run.usage, Usage { input_tokens: u32::MAX, output_tokens: u32::MAX });
+    assert_eq!(run.usage.total_tokens(), u32::MAX);
+}
@@ -1,16 +1,26 @@
 mod common;
 
+use common::serve;
 use inklings::{
     Client,
-    provider::AnthropicProvider,
-    types::{Message, Role},
+    provider::{AnthropicProvider, Provider},
+    testing::{TestResponse, TestServer},
+    types::{ChatOptions, ContentPart, Error, FinishReason, Message, Role, Tool, ToolCall, ToolResult, Usage},
 };
+use serde_json::json;
+use std::sync::Arc;
+
+/// Replays the recording of a conversation with the live API; see [`common::cassette_provider`].
+async fn cassette(name: &str) -> Arc<dyn Provider + Send + Sync> {
+    common::cassette_provider(&format!("anthropic/{}", name), "https://api.anthropic.com/v1", "ANTHROPIC_API_KEY", |api_key, base_url| {
+        AnthropicProvider::builder(api_key).base_url(base_url).build()
+    })
+    .await
+}
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_anthropic_complete() {
-    let api_key = std::env::var("ANTHROPIC_API_KEY").expect("ANTHROPIC_API_KEY not set");
-    let provider = AnthropicProvider::new(api_key, None);
+    let provider = cassette("complete").await;
     let client = Client::new(Box::new(provider));
 
     let result = client.complete("Say 'test'\n\nAssistant:").await;
@@ -18,21 +28,13 @@ async fn test_anthropic_complete() {
 }
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_anthropic_chat() {
-    let api_key = std::env::var("ANTHROPIC_API_KEY").expect("ANTHROPIC_API_KEY not set");
-    let provider = AnthropicProvider::new(api_key, None);
+    let provider = cassette("chat").await;
     let client = Client::new(Box::new(provider));
 
     let messages = vec![
-        Message {
-            role: Role::User,
-            content: "Say 'test'".to_string(),
-        },
-        Message {
-            role: Role::Assistant,
-            content: "".to_string(), // Add empty assistant message to ensure proper format
-        },
+        Message::new(Role::User, "Say 'test'"),
+        Message::new(Role::Assistant, ""), // Add empty assistant message to ensure proper format
     ];
 
     let result = client.chat(messages).await;
@@ -49,23 +51,15 @@ async fn test_anthropic_invalid_key() {
 }
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_anthropic_stream_chat() {
     use futures::StreamExt;
-    
-    let api_key = std::env::var("ANTHROPIC_API_KEY").expect("ANTHROPIC_API_KEY not set");
-    let provider = AnthropicProvider::new(api_key, None);
+
+    let provider = cassette("stream_chat").await;
     let client = Client::new(Box::new(provider));
 
     let messages = vec![
-        Message {
-            role: Role::User,
-            content: "Count from 1 to 5 slowly.".to_string(),
-        },
-        Message {
-            role: Role::Assistant,
-            content: "".to_string(), // Add empty assistant message to ensure proper format
-        },
+        Message::new(Role::User, "Count from 1 to 5 slowly."),
+        Message::new(Role::Assistant, ""), // Add empty assistant message to ensure proper format
     ];
 
     let mut stream = client.stream_chat(messages).await.unwrap();
@@ -78,17 +72,261 @@ async fn test_anthropic_stream_chat() {
 }
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_anthropic_specific_response() {
-    let api_key = std::env::var("ANTHROPIC_API_KEY").expect("ANTHROPIC_API_KEY not set");
-    let provider = AnthropicProvider::new(api_key, None);
+    let provider = cassette("specific_response").await;
     common::test_specific_response(provider).await;
 }
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_anthropic_stream_specific_response() {
-    let api_key = std::env::var("ANTHROPIC_API_KEY").expect("ANTHROPIC_API_KEY not set");
-    let provider = AnthropicProvider::new(api_key, None);
+    let provider = cassette("stream_specific_response").await;
     common::test_stream_specific_response(provider).await;
 }
+
+#[tokio::test]
+async fn test_anthropic_tool_call() {
+    let provider = cassette("tool_call").await;
+    common::test_tool_call(provider).await;
+}
+
+#[tokio::test]
+async fn test_anthropic_response_metadata() {
+    let provider = cassette("response_metadata").await;
+    common::test_response_metadata(provider).await;
+}
+
+#[tokio::test]
+async fn test_anthropic_max_tokens_truncation() {
+    let provider = cassette("max_tokens_truncation").await;
+    common::test_max_tokens_truncation(provider).await;
+}
+
+#[test]
+fn test_anthropic_unsupported_options() {
+    let provider = AnthropicProvider::new("unused_key".to_string(), None);
+    let options = ChatOptions::new().temperature(0.5).seed(42).frequency_penalty(0.1);
+
+    assert_eq!(provider.unsupported_options(&options), vec!["seed", "frequency_penalty"]);
+}
+
+#[tokio::test]
+async fn test_anthropic_system_prompt() {
+    let provider = cassette("system_prompt").await;
+    common::test_system_prompt(provider).await;
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_collect() {
+    let provider = cassette("stream_collect").await;
+    common::test_stream_collect(provider).await;
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_tool_call() {
+    let provider = cassette("stream_tool_call").await;
+    common::test_stream_tool_call(provider).await;
+}
+
+fn server_provider(server: &TestServer) -> AnthropicProvider {
+    AnthropicProvider::builder("test_key")
+        .base_url(server.url())
+        .build()
+        .unwrap()
+}
+
+fn message_response(content: serde_json::Value, stop_reason: &str) -> serde_json::Value {
+    json!({
+        "id": "msg_123",
+        "type": "message",
+        "role": "assistant",
+        "model": "claude-3-5-haiku-20241022",
+        "content": content,
+        "stop_reason": stop_reason,
+        "usage": { "input_tokens": 20, "output_tokens": 4 }
+    })
+}
+
+#[tokio::test]
+async fn test_anthropic_builder_configures_requests() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        message_response(json!([{ "type": "text", "text": "Ahoy" }]), "end_turn"),
+    )])
+    .await;
+    let provider = AnthropicProvider::builder("test_key")
+        .model("claude-3-5-sonnet-20241022")
+        .base_url(server.url())
+        .header("anthropic-beta", "prompt-caching-2024-07-31")
+        .build()
+        .unwrap();
+    let client = Client::new(Box::new(provider));
+
+    let messages = vec![
+        Message::new(Role::System, "You are a pirate."),
+        Message::new(Role::User, "Hi"),
+        Message::new(Role::Assistant, "Arr!"),
+        Message::new(Role::System, "Be brief."),
+        Message::new(Role::User, "Greet me"),
+    ];
+    let response = client.chat(messages).await.unwrap();
+
+    assert_eq!(response.content(), "Ahoy");
+    assert_eq!(response.id.as_deref(), Some("msg_123"));
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 20, output_tokens: 4 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/messages");
+    assert_eq!(request.header("x-api-key"), Some("test_key"));
+    assert_eq!(request.header("anthropic-version"), Some("2023-06-01"));
+    assert_eq!(request.header("anthropic-beta"), Some("prompt-caching-2024-07-31"));
+    let body = request.json();
+    assert_eq!(body["model"], "claude-3-5-sonnet-20241022");
+    assert_eq!(body["system"], "You are a pirate.\n\nBe brief.");
+    assert_eq!(body["max_tokens"], 1000);
+    assert_eq!(body["messages"], json!([
+        { "role": "user", "content": "Hi" },
+        { "role": "assistant", "content": "Arr!" },
+        { "role": "user", "content": "Greet me" },
+    ]));
+}
+
+#[tokio::test]
+async fn test_anthropic_sends_options_and_tools() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        message_response(json!([
+            { "type": "text", "text": "Checking." },
+            { "type": "tool_use", "id": "toolu_1", "name": "get_weather", "input": { "city": "Paris" } }
+        ]), "tool_use"),
+    )])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let call = ToolCall { id: "toolu_0".to_string(), name: "get_weather".to_string(), arguments: json!({ "city": "Rome" }) };
+    let messages = vec![
+        Message::new(Role::User, "Weather in Rome and Paris?"),
+        Message { tool_calls: vec![call], ..Message::new(Role::Assistant, "") },
+        Message::tool_results(vec![ToolResult::new("toolu_0", "Sunny")]),
+    ];
+    let tool = Tool::new("get_weather", "Get the weather", json!({ "type": "object" }));
+    let options = ChatOptions::new().tools(vec![tool]).temperature(0.3).max_tokens(256).stop(vec!["END".to_string()]);
+    let response = client.chat_with_options(messages, &options).await.unwrap();
+
+    assert_eq!(response.content(), "Checking.");
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls()[0].id, "toolu_1");
+    assert_eq!(response.tool_calls()[0].arguments, json!({ "city": "Paris" }));
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["tools"][0]["input_schema"], json!({ "type": "object" }));
+    assert_eq!(body["temperature"], 0.3);
+    assert_eq!(body["max_tokens"], 256);
+    assert_eq!(body["stop_sequences"], json!(["END"]));
+    assert_eq!(body["messages"][1]["content"], json!([
+        { "type": "tool_use", "id": "toolu_0", "name": "get_weather", "input": { "city": "Rome" } }
+    ]));
+    assert_eq!(body["messages"][2]["content"], json!([
+        { "type": "tool_result", "tool_use_id": "toolu_0", "content": "Sunny", "is_error": false }
+    ]));
+}
+
+#[tokio::test]
+async fn test_anthropic_sends_images_and_documents() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        message_response(json!([{ "type": "text", "text": "A cat" }, { "type": "text", "text": " and a report." }]), "end_turn"),
+    )])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let message = Message::with_parts(Role::User, vec![
+        ContentPart::image_bytes("image/png", b"\x89PNG"),
+        ContentPart::image_url("https://example.com/cat.jpg"),
+        ContentPart::pdf(b"%PDF-1.7").named("Q3 report"),
+        ContentPart::text("What is in these?"),
+    ]);
+    let response = client.chat(vec![message]).await.unwrap();
+
+    assert_eq!(response.content(), "A cat and a report.");
+    let body = server.requests()[0].json();
+    assert_eq!(body["messages"][0]["content"], json!([
+        { "type": "image", "source": { "type": "base64", "media_type": "image/png", "data": "iVBORw==" } },
+        { "type": "image", "source": { "type": "url", "url": "https://example.com/cat.jpg" } },
+        { "type": "document", "source": { "type": "base64", "media_type": "application/pdf", "data": "JVBERi0xLjc=" }, "title": "Q3 report" },
+        { "type": "text", "text": "What is in these?" }
+    ]));
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_events() {
+    let server = serve(vec![TestResponse::sse(&[
+        ("message_start", json!({ "type": "message_start", "message": { "id": "msg_1", "model": "claude-3-5-haiku-20241022", "usage": { "input_tokens": 25, "output_tokens": 1 } } })),
+        ("content_block_start", json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } })),
+        ("ping", json!({ "type": "ping" })),
+        ("content_block_delta", json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Hello" } })),
+        ("content_block_stop", json!({ "type": "content_block_stop", "index": 0 })),
+        ("content_block_start", json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "tool_use", "id": "toolu_1", "name": "lookup", "input": {} } })),
+        ("content_block_delta", json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "{\"q\":" } })),
+        ("content_block_delta", json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": " 1}" } })),
+        ("content_block_stop", json!({ "type": "content_block_stop", "index": 1 })),
+        ("message_delta", json!({ "type": "message_delta", "delta": { "stop_reason": "tool_use" }, "usage": { "output_tokens": 15 } })),
+        ("message_stop", json!({ "type": "message_stop" })),
+    ])])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let response = inklings::stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Hello");
+    assert_eq!(response.id.as_deref(), Some("msg_1"));
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls()[0].name, "lookup");
+    assert_eq!(response.tool_calls()[0].arguments, json!({ "q": 1 }));
+    assert_eq!(response.usage, Some(Usage { input_tokens: 25, output_tokens: 15 }));
+    assert_eq!(server.requests()[0].json()["stream"], true);
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_error_and_truncation() {
+    let server = serve(vec![
+        TestResponse::sse(&[
+            ("message_start", json!({ "type": "message_start", "message": { "id": "msg_1", "model": "claude-3-5-haiku-20241022", "usage": { "input_tokens": 25, "output_tokens": 1 } } })),
+            ("error", json!({ "type": "error", "error": { "type": "overloaded_error", "message": "Overloaded" } })),
+        ]),
+        TestResponse::sse(&[
+            ("message_start", json!({ "type": "message_start", "message": { "id": "msg_2", "model": "claude-3-5-haiku-20241022", "usage": { "input_tokens": 25, "output_tokens": 1 } } })),
+            ("content_block_delta", json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Hel" } })),
+        ]),
+    ])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let error = inklings::stream::collect(stream).await.unwrap_err();
+    assert!(matches!(error, Error::Overloaded(_)));
+    assert!(error.is_retryable());
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let result = inklings::stream::collect(stream).await;
+    assert!(matches!(result, Err(Error::StreamTruncated)));
+}
+
+#[tokio::test]
+async fn test_anthropic_error_response() {
+    let server = serve(vec![TestResponse::json(
+        401,
+        json!({ "type": "error", "error": { "type": "authentication_error", "message": "invalid x-api-key" } }),
+    )
+    .header("request-id", "req_01")])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let error = client.complete("Hi").await.unwrap_err();
+
+    assert!(matches!(error, Error::Authentication(_)));
+    assert_eq!(error.request_id(), Some("req_01"));
+    assert!(!error.is_retryable());
+}
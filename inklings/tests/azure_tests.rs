@@ -0,0 +1,247 @@
+mod common;
+
+use common::serve;
+use inklings::{
+    Client,
+    provider::{AzureOpenAIProvider, ContentFilterResult, ContentFilterResults, Provider, TokenCredential},
+    testing::TestResponse,
+    types::{ChatOptions, Error, FinishReason, Message, Role, Usage},
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+fn safe_filters() -> serde_json::Value {
+    json!({
+        "hate": { "filtered": false, "severity": "safe" },
+        "self_harm": { "filtered": false, "severity": "safe" },
+        "sexual": { "filtered": false, "severity": "safe" },
+        "violence": { "filtered": false, "severity": "low" }
+    })
+}
+
+fn completion(content: &str, finish_reason: &str, completion_filters: serde_json::Value) -> serde_json::Value {
+    json!({
+        "id": "chatcmpl-azure1",
+        "object": "chat.completion",
+        "model": "gpt-4o-2024-08-06",
+        "prompt_filter_results": [{
+            "prompt_index": 0,
+            "content_filter_results": {
+                "hate": { "filtered": false, "severity": "safe" },
+                "jailbreak": { "filtered": false, "detected": false }
+            }
+        }],
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": content },
+            "finish_reason": finish_reason,
+            "content_filter_results": completion_filters
+        }],
+        "usage": { "prompt_tokens": 9, "completion_tokens": 1, "total_tokens": 10 }
+    })
+}
+
+/// Issues numbered tokens so tests can see a fresh one is requested per call.
+struct CountingCredential(Arc<AtomicUsize>);
+
+#[async_trait::async_trait]
+impl TokenCredential for CountingCredential {
+    async fn token(&self) -> Result<String, Error> {
+        Ok(format!("entra-token-{}", self.0.fetch_add(1, Ordering::SeqCst)))
+    }
+}
+
+/// A provider for the deployment named by `AZURE_OPENAI_RESOURCE` and `AZURE_OPENAI_DEPLOYMENT`.
+fn live_provider() -> AzureOpenAIProvider {
+    let resource = std::env::var("AZURE_OPENAI_RESOURCE").expect("AZURE_OPENAI_RESOURCE not set");
+    let deployment = std::env::var("AZURE_OPENAI_DEPLOYMENT").expect("AZURE_OPENAI_DEPLOYMENT not set");
+    let api_key = std::env::var("AZURE_OPENAI_API_KEY").expect("AZURE_OPENAI_API_KEY not set");
+    AzureOpenAIProvider::new(&resource, deployment, api_key)
+}
+
+#[tokio::test]
+#[ignore] // Requires an Azure OpenAI deployment
+async fn test_azure_specific_response() {
+    common::test_specific_response(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires an Azure OpenAI deployment
+async fn test_azure_stream_specific_response() {
+    common::test_stream_specific_response(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires an Azure OpenAI deployment
+async fn test_azure_tool_call() {
+    common::test_tool_call(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires an Azure OpenAI deployment
+async fn test_azure_response_metadata() {
+    common::test_response_metadata(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires an Azure OpenAI deployment
+async fn test_azure_max_tokens_truncation() {
+    common::test_max_tokens_truncation(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires an Azure OpenAI deployment
+async fn test_azure_system_prompt() {
+    common::test_system_prompt(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires an Azure OpenAI deployment
+async fn test_azure_stream_collect() {
+    common::test_stream_collect(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires an Azure OpenAI deployment
+async fn test_azure_stream_tool_call() {
+    common::test_stream_tool_call(live_provider()).await;
+}
+
+#[tokio::test]
+async fn test_azure_deployment_routing_and_api_key() {
+    let server = serve(vec![TestResponse::json(200, completion("pineapple", "stop", safe_filters()))]).await;
+    let provider = AzureOpenAIProvider::builder("my-resource", "gpt4o-prod")
+        .api_key("azure_key")
+        .api_version("2024-06-01")
+        .endpoint(format!("{}/openai", server.url()))
+        .build()
+        .unwrap();
+    let client = Client::new(Box::new(provider));
+
+    let options = ChatOptions::new().temperature(0.1);
+    let response = client.chat_with_options(vec![Message::new(Role::User, "Say pineapple")], &options).await.unwrap();
+
+    assert_eq!(response.content(), "pineapple");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 9, output_tokens: 1 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/openai/deployments/gpt4o-prod/chat/completions?api-version=2024-06-01");
+    assert_eq!(request.header("api-key"), Some("azure_key"));
+    assert_eq!(request.header("authorization"), None);
+    assert_eq!(request.json()["temperature"], 0.1);
+}
+
+#[tokio::test]
+async fn test_azure_token_credential() {
+    let server = serve(vec![
+        TestResponse::json(200, completion("one", "stop", safe_filters())),
+        TestResponse::json(200, completion("two", "stop", safe_filters())),
+    ])
+    .await;
+    let provider = AzureOpenAIProvider::builder("my-resource", "gpt4o-prod")
+        .credential(CountingCredential(Arc::new(AtomicUsize::new(1))))
+        .endpoint(format!("{}/openai", server.url()))
+        .build()
+        .unwrap();
+
+    provider.complete("Hi").await.unwrap();
+    provider.complete("Hi").await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].header("authorization"), Some("Bearer entra-token-1"));
+    assert_eq!(requests[1].header("authorization"), Some("Bearer entra-token-2"));
+    assert_eq!(requests[0].header("api-key"), None);
+    assert!(requests[0].path.ends_with("api-version=2024-10-21"));
+}
+
+#[tokio::test]
+async fn test_azure_content_filter_annotations() {
+    let filtered = json!({
+        "hate": { "filtered": false, "severity": "safe" },
+        "violence": { "filtered": true, "severity": "high" },
+        "protected_material_text": { "filtered": false, "detected": false }
+    });
+    let server = serve(vec![TestResponse::json(200, completion("", "content_filter", filtered))]).await;
+    let provider = AzureOpenAIProvider::builder("my-resource", "gpt4o-prod")
+        .api_key("azure_key")
+        .endpoint(format!("{}/openai", server.url()))
+        .build()
+        .unwrap();
+
+    let response = provider.chat(vec![Message::new(Role::User, "Hi")], &ChatOptions::default()).await.unwrap();
+    let filters = ContentFilterResults::from_response(&response);
+
+    assert_eq!(response.finish_reason, FinishReason::ContentFilter);
+    assert!(filters.is_filtered());
+    assert_eq!(filters.prompt.len(), 2);
+    assert_eq!(filters.prompt[1], ContentFilterResult {
+        category: "jailbreak".to_string(),
+        filtered: false,
+        severity: None,
+        detected: Some(false),
+    });
+    assert!(filters.completion.contains(&ContentFilterResult {
+        category: "violence".to_string(),
+        filtered: true,
+        severity: Some("high".to_string()),
+        detected: None,
+    }));
+}
+
+#[tokio::test]
+async fn test_azure_stream_skips_annotation_chunk() {
+    let server = serve(vec![TestResponse::sse(&[
+        ("", json!({ "id": "", "model": "", "object": "", "created": 0, "choices": [], "prompt_filter_results": [{ "prompt_index": 0, "content_filter_results": safe_filters() }] })),
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-2024-08-06", "choices": [{ "index": 0, "delta": { "role": "assistant", "content": "Hi" }, "finish_reason": null, "content_filter_results": {} }] })),
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-2024-08-06", "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop", "content_filter_results": safe_filters() }] })),
+        ("", json!("[DONE]")),
+    ])])
+    .await;
+    let provider = AzureOpenAIProvider::builder("my-resource", "gpt4o-prod")
+        .api_key("azure_key")
+        .endpoint(format!("{}/openai", server.url()))
+        .build()
+        .unwrap();
+
+    let stream = provider.stream_chat(vec![Message::new(Role::User, "Hi")], &ChatOptions::default()).await.unwrap();
+    let response = inklings::stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Hi");
+    assert_eq!(response.id.as_deref(), Some("chatcmpl-1"));
+    assert_eq!(response.model, "gpt-4o-2024-08-06");
+}
+
+#[tokio::test]
+async fn test_azure_content_filter_error() {
+    let server = serve(vec![TestResponse::json(400, json!({
+        "error": {
+            "message": "The response was filtered due to the prompt triggering Azure OpenAI's content management policy.",
+            "type": null,
+            "param": "prompt",
+            "code": "content_filter",
+            "status": 400,
+            "innererror": {
+                "code": "ResponsibleAIPolicyViolation",
+                "content_filter_result": { "hate": { "filtered": true, "severity": "high" } }
+            }
+        }
+    }))])
+    .await;
+    let provider = AzureOpenAIProvider::builder("my-resource", "gpt4o-prod")
+        .api_key("azure_key")
+        .endpoint(format!("{}/openai", server.url()))
+        .build()
+        .unwrap();
+
+    let error = provider.complete("Hi").await.unwrap_err();
+    assert!(matches!(error, Error::ContentFiltered(_)));
+    assert_eq!(error.status(), Some(400));
+}
+
+#[test]
+fn test_azure_requires_credential() {
+    let result = AzureOpenAIProvider::builder("my-resource", "gpt4o-prod").build();
+    assert!(matches!(result, Err(Error::Configuration(_))));
+}
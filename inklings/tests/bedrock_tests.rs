@@ -0,0 +1,401 @@
+mod common;
+
+use common::serve;
+use hmac::{Hmac, Mac};
+use inklings::{
+    Client,
+    provider::{AwsCredentials, BedrockProvider, Provider},
+    testing::{TestRequest, TestResponse, TestServer},
+    types::{ChatOptions, ContentPart, Error, FinishReason, MediaSource, Message, Role, Tool, ToolResult, Usage},
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+const ACCESS_KEY_ID: &str = "AKIDEXAMPLE";
+const SECRET_ACCESS_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+const MODEL: &str = "anthropic.claude-3-5-haiku-20241022-v1:0";
+
+fn live_provider() -> BedrockProvider {
+    BedrockProvider::new(std::env::var("BEDROCK_MODEL").ok()).unwrap()
+}
+
+#[tokio::test]
+#[ignore] // Requires AWS credentials with Bedrock access
+async fn test_bedrock_specific_response() {
+    common::test_specific_response(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires AWS credentials with Bedrock access
+async fn test_bedrock_stream_specific_response() {
+    common::test_stream_specific_response(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires AWS credentials with Bedrock access
+async fn test_bedrock_tool_call() {
+    common::test_tool_call(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires AWS credentials with Bedrock access
+async fn test_bedrock_response_metadata() {
+    common::test_response_metadata(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires AWS credentials with Bedrock access
+async fn test_bedrock_max_tokens_truncation() {
+    common::test_max_tokens_truncation(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires AWS credentials with Bedrock access
+async fn test_bedrock_system_prompt() {
+    common::test_system_prompt(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires AWS credentials with Bedrock access
+async fn test_bedrock_stream_collect() {
+    common::test_stream_collect(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires AWS credentials with Bedrock access
+async fn test_bedrock_stream_tool_call() {
+    common::test_stream_tool_call(live_provider()).await;
+}
+
+fn server_provider(server: &TestServer, credentials: AwsCredentials) -> BedrockProvider {
+    BedrockProvider::builder()
+        .region("us-west-2")
+        .credentials(credentials)
+        .endpoint(server.url())
+        .build()
+        .unwrap()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Recomputes the SigV4 signature of a recorded request the way AWS does and checks it
+/// matches the `authorization` header.
+fn assert_signed(request: &TestRequest, region: &str) {
+    let authorization = request.header("authorization").expect("request is not signed");
+    let fields = authorization.strip_prefix("AWS4-HMAC-SHA256 ").expect("unexpected signing algorithm");
+    let field = |name: &str| {
+        fields
+            .split(", ")
+            .find_map(|field| field.strip_prefix(&format!("{}=", name)))
+            .unwrap_or_else(|| panic!("authorization has no {}", name))
+    };
+    let amz_date = request.header("x-amz-date").expect("request has no x-amz-date");
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/bedrock/aws4_request", date, region);
+    assert_eq!(field("Credential"), format!("{}/{}", ACCESS_KEY_ID, scope));
+
+    let signed_headers = field("SignedHeaders");
+    for required in ["content-type", "host", "x-amz-date"] {
+        assert!(signed_headers.split(';').any(|name| name == required), "{} is not signed", required);
+    }
+    let canonical_headers: String = signed_headers
+        .split(';')
+        .map(|name| format!("{}:{}\n", name, request.header(name).unwrap()))
+        .collect();
+    // Every path segment is encoded a second time.
+    let canonical_uri = request.path.replace('%', "%25");
+    let canonical_request = format!(
+        "POST\n{}\n\n{}\n{}\n{}",
+        canonical_uri,
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(request.body.as_bytes())),
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes())),
+    );
+    let mut key = hmac(format!("AWS4{}", SECRET_ACCESS_KEY).as_bytes(), date);
+    for part in [region, "bedrock", "aws4_request"] {
+        key = hmac(&key, part);
+    }
+    assert_eq!(field("Signature"), hex::encode(hmac(&key, &string_to_sign)), "signature mismatch");
+}
+
+/// Encodes one message in the `application/vnd.amazon.eventstream` format.
+fn frame(headers: &[(&str, &str)], payload: &serde_json::Value) -> Vec<u8> {
+    let mut header_bytes = Vec::new();
+    for (name, value) in headers {
+        header_bytes.push(name.len() as u8);
+        header_bytes.extend_from_slice(name.as_bytes());
+        header_bytes.push(7);
+        header_bytes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        header_bytes.extend_from_slice(value.as_bytes());
+    }
+    let payload = payload.to_string().into_bytes();
+    let total_length = 12 + header_bytes.len() + payload.len() + 4;
+
+    let mut message = Vec::new();
+    message.extend_from_slice(&(total_length as u32).to_be_bytes());
+    message.extend_from_slice(&(header_bytes.len() as u32).to_be_bytes());
+    let prelude_crc = crc32fast::hash(&message);
+    message.extend_from_slice(&prelude_crc.to_be_bytes());
+    message.extend_from_slice(&header_bytes);
+    message.extend_from_slice(&payload);
+    let message_crc = crc32fast::hash(&message);
+    message.extend_from_slice(&message_crc.to_be_bytes());
+    message
+}
+
+fn event(event_type: &str, payload: serde_json::Value) -> Vec<u8> {
+    frame(
+        &[(":message-type", "event"), (":event-type", event_type), (":content-type", "application/json")],
+        &payload,
+    )
+}
+
+/// A ConverseStream body, split into chunks that cut across message boundaries.
+fn event_stream(messages: Vec<Vec<u8>>) -> TestResponse {
+    TestResponse::raw(200, "application/vnd.amazon.eventstream", messages).chunk_size(37)
+}
+
+fn converse_response(content: serde_json::Value, stop_reason: &str) -> TestResponse {
+    TestResponse::json(200, json!({
+        "output": { "message": { "role": "assistant", "content": content } },
+        "stopReason": stop_reason,
+        "usage": { "inputTokens": 14, "outputTokens": 3, "totalTokens": 17 },
+        "metrics": { "latencyMs": 412 }
+    }))
+    .header("x-amzn-RequestId", "5f1c3a9e-8d2b-4c7a-9e1f-0a2b3c4d5e6f")
+}
+
+#[tokio::test]
+async fn test_bedrock_converse_request_is_signed() {
+    let server = serve(vec![converse_response(json!([{ "text": "pineapple" }]), "end_turn")]).await;
+    let client = Client::new(Box::new(server_provider(&server, AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY))));
+
+    let options = ChatOptions::new().temperature(0.5).max_tokens(100).stop(vec!["END".to_string()]);
+    let messages = vec![
+        Message::new(Role::System, "Be brief"),
+        Message::new(Role::User, "Say pineapple"),
+    ];
+    let response = client.chat_with_options(messages, &options).await.unwrap();
+
+    assert_eq!(response.content(), "pineapple");
+    assert_eq!(response.id.as_deref(), Some("5f1c3a9e-8d2b-4c7a-9e1f-0a2b3c4d5e6f"));
+    assert_eq!(response.model, MODEL);
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 14, output_tokens: 3 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/model/anthropic.claude-3-5-haiku-20241022-v1%3A0/converse");
+    assert_eq!(request.header("x-amz-security-token"), None);
+    assert_signed(request, "us-west-2");
+    assert_eq!(request.json(), json!({
+        "messages": [{ "role": "user", "content": [{ "text": "Say pineapple" }] }],
+        "system": [{ "text": "Be brief" }],
+        "inferenceConfig": { "maxTokens": 100, "temperature": 0.5, "stopSequences": ["END"] }
+    }));
+}
+
+#[tokio::test]
+async fn test_bedrock_session_token_is_signed() {
+    let server = serve(vec![converse_response(json!([{ "text": "ok" }]), "end_turn")]).await;
+    let credentials = AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY).session_token("session-token-123");
+    let provider = server_provider(&server, credentials);
+
+    provider.complete("Hi").await.unwrap();
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("x-amz-security-token"), Some("session-token-123"));
+    assert!(request.header("authorization").unwrap().contains("x-amz-security-token"));
+    assert_signed(request, "us-west-2");
+}
+
+#[tokio::test]
+async fn test_bedrock_tool_use_round_trip() {
+    let server = serve(vec![
+        converse_response(json!([
+            { "text": "Let me check." },
+            { "toolUse": { "toolUseId": "tooluse_abc", "name": "get_weather", "input": { "city": "Paris" } } }
+        ]), "tool_use"),
+        converse_response(json!([{ "text": "Sunny." }]), "end_turn"),
+    ])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server, AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY))));
+    let tools = vec![Tool::new("get_weather", "Get the weather", json!({ "type": "object" }))];
+
+    let mut messages = vec![Message::new(Role::User, "Weather in Paris?")];
+    let reply = client.chat_with_tools(messages.clone(), &tools).await.unwrap();
+
+    assert_eq!(reply.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(reply.tool_calls()[0].id, "tooluse_abc");
+    assert_eq!(reply.tool_calls()[0].arguments, json!({ "city": "Paris" }));
+
+    messages.push(reply.message);
+    messages.push(Message::tool_results(vec![ToolResult::error("tooluse_abc", "Service down")]));
+    client.chat_with_tools(messages, &tools).await.unwrap();
+
+    let body = server.requests()[1].json();
+    assert_eq!(body["toolConfig"]["tools"][0]["toolSpec"]["inputSchema"], json!({ "json": { "type": "object" } }));
+    assert_eq!(body["messages"][1]["content"][1], json!({
+        "toolUse": { "toolUseId": "tooluse_abc", "name": "get_weather", "input": { "city": "Paris" } }
+    }));
+    assert_eq!(body["messages"][2]["content"][0], json!({
+        "toolResult": { "toolUseId": "tooluse_abc", "content": [{ "text": "Service down" }], "status": "error" }
+    }));
+}
+
+#[tokio::test]
+async fn test_bedrock_sends_images_and_documents() {
+    let server = serve(vec![converse_response(json!([{ "text": "A chart." }]), "end_turn")]).await;
+    let client = Client::new(Box::new(server_provider(&server, AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY))));
+
+    let message = Message::with_parts(Role::User, vec![
+        ContentPart::image_bytes("image/jpeg", b"\xff\xd8"),
+        ContentPart::pdf(b"%PDF-1.7").named("Q3 report"),
+        ContentPart::text("Summarise"),
+    ]);
+    client.chat(vec![message]).await.unwrap();
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["messages"][0]["content"], json!([
+        { "image": { "format": "jpeg", "source": { "bytes": "/9g=" } } },
+        { "document": { "format": "pdf", "name": "Q3 report", "source": { "bytes": "JVBERi0xLjc=" } } },
+        { "text": "Summarise" }
+    ]));
+
+    let unsupported = [
+        ContentPart::image_url("https://example.com/a.png"),
+        ContentPart::document(MediaSource::from_bytes("application/zip", b"PK")),
+    ];
+    for part in unsupported {
+        let error = client.chat(vec![Message::with_parts(Role::User, vec![part])]).await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(_)));
+    }
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_bedrock_converse_stream() {
+    let server = serve(vec![event_stream(vec![
+        event("messageStart", json!({ "role": "assistant" })),
+        event("contentBlockDelta", json!({ "contentBlockIndex": 0, "delta": { "text": "Hel" } })),
+        event("contentBlockDelta", json!({ "contentBlockIndex": 0, "delta": { "text": "lo" } })),
+        event("contentBlockStop", json!({ "contentBlockIndex": 0 })),
+        event("contentBlockStart", json!({ "contentBlockIndex": 1, "start": { "toolUse": { "toolUseId": "tooluse_1", "name": "lookup" } } })),
+        event("contentBlockDelta", json!({ "contentBlockIndex": 1, "delta": { "toolUse": { "input": "{\"q\":" } } })),
+        event("contentBlockDelta", json!({ "contentBlockIndex": 1, "delta": { "toolUse": { "input": "1}" } } })),
+        event("contentBlockStop", json!({ "contentBlockIndex": 1 })),
+        event("messageStop", json!({ "stopReason": "tool_use" })),
+        event("metadata", json!({ "usage": { "inputTokens": 8, "outputTokens": 6, "totalTokens": 14 }, "metrics": { "latencyMs": 300 } })),
+    ])])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server, AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY))));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let response = inklings::stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Hello");
+    assert_eq!(response.model, MODEL);
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls()[0].id, "tooluse_1");
+    assert_eq!(response.tool_calls()[0].arguments, json!({ "q": 1 }));
+    assert_eq!(response.usage, Some(Usage { input_tokens: 8, output_tokens: 6 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/model/anthropic.claude-3-5-haiku-20241022-v1%3A0/converse-stream");
+    assert_signed(request, "us-west-2");
+}
+
+#[tokio::test]
+async fn test_bedrock_stream_exception_and_corruption() {
+    let mut corrupted = event("contentBlockDelta", json!({ "contentBlockIndex": 0, "delta": { "text": "lo" } }));
+    let last = corrupted.len() - 1;
+    corrupted[last] ^= 0xff;
+    let server = serve(vec![
+        event_stream(vec![
+            event("messageStart", json!({ "role": "assistant" })),
+            frame(
+                &[(":message-type", "exception"), (":exception-type", "throttlingException"), (":content-type", "application/json")],
+                &json!({ "message": "Too many requests, please wait before trying again." }),
+            ),
+        ]),
+        event_stream(vec![event("messageStart", json!({ "role": "assistant" })), corrupted]),
+        event_stream(vec![event("messageStart", json!({ "role": "assistant" }))]),
+    ])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server, AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY))));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let error = inklings::stream::collect(stream).await.unwrap_err();
+    assert!(matches!(&error, Error::RateLimited(details) if details.error_type.as_deref() == Some("ThrottlingException")));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let error = inklings::stream::collect(stream).await.unwrap_err();
+    assert!(matches!(&error, Error::ProviderError(message) if message.contains("checksum")));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let error = inklings::stream::collect(stream).await.unwrap_err();
+    assert!(matches!(error, Error::StreamTruncated));
+}
+
+#[tokio::test]
+async fn test_bedrock_error_response() {
+    let server = serve(vec![
+        TestResponse::json(400, json!({ "message": "The provided model identifier is invalid." }))
+            .header("x-amzn-ErrorType", "ValidationException:http://internal.amazon.com/coral/com.amazon.bedrock/")
+            .header("x-amzn-RequestId", "req-1"),
+        TestResponse::json(429, json!({ "message": "Too many requests, please wait before trying again." }))
+            .header("x-amzn-ErrorType", "ThrottlingException"),
+    ])
+    .await;
+    let provider = server_provider(&server, AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY));
+
+    let error = provider.complete("Hi").await.unwrap_err();
+    assert!(matches!(&error, Error::InvalidRequest(details) if details.error_type.as_deref() == Some("ValidationException")));
+    assert_eq!(error.request_id(), Some("req-1"));
+
+    let error = provider.complete("Hi").await.unwrap_err();
+    assert!(matches!(error, Error::RateLimited(_)));
+    assert!(error.is_retryable());
+}
+
+#[tokio::test]
+async fn test_bedrock_reports_unsupported_options() {
+    let server = serve(vec![]).await;
+    let provider = server_provider(&server, AwsCredentials::new(ACCESS_KEY_ID, SECRET_ACCESS_KEY));
+
+    let options = ChatOptions::new().temperature(0.5).seed(1).presence_penalty(0.1);
+    assert_eq!(provider.unsupported_options(&options), vec!["seed", "presence_penalty"]);
+}
+
+#[test]
+fn test_credentials_from_profile() {
+    let path = std::env::temp_dir().join(format!("inklings-aws-credentials-{}", std::process::id()));
+    std::fs::write(&path, "\
+[default]
+aws_access_key_id = AKIDDEFAULT
+aws_secret_access_key = default-secret
+
+# Temporary credentials
+[dev]
+aws_access_key_id=AKIDDEV
+aws_secret_access_key=dev-secret
+aws_session_token=dev-token
+").unwrap();
+    std::env::set_var("AWS_SHARED_CREDENTIALS_FILE", &path);
+
+    let credentials = AwsCredentials::from_profile("dev").unwrap();
+    assert_eq!(credentials, AwsCredentials::new("AKIDDEV", "dev-secret").session_token("dev-token"));
+    assert_eq!(AwsCredentials::from_profile("default").unwrap().access_key_id, "AKIDDEFAULT");
+    assert!(matches!(AwsCredentials::from_profile("missing"), Err(Error::Configuration(_))));
+
+    std::fs::remove_file(path).unwrap();
+}
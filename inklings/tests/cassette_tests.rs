@@ -0,0 +1,331 @@
+use inklings::{
+    Client,
+    provider::{AnthropicProvider, OpenAIProvider, RecordingProvider, ReplayProvider},
+    stream,
+    testing::{TestResponse, TestServer},
+    types::{Error, FinishReason, Message, Role, Usage},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+const API_KEY: &str = "sk-test-0123456789abcdef";
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("inklings-cassettes-{}", std::process::id()))
+        .join(format!("{}.json", name))
+}
+
+fn read_cassette(path: &PathBuf) -> (String, Value) {
+    let contents = std::fs::read_to_string(path).unwrap();
+    let cassette = serde_json::from_str(&contents).unwrap();
+    (contents, cassette)
+}
+
+/// The values recorded for header `name`, in order.
+fn header_values<'a>(headers: &'a Value, name: &str) -> Vec<&'a str> {
+    headers
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|header| header[0] == name)
+        .map(|header| header[1].as_str().unwrap())
+        .collect()
+}
+
+fn openai_completion(content: &str) -> TestResponse {
+    TestResponse::json(200, json!({
+        "id": "chatcmpl-123",
+        "model": "gpt-4o-mini",
+        "choices": [{ "index": 0, "message": { "role": "assistant", "content": content }, "finish_reason": "stop" }],
+        "usage": { "prompt_tokens": 12, "completion_tokens": 3 }
+    }))
+}
+
+fn openai_stream() -> TestResponse {
+    TestResponse::sse(&[
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": { "content": "Hel" }, "finish_reason": null }] })),
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": { "content": "lo" }, "finish_reason": "stop" }] })),
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [], "usage": { "prompt_tokens": 5, "completion_tokens": 2 } })),
+        ("", json!("[DONE]")),
+    ])
+}
+
+async fn record_openai(path: &PathBuf, responses: Vec<TestResponse>) -> (TestServer, Client) {
+    let server = TestServer::start().await;
+    for response in responses {
+        server.enqueue(response);
+    }
+    let provider = RecordingProvider::start(path, server.url(), |base_url| {
+        OpenAIProvider::builder(API_KEY).base_url(base_url).build()
+    })
+    .await
+    .unwrap();
+    (server, Client::new(Box::new(provider)))
+}
+
+async fn replay_openai(path: &PathBuf) -> Client {
+    let provider = ReplayProvider::start(path, |base_url| {
+        OpenAIProvider::builder("placeholder").base_url(base_url).build()
+    })
+    .await
+    .unwrap();
+    Client::new(Box::new(provider))
+}
+
+#[tokio::test]
+async fn test_record_and_replay_chat_and_stream() {
+    let path = cassette_path("chat_and_stream");
+    let (server, client) = record_openai(&path, vec![openai_completion("Hi there"), openai_stream()]).await;
+
+    let recorded = client.chat(vec![Message::new(Role::User, "Hello")]).await.unwrap();
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Stream please")]).await.unwrap();
+    let recorded_stream = stream::collect(stream).await.unwrap();
+    assert_eq!(recorded.content(), "Hi there");
+    assert_eq!(recorded_stream.content(), "Hello");
+    assert_eq!(server.requests()[0].header("authorization"), Some(format!("Bearer {}", API_KEY).as_str()));
+
+    let (_, cassette) = read_cassette(&path);
+    let interactions = cassette["interactions"].as_array().unwrap();
+    assert_eq!(interactions.len(), 2);
+    assert_eq!(interactions[0]["request"]["path"], "/chat/completions");
+    assert_eq!(interactions[0]["request"]["body"]["messages"][0]["content"], "Hello");
+    assert_eq!(header_values(&interactions[1]["response"]["headers"], "content-type"), ["text/event-stream"]);
+    let chunks: Vec<&str> = interactions[1]["response"]["body"].as_array().unwrap().iter().map(|c| c.as_str().unwrap()).collect();
+    assert!(chunks.concat().ends_with("data: [DONE]\n\n"), "{:?}", chunks);
+    drop(server);
+
+    let client = replay_openai(&path).await;
+    let replayed = client.chat(vec![Message::new(Role::User, "Hello")]).await.unwrap();
+    assert_eq!(replayed.content(), "Hi there");
+    assert_eq!(replayed.id.as_deref(), Some("chatcmpl-123"));
+    assert_eq!(replayed.usage, Some(Usage { input_tokens: 12, output_tokens: 3 }));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Stream please")]).await.unwrap();
+    let replayed_stream = stream::collect(stream).await.unwrap();
+    assert_eq!(replayed_stream.content(), "Hello");
+    assert_eq!(replayed_stream.finish_reason, FinishReason::Stop);
+    assert_eq!(replayed_stream.usage, Some(Usage { input_tokens: 5, output_tokens: 2 }));
+}
+
+#[tokio::test]
+async fn test_api_keys_are_redacted() {
+    let path = cassette_path("redacted");
+    let echo = TestResponse::json(401, json!({
+        "error": { "message": format!("Incorrect API key provided: {}", API_KEY), "type": "invalid_request_error" }
+    }));
+    let (_server, client) = record_openai(&path, vec![echo]).await;
+
+    let error = client.chat(vec![Message::new(Role::User, "Hello")]).await.unwrap_err();
+    assert!(matches!(error, Error::Authentication(_)));
+
+    let (contents, cassette) = read_cassette(&path);
+    assert!(!contents.contains(API_KEY), "{}", contents);
+    assert_eq!(header_values(&cassette["interactions"][0]["request"]["headers"], "authorization"), ["REDACTED"]);
+    let body = cassette["interactions"][0]["response"]["body"][0].as_str().unwrap();
+    assert!(body.contains("Incorrect API key provided: REDACTED"), "{}", body);
+
+    let client = replay_openai(&path).await;
+    let error = client.chat(vec![Message::new(Role::User, "Hello")]).await.unwrap_err();
+    assert!(matches!(error, Error::Authentication(details) if details.status == Some(401)));
+}
+
+#[tokio::test]
+async fn test_repeated_headers_are_kept() {
+    let path = cassette_path("repeated_headers");
+    let response = openai_completion("Hi there")
+        .header("vary", "Origin")
+        .header("vary", "Accept-Encoding")
+        .header("set-cookie", "__cf_bm=abc; path=/")
+        .header("set-cookie", "_cfuvid=def; path=/");
+    let (_server, client) = record_openai(&path, vec![response]).await;
+    client.chat(vec![Message::new(Role::User, "Hello")]).await.unwrap();
+
+    let (_, cassette) = read_cassette(&path);
+    let headers = &cassette["interactions"][0]["response"]["headers"];
+    assert_eq!(header_values(headers, "vary"), ["Origin", "Accept-Encoding"]);
+    assert_eq!(header_values(headers, "set-cookie"), ["REDACTED", "REDACTED"]);
+
+    // Record the replay in turn to see the headers it sends.
+    let mut replay_url = String::new();
+    let _replay = ReplayProvider::start(&path, |base_url| {
+        replay_url = base_url.to_string();
+        OpenAIProvider::builder("placeholder").base_url(base_url).build()
+    })
+    .await
+    .unwrap();
+    let rerecorded = cassette_path("repeated_headers_replayed");
+    let provider = RecordingProvider::start(&rerecorded, &replay_url, |base_url| {
+        OpenAIProvider::builder(API_KEY).base_url(base_url).build()
+    })
+    .await
+    .unwrap();
+    Client::new(Box::new(provider)).chat(vec![Message::new(Role::User, "Hello")]).await.unwrap();
+
+    let (_, cassette) = read_cassette(&rerecorded);
+    let headers = &cassette["interactions"][0]["response"]["headers"];
+    assert_eq!(header_values(headers, "vary"), ["Origin", "Accept-Encoding"]);
+    assert_eq!(header_values(headers, "set-cookie"), ["REDACTED", "REDACTED"]);
+}
+
+#[tokio::test]
+async fn test_repeated_requests_replay_in_order() {
+    let path = cassette_path("repeated");
+    let (_server, client) = record_openai(&path, vec![openai_completion("first"), openai_completion("second")]).await;
+    client.chat(vec![Message::new(Role::User, "Again")]).await.unwrap();
+    client.chat(vec![Message::new(Role::User, "Again")]).await.unwrap();
+
+    let client = replay_openai(&path).await;
+    let messages = vec![Message::new(Role::User, "Again")];
+    assert_eq!(client.chat_text(messages.clone()).await.unwrap(), "first");
+    assert_eq!(client.chat_text(messages.clone()).await.unwrap(), "second");
+    assert_eq!(client.chat_text(messages).await.unwrap(), "second");
+}
+
+#[tokio::test]
+async fn test_unrecorded_request_fails() {
+    let path = cassette_path("miss");
+    let (_server, client) = record_openai(&path, vec![openai_completion("Hi there")]).await;
+    client.chat(vec![Message::new(Role::User, "Hello")]).await.unwrap();
+
+    let client = replay_openai(&path).await;
+    let error = client.chat(vec![Message::new(Role::User, "Something else")]).await.unwrap_err();
+
+    assert!(error.to_string().contains("no recording of POST /chat/completions"), "{}", error);
+    assert!(error.to_string().contains("miss.json"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_requests_match_regardless_of_key_order() {
+    let path = cassette_path("key_order");
+    let (_server, client) = record_openai(&path, vec![openai_completion("Hi there")]).await;
+    client.chat(vec![Message::new(Role::User, "Hello")]).await.unwrap();
+
+    // Edit the cassette by hand, reversing the key order of every object in it. `Value` keeps
+    // its keys sorted, so the reversed JSON is written out as text.
+    fn reversed(value: &Value) -> String {
+        match value {
+            Value::Object(object) => {
+                let entries: Vec<String> = object.iter().rev().map(|(k, v)| format!("{}:{}", Value::from(k.as_str()), reversed(v))).collect();
+                format!("{{{}}}", entries.join(","))
+            }
+            Value::Array(items) => format!("[{}]", items.iter().map(reversed).collect::<Vec<_>>().join(",")),
+            other => other.to_string(),
+        }
+    }
+    let (_, cassette) = read_cassette(&path);
+    let contents = reversed(&cassette);
+    assert!(contents.contains(r#""body":{"model":"gpt-4o-mini","messages":"#), "{}", contents);
+    std::fs::write(&path, contents).unwrap();
+
+    let client = replay_openai(&path).await;
+    assert_eq!(client.chat_text(vec![Message::new(Role::User, "Hello")]).await.unwrap(), "Hi there");
+}
+
+#[tokio::test]
+async fn test_anthropic_stream_round_trip() {
+    let path = cassette_path("anthropic_stream");
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::sse(&[
+        ("message_start", json!({ "type": "message_start", "message": { "id": "msg_1", "model": "claude-3-5-haiku-20241022", "usage": { "input_tokens": 25, "output_tokens": 1 } } })),
+        ("content_block_start", json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } })),
+        ("content_block_delta", json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Bonjour" } })),
+        ("content_block_stop", json!({ "type": "content_block_stop", "index": 0 })),
+        ("message_delta", json!({ "type": "message_delta", "delta": { "stop_reason": "end_turn" }, "usage": { "output_tokens": 3 } })),
+        ("message_stop", json!({ "type": "message_stop" })),
+    ]));
+    let provider = RecordingProvider::start(&path, server.url(), |base_url| {
+        AnthropicProvider::builder(API_KEY).base_url(base_url).build()
+    })
+    .await
+    .unwrap();
+    let client = Client::new(Box::new(provider));
+    let recorded = stream::collect(client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap()).await.unwrap();
+    assert_eq!(recorded.content(), "Bonjour");
+
+    let (contents, cassette) = read_cassette(&path);
+    assert!(!contents.contains(API_KEY));
+    assert_eq!(header_values(&cassette["interactions"][0]["request"]["headers"], "x-api-key"), ["REDACTED"]);
+    drop(server);
+
+    let provider = ReplayProvider::start(&path, |base_url| {
+        AnthropicProvider::builder("placeholder").base_url(base_url).build()
+    })
+    .await
+    .unwrap();
+    let client = Client::new(Box::new(provider));
+    let replayed = stream::collect(client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap()).await.unwrap();
+    assert_eq!(replayed.content(), "Bonjour");
+    assert_eq!(replayed.usage, Some(Usage { input_tokens: 25, output_tokens: 3 }));
+}
+
+#[tokio::test]
+async fn test_bodies_split_inside_characters_and_secrets_are_redacted() {
+    let path = cassette_path("split");
+    let echo = TestResponse::json(401, json!({
+        "error": { "message": format!("Clé invalide : {} — réessayez", API_KEY), "type": "invalid_request_error" }
+    }))
+    .chunk_size(3);
+    let binary = TestResponse::raw(500, "application/octet-stream", vec![[b"\xff\xfe".as_slice(), API_KEY.as_bytes()].concat()])
+        .chunk_size(5);
+    let (_server, client) = record_openai(&path, vec![echo, binary]).await;
+
+    client.chat(vec![Message::new(Role::User, "Hello")]).await.unwrap_err();
+    client.chat(vec![Message::new(Role::User, "Binary")]).await.unwrap_err();
+
+    let (contents, cassette) = read_cassette(&path);
+    assert!(!contents.contains(API_KEY), "{}", contents);
+    let chunks = cassette["interactions"][0]["response"]["body"].as_array().unwrap();
+    let body: String = chunks.iter().map(|chunk| chunk.as_str().unwrap()).collect();
+    assert!(body.contains("Clé invalide : REDACTED — réessayez"), "{}", body);
+    let chunks = cassette["interactions"][1]["response"]["body_base64"].as_array().unwrap();
+    let body: Vec<u8> = chunks.iter().flat_map(|chunk| STANDARD.decode(chunk.as_str().unwrap()).unwrap()).collect();
+    assert_eq!(body, b"\xff\xfeREDACTED");
+
+    let client = replay_openai(&path).await;
+    let error = client.chat(vec![Message::new(Role::User, "Hello")]).await.unwrap_err();
+    assert!(error.to_string().contains("Clé invalide : REDACTED — réessayez"), "{}", error);
+}
+
+#[tokio::test]
+async fn test_failed_upstream_bodies_fail_and_are_not_recorded() {
+    // An upstream that promises more body than it sends before hanging up.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0u8; 4096];
+        let _ = socket.read(&mut request).await;
+        let chunk = format!("data: {}\n\n", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": { "content": "Hel" } }] }));
+        let head = format!("HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\n\r\n", chunk.len() + 100);
+        socket.write_all(head.as_bytes()).await.unwrap();
+        socket.write_all(chunk.as_bytes()).await.unwrap();
+    });
+    let path = cassette_path("failed_upstream");
+    let provider = RecordingProvider::start(&path, &upstream, |base_url| {
+        OpenAIProvider::builder(API_KEY).base_url(base_url).build()
+    })
+    .await
+    .unwrap();
+    let client = Client::new(Box::new(provider));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hello")]).await.unwrap();
+    let error = stream::collect(stream).await.unwrap_err();
+
+    assert!(error.to_string().contains("body error"), "{}", error);
+    let (_, cassette) = read_cassette(&path);
+    assert_eq!(cassette["interactions"], json!([]));
+}
+
+#[tokio::test]
+async fn test_missing_cassette_is_a_configuration_error() {
+    let result = ReplayProvider::start(cassette_path("does_not_exist"), |base_url| {
+        OpenAIProvider::builder("placeholder").base_url(base_url).build()
+    })
+    .await;
+
+    assert!(matches!(result, Err(Error::Configuration(message)) if message.contains("does_not_exist.json")));
+}
@@ -0,0 +1,140 @@
+use futures::StreamExt;
+use inklings::{
+    provider::{ChaosProvider, Latency, MockProvider, Provider},
+    types::{ApiError, ChatOptions, Error, Message, StreamEvent},
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+fn api_error(status: u16) -> ApiError {
+    ApiError { status: Some(status), error_type: None, message: "injected".to_string(), request_id: None, retry_after: None }
+}
+
+fn chunks() -> MockProvider {
+    MockProvider::with_stream_response(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+}
+
+async fn outcomes(provider: &ChaosProvider<MockProvider>, calls: usize) -> Vec<Option<u16>> {
+    let mut outcomes = Vec::new();
+    for _ in 0..calls {
+        let result = provider.chat(vec![Message::user("Hi")], &ChatOptions::default()).await;
+        outcomes.push(result.err().map(|error| error.status().unwrap()));
+    }
+    outcomes
+}
+
+#[tokio::test]
+async fn test_seeded_errors_are_reproducible() {
+    let chaos = |seed| {
+        ChaosProvider::new(MockProvider::new("ok".to_string()))
+            .seed(seed)
+            .error_rate(0.2, Error::RateLimited(api_error(429)))
+            .error_rate(0.1, Error::ServerError(api_error(500)))
+    };
+
+    let first = outcomes(&chaos(7), 200).await;
+    let second = outcomes(&chaos(7), 200).await;
+    let other_seed = outcomes(&chaos(8), 200).await;
+
+    assert_eq!(first, second);
+    assert_ne!(first, other_seed);
+    let rate_limited = first.iter().filter(|outcome| **outcome == Some(429)).count();
+    let server_errors = first.iter().filter(|outcome| **outcome == Some(500)).count();
+    assert!((20..=60).contains(&rate_limited), "{}", rate_limited);
+    assert!((5..=35).contains(&server_errors), "{}", server_errors);
+}
+
+#[tokio::test]
+async fn test_failed_calls_do_not_reach_the_provider() {
+    let inner = Arc::new(MockProvider::new("ok".to_string()));
+    let provider = ChaosProvider::new(inner.clone()).seed(1).error_rate(1.0, Error::Overloaded(api_error(529)));
+
+    let error = provider.complete("Hi").await.unwrap_err();
+
+    assert!(matches!(error, Error::Overloaded(details) if details.message == "injected"));
+    assert_eq!(inner.calls(), 0);
+
+    let untouched = ChaosProvider::new(inner.clone());
+    assert_eq!(untouched.complete("Hi").await.unwrap(), "ok");
+    assert_eq!(inner.calls(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_latency_distributions() {
+    let fixed = ChaosProvider::new(MockProvider::new("ok".to_string())).latency(Latency::Fixed(Duration::from_millis(300)));
+    let started = Instant::now();
+    fixed.complete("Hi").await.unwrap();
+    assert_eq!(started.elapsed(), Duration::from_millis(300));
+
+    let (min, max) = (Duration::from_millis(100), Duration::from_millis(200));
+    let uniform = ChaosProvider::new(MockProvider::new("ok".to_string())).seed(3).latency(Latency::Uniform { min, max });
+    for _ in 0..20 {
+        let started = Instant::now();
+        uniform.complete("Hi").await.unwrap();
+        assert!((min..=max).contains(&started.elapsed()), "{:?}", started.elapsed());
+    }
+
+    let mean = Duration::from_millis(100);
+    let exponential = ChaosProvider::new(MockProvider::new("ok".to_string())).seed(3).latency(Latency::Exponential { mean });
+    let started = Instant::now();
+    for _ in 0..200 {
+        exponential.complete("Hi").await.unwrap();
+    }
+    let average = started.elapsed() / 200;
+    assert!(average > Duration::from_millis(70) && average < Duration::from_millis(130), "{:?}", average);
+}
+
+#[tokio::test]
+async fn test_cut_streams_are_truncated() {
+    let provider = ChaosProvider::new(chunks()).cut_after(2, 1.0);
+
+    let stream = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    let events: Vec<Result<StreamEvent, Error>> = stream.collect().await;
+
+    assert_eq!(events.len(), 3);
+    assert!(matches!(events[1], Ok(StreamEvent::TextDelta(ref text)) if text == "a"));
+    assert!(matches!(events[2], Err(Error::StreamTruncated)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_stalled_streams_hang() {
+    let provider = ChaosProvider::new(chunks()).stall_after(1, 1.0);
+
+    let mut stream = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+
+    assert!(matches!(stream.next().await, Some(Ok(StreamEvent::Start { .. }))));
+    let next = tokio::time::timeout(Duration::from_secs(60), stream.next()).await;
+    assert!(next.is_err());
+}
+
+#[tokio::test]
+async fn test_malformed_chunks_end_the_stream() {
+    let provider = ChaosProvider::new(chunks()).seed(5).malformed_rate(0.5);
+
+    let mut failures = 0;
+    for _ in 0..20 {
+        let stream = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+        let events: Vec<Result<StreamEvent, Error>> = stream.collect().await;
+        if let Some(Err(error)) = events.last() {
+            assert!(matches!(error, Error::ProviderError(message) if message.contains("EOF while parsing")), "{:?}", error);
+            assert_eq!(events.iter().filter(|event| event.is_err()).count(), 1);
+            failures += 1;
+        } else {
+            assert_eq!(events.len(), 6);
+        }
+    }
+    assert!(failures > 10, "{}", failures);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_chunk_latency() {
+    let provider = ChaosProvider::new(chunks()).chunk_latency(Latency::Fixed(Duration::from_millis(10)));
+
+    let started = Instant::now();
+    let stream = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    let events: Vec<Result<StreamEvent, Error>> = stream.collect().await;
+
+    assert_eq!(events.len(), 6);
+    assert_eq!(started.elapsed(), Duration::from_millis(60));
+}
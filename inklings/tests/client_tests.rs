@@ -1,7 +1,7 @@
 use inklings::{
     Client,
     provider::MockProvider,
-    types::{Message, Role, Error},
+    types::{ChatOptions, Message, Role, Error, FinishReason, Tool},
 };
 
 #[tokio::test]
@@ -20,13 +20,10 @@ async fn test_chat() {
     let provider = MockProvider::new(expected_response.clone());
     let client = Client::new(Box::new(provider));
 
-    let messages = vec![Message {
-        role: Role::User,
-        content: "Hi!".to_string(),
-    }];
+    let messages = vec![Message::new(Role::User, "Hi!")];
 
     let result = client.chat(messages).await.unwrap();
-    assert_eq!(result, expected_response);
+    assert_eq!(result.content(), expected_response);
 }
 
 #[tokio::test]
@@ -45,20 +42,55 @@ async fn test_chat_with_multiple_messages() {
     let client = Client::new(Box::new(provider));
 
     let messages = vec![
-        Message {
-            role: Role::System,
-            content: "System prompt".to_string(),
-        },
-        Message {
-            role: Role::User,
-            content: "User message".to_string(),
-        },
-        Message {
-            role: Role::Assistant,
-            content: "Assistant response".to_string(),
-        },
+        Message::new(Role::System, "System prompt"),
+        Message::new(Role::User, "User message"),
+        Message::new(Role::Assistant, "Assistant response"),
     ];
 
     let result = client.chat(messages).await.unwrap();
-    assert_eq!(result, expected_response);
+    assert_eq!(result.content(), expected_response);
+}
+
+#[tokio::test]
+async fn test_chat_with_tools() {
+    let provider = MockProvider::new("No tools needed".to_string());
+    let client = Client::new(Box::new(provider));
+
+    let tools = vec![Tool::new("noop", "Does nothing", serde_json::json!({ "type": "object" }))];
+    let reply = client
+        .chat_with_tools(vec![Message::new(Role::User, "Hi!")], &tools)
+        .await
+        .unwrap();
+
+    assert!(matches!(reply.message.role, Role::Assistant));
+    assert_eq!(reply.content(), "No tools needed");
+    assert!(reply.tool_calls().is_empty());
+}
+
+#[tokio::test]
+async fn test_chat_response_metadata() {
+    let provider = MockProvider::new("Hello".to_string());
+    let client = Client::new(Box::new(provider));
+
+    let response = client.chat(vec![Message::new(Role::User, "Hi!")]).await.unwrap();
+    assert_eq!(response.model, "mock");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert!(!response.is_truncated());
+
+    let text = client.chat_text(vec![Message::new(Role::User, "Hi!")]).await.unwrap();
+    assert_eq!(text, "Hello");
+}
+
+#[tokio::test]
+async fn test_chat_with_options() {
+    let provider = MockProvider::new("Configured".to_string());
+    let options = ChatOptions::new().temperature(0.2).max_tokens(64).seed(7);
+    let client = Client::new(Box::new(provider)).with_options(options.clone());
+
+    let response = client
+        .chat_with_options(vec![Message::new(Role::User, "Hi!")], &options)
+        .await
+        .unwrap();
+    assert_eq!(response.content(), "Configured");
+    assert!(client.unsupported_options(&options).is_empty());
 }
@@ -0,0 +1,284 @@
+mod common;
+
+use common::serve;
+use futures::StreamExt;
+use inklings::{
+    Agent, Client,
+    provider::{CohereProvider, Provider},
+    testing::{TestResponse, TestServer},
+    types::{ChatOptions, ContentPart, Error, FinishReason, Message, Role, StreamEvent, Tool, ToolResult, Usage},
+};
+use serde_json::json;
+
+fn api_key() -> String {
+    std::env::var("COHERE_API_KEY").expect("COHERE_API_KEY not set")
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_cohere_specific_response() {
+    common::test_specific_response(CohereProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_cohere_stream_specific_response() {
+    common::test_stream_specific_response(CohereProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_cohere_tool_call() {
+    common::test_tool_call(CohereProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_cohere_response_metadata() {
+    common::test_response_metadata(CohereProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_cohere_max_tokens_truncation() {
+    common::test_max_tokens_truncation(CohereProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_cohere_system_prompt() {
+    common::test_system_prompt(CohereProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_cohere_stream_collect() {
+    common::test_stream_collect(CohereProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_cohere_stream_tool_call() {
+    common::test_stream_tool_call(CohereProvider::new(api_key(), None)).await;
+}
+
+fn server_provider(server: &TestServer) -> CohereProvider {
+    CohereProvider::builder("test_key")
+        .base_url(server.url())
+        .build()
+        .unwrap()
+}
+
+fn chat_response(text: &str, tool_calls: serde_json::Value) -> serde_json::Value {
+    json!({
+        "response_id": "5f7c1b2e-1d4a-4a9e-9b1e-2c3d4e5f6a7b",
+        "text": text,
+        "generation_id": "0d1e2f3a-4b5c-6d7e-8f9a-0b1c2d3e4f5a",
+        "finish_reason": "COMPLETE",
+        "tool_calls": tool_calls,
+        "meta": { "billed_units": { "input_tokens": 20, "output_tokens": 5 } }
+    })
+}
+
+#[tokio::test]
+async fn test_cohere_chat_history_and_preamble() {
+    let server = serve(vec![TestResponse::json(200, chat_response("pineapple", json!(null)))]).await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let options = ChatOptions::new().temperature(0.3).top_p(0.9).max_tokens(20).stop(vec!["END".to_string()]);
+    let messages = vec![
+        Message::new(Role::System, "Be brief"),
+        Message::new(Role::User, "Name a fruit"),
+        Message::new(Role::Assistant, "Apple"),
+        Message::new(Role::User, "Another one"),
+    ];
+    let response = client.chat_with_options(messages, &options).await.unwrap();
+
+    assert_eq!(response.content(), "pineapple");
+    assert_eq!(response.id.as_deref(), Some("5f7c1b2e-1d4a-4a9e-9b1e-2c3d4e5f6a7b"));
+    assert_eq!(response.model, "command-r");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 20, output_tokens: 5 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/chat");
+    assert_eq!(request.header("authorization"), Some("Bearer test_key"));
+    let body = request.json();
+    assert_eq!(body["preamble"], "Be brief");
+    assert_eq!(body["message"], "Another one");
+    assert_eq!(body["chat_history"], json!([
+        { "role": "USER", "message": "Name a fruit" },
+        { "role": "CHATBOT", "message": "Apple" },
+    ]));
+    assert_eq!(body["temperature"], 0.3);
+    assert_eq!(body["p"], 0.9);
+    assert_eq!(body["max_tokens"], 20);
+    assert_eq!(body["stop_sequences"], json!(["END"]));
+    assert_eq!(body["stream"], false);
+}
+
+#[tokio::test]
+async fn test_cohere_tool_calls_round_trip() {
+    let server = serve(vec![
+        TestResponse::json(200, chat_response(
+            "I will look up the weather.",
+            json!([{ "name": "get_weather", "parameters": { "city": "Paris" } }]),
+        )),
+        TestResponse::json(200, chat_response("It is sunny.", json!(null))),
+    ])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+    let tools = vec![Tool::new("get_weather", "Get the weather", json!({
+        "type": "object",
+        "properties": { "city": { "type": "string", "description": "City name" }, "days": { "type": "integer" } },
+        "required": ["city"]
+    }))];
+
+    let mut messages = vec![Message::new(Role::User, "Weather in Paris?")];
+    let reply = client.chat_with_tools(messages.clone(), &tools).await.unwrap();
+
+    assert_eq!(reply.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(reply.tool_calls()[0].name, "get_weather");
+    assert_eq!(reply.tool_calls()[0].arguments, json!({ "city": "Paris" }));
+
+    let id = reply.tool_calls()[0].id.clone();
+    messages.push(reply.message);
+    messages.push(Message::tool_results(vec![ToolResult::new(id, "Sunny")]));
+    client.chat_with_tools(messages, &tools).await.unwrap();
+
+    let body = server.requests()[1].json();
+    assert_eq!(body["tools"][0]["parameter_definitions"], json!({
+        "city": { "type": "str", "description": "City name", "required": true },
+        "days": { "type": "int", "required": false },
+    }));
+    assert_eq!(body["message"], "");
+    assert_eq!(body["chat_history"][1], json!({
+        "role": "CHATBOT",
+        "message": "I will look up the weather.",
+        "tool_calls": [{ "name": "get_weather", "parameters": { "city": "Paris" } }]
+    }));
+    assert_eq!(body["tool_results"], json!([{
+        "call": { "name": "get_weather", "parameters": { "city": "Paris" } },
+        "outputs": [{ "output": "Sunny" }]
+    }]));
+}
+
+#[tokio::test]
+async fn test_cohere_tool_results_answer_the_latest_call() {
+    // Cohere numbers the calls of every response from `call_0`.
+    let server = serve(vec![
+        TestResponse::json(200, chat_response("", json!([{ "name": "get_weather", "parameters": { "city": "Paris" } }]))),
+        TestResponse::json(200, chat_response("", json!([{ "name": "get_time", "parameters": { "zone": "CET" } }]))),
+        TestResponse::json(200, chat_response("Sunny, and it is 14:05.", json!(null))),
+    ])
+    .await;
+    let agent = Agent::new(Client::new(Box::new(server_provider(&server))))
+        .tool(Tool::new("get_weather", "Get the weather", json!({ "type": "object" })), |_| async { Ok("Sunny".to_string()) })
+        .tool(Tool::new("get_time", "Get the local time", json!({ "type": "object" })), |_| async { Ok("14:05".to_string()) });
+
+    let run = agent.run(vec![Message::new(Role::User, "Weather and time in Paris?")]).await.unwrap();
+
+    assert_eq!(run.iterations(), 3);
+    let ids: Vec<&str> = run.tool_calls().map(|(call, _)| call.id.as_str()).collect();
+    assert_eq!(ids, vec!["call_0", "call_0"]);
+    let body = server.requests()[2].json();
+    assert_eq!(body["chat_history"][2], json!({
+        "role": "TOOL",
+        "tool_results": [{ "call": { "name": "get_weather", "parameters": { "city": "Paris" } }, "outputs": [{ "output": "Sunny" }] }]
+    }));
+    assert_eq!(body["tool_results"], json!([{
+        "call": { "name": "get_time", "parameters": { "zone": "CET" } },
+        "outputs": [{ "output": "14:05" }]
+    }]));
+}
+
+fn stream_lines() -> Vec<serde_json::Value> {
+    vec![
+        json!({ "is_finished": false, "event_type": "stream-start", "generation_id": "gen-1" }),
+        json!({ "is_finished": false, "event_type": "text-generation", "text": "Hel" }),
+        json!({ "is_finished": false, "event_type": "text-generation", "text": "lo" }),
+        json!({ "is_finished": false, "event_type": "tool-calls-chunk", "tool_call_delta": { "index": 0, "name": "lookup" } }),
+        json!({ "is_finished": false, "event_type": "tool-calls-chunk", "tool_call_delta": { "index": 0, "parameters": "{\"q\":" } }),
+        json!({ "is_finished": false, "event_type": "tool-calls-chunk", "tool_call_delta": { "index": 0, "parameters": "1}" } }),
+        json!({ "is_finished": false, "event_type": "tool-calls-generation", "tool_calls": [{ "name": "lookup", "parameters": { "q": 1 } }] }),
+        json!({
+            "is_finished": true,
+            "event_type": "stream-end",
+            "finish_reason": "COMPLETE",
+            "response": { "response_id": "resp-1", "text": "Hello", "meta": { "billed_units": { "input_tokens": 8, "output_tokens": 6 } } }
+        }),
+    ]
+}
+
+#[tokio::test]
+async fn test_cohere_stream_events() {
+    let server = serve(vec![TestResponse::ndjson(&stream_lines())]).await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let events: Vec<StreamEvent> = client
+        .stream_chat(vec![Message::new(Role::User, "Hi")])
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert_eq!(events[0], StreamEvent::Start { id: Some("gen-1".to_string()), model: "command-r".to_string(), provider: None });
+    assert_eq!(events.last(), Some(&StreamEvent::Done));
+
+    let mut accumulator = inklings::stream::StreamAccumulator::new();
+    events.iter().for_each(|event| accumulator.push(event));
+    let response = accumulator.finish();
+    assert_eq!(response.content(), "Hello");
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls()[0].name, "lookup");
+    assert_eq!(response.tool_calls()[0].arguments, json!({ "q": 1 }));
+    assert_eq!(response.usage, Some(Usage { input_tokens: 8, output_tokens: 6 }));
+    assert_eq!(server.requests()[0].json()["stream"], true);
+}
+
+#[tokio::test]
+async fn test_cohere_stream_truncation() {
+    let mut lines = stream_lines();
+    lines.truncate(3);
+    let server = serve(vec![TestResponse::ndjson(&lines)]).await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let result = inklings::stream::collect(stream).await;
+    assert!(matches!(result, Err(Error::StreamTruncated)));
+}
+
+#[tokio::test]
+async fn test_cohere_stream_failed_generation() {
+    for reason in ["ERROR", "ERROR_TOXIC", "ERROR_LIMIT"] {
+        let mut lines = stream_lines();
+        lines.last_mut().unwrap()["finish_reason"] = json!(reason);
+        let server = serve(vec![TestResponse::ndjson(&lines)]).await;
+        let client = Client::new(Box::new(server_provider(&server)));
+
+        let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+        let error = inklings::stream::collect(stream).await.unwrap_err();
+        assert!(matches!(&error, Error::ProviderError(message) if message.contains(reason)), "{:?}", error);
+    }
+}
+
+#[tokio::test]
+async fn test_cohere_error_response() {
+    let server = serve(vec![TestResponse::json(401, json!({ "message": "invalid api token" }))]).await;
+
+    let error = server_provider(&server).complete("Hi").await.unwrap_err();
+
+    assert!(matches!(error, Error::Authentication(_)));
+    assert_eq!(error.api_error().unwrap().message, "invalid api token");
+}
+
+#[tokio::test]
+async fn test_cohere_rejects_images() {
+    let server = serve(vec![]).await;
+
+    let message = Message::with_parts(Role::User, vec![ContentPart::text("Describe"), ContentPart::image_url("https://example.com/a.png")]);
+    let error = server_provider(&server).chat(vec![message], &ChatOptions::default()).await.unwrap_err();
+
+    assert!(matches!(error, Error::Unsupported(_)));
+    assert!(server.requests().is_empty());
+}
@@ -0,0 +1,104 @@
+use inklings::types::Error;
+use reqwest::header::{HeaderMap, HeaderValue};
+use std::time::Duration;
+
+fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in pairs {
+        headers.insert(*name, HeaderValue::from_static(value));
+    }
+    headers
+}
+
+#[test]
+fn test_openai_authentication_error() {
+    let body = r#"{"error": {"message": "Incorrect API key provided", "type": "invalid_request_error", "code": "invalid_api_key"}}"#;
+    let error = Error::from_response_parts(401, &headers(&[("x-request-id", "req_123")]), body);
+
+    assert!(matches!(error, Error::Authentication(_)));
+    assert_eq!(error.status(), Some(401));
+    assert_eq!(error.request_id(), Some("req_123"));
+    assert_eq!(error.api_error().unwrap().error_type.as_deref(), Some("invalid_api_key"));
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_anthropic_rate_limit_with_retry_after() {
+    let body = r#"{"type": "error", "error": {"type": "rate_limit_error", "message": "Number of request tokens has exceeded your per-minute rate limit"}}"#;
+    let error = Error::from_response_parts(429, &headers(&[("retry-after", "20"), ("request-id", "req_abc")]), body);
+
+    assert!(matches!(error, Error::RateLimited(_)));
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(20)));
+    assert_eq!(error.request_id(), Some("req_abc"));
+    assert!(error.is_retryable());
+}
+
+#[test]
+fn test_retry_after_ms_takes_precedence() {
+    let body = r#"{"error": {"message": "Rate limit reached", "type": "requests", "code": "rate_limit_exceeded"}}"#;
+    let error = Error::from_response_parts(429, &headers(&[("retry-after", "1"), ("retry-after-ms", "250")]), body);
+
+    assert_eq!(error.retry_after(), Some(Duration::from_millis(250)));
+}
+
+#[test]
+fn test_unrepresentable_retry_after_is_ignored() {
+    let body = r#"{"error": {"message": "Rate limit reached", "type": "requests", "code": "rate_limit_exceeded"}}"#;
+    for value in ["inf", "-inf", "NaN", "1e30"] {
+        let error = Error::from_response_parts(429, &headers(&[("retry-after", value)]), body);
+        assert!(matches!(error, Error::RateLimited(_)));
+        assert_eq!(error.retry_after(), None, "retry-after: {}", value);
+    }
+
+    // An unusable `retry-after-ms` leaves `retry-after` to answer.
+    let error = Error::from_response_parts(429, &headers(&[("retry-after", "3"), ("retry-after-ms", "inf")]), body);
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(3)));
+}
+
+#[test]
+fn test_insufficient_quota_is_not_retryable() {
+    let body = r#"{"error": {"message": "You exceeded your current quota", "type": "insufficient_quota", "code": "insufficient_quota"}}"#;
+    let error = Error::from_response_parts(429, &HeaderMap::new(), body);
+
+    assert!(matches!(error, Error::QuotaExceeded(_)));
+    assert!(!error.is_retryable());
+}
+
+#[test]
+fn test_context_length_exceeded() {
+    let openai = r#"{"error": {"message": "This model's maximum context length is 128000 tokens.", "type": "invalid_request_error", "code": "context_length_exceeded"}}"#;
+    let anthropic = r#"{"type": "error", "error": {"type": "invalid_request_error", "message": "prompt is too long: 210000 tokens > 200000 maximum"}}"#;
+
+    assert!(matches!(Error::from_response_parts(400, &HeaderMap::new(), openai), Error::ContextLengthExceeded(_)));
+    assert!(matches!(Error::from_response_parts(400, &HeaderMap::new(), anthropic), Error::ContextLengthExceeded(_)));
+}
+
+#[test]
+fn test_content_filtered() {
+    let body = r#"{"error": {"message": "Your request was rejected by the safety system", "type": "invalid_request_error", "code": "content_policy_violation"}}"#;
+    let error = Error::from_response_parts(400, &HeaderMap::new(), body);
+
+    assert!(matches!(error, Error::ContentFiltered(_)));
+}
+
+#[test]
+fn test_overloaded_and_server_errors_are_retryable() {
+    let overloaded = r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#;
+    let error = Error::from_response_parts(529, &HeaderMap::new(), overloaded);
+    assert!(matches!(error, Error::Overloaded(_)));
+    assert!(error.is_retryable());
+
+    let error = Error::from_response_parts(502, &HeaderMap::new(), "<html>Bad Gateway</html>");
+    assert!(matches!(&error, Error::ServerError(details) if details.message == "<html>Bad Gateway</html>"));
+    assert!(error.is_retryable());
+}
+
+#[test]
+fn test_invalid_request() {
+    let body = r#"{"type": "error", "error": {"type": "not_found_error", "message": "model: claude-nonexistent"}}"#;
+    let error = Error::from_response_parts(404, &HeaderMap::new(), body);
+
+    assert!(matches!(error, Error::InvalidRequest(_)));
+    assert!(!error.is_retryable());
+    assert_eq!(error.to_string(), "Invalid request: model: claude-nonexistent (status 404, type not_found_error)");
+}
@@ -0,0 +1,134 @@
+use inklings::{
+    Client,
+    provider::{FallbackProvider, MockProvider, MockReply, Provider},
+    rate_limit::RateLimiter,
+    stream,
+    types::{ApiError, ChatOptions, Error, Message, StreamEvent},
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+fn api_error(status: u16) -> ApiError {
+    ApiError { status: Some(status), error_type: None, message: "failed".to_string(), request_id: None, retry_after: None }
+}
+
+fn mock(replies: Vec<MockReply>) -> Arc<MockProvider> {
+    Arc::new(MockProvider::builder().replies(replies).build())
+}
+
+fn fallback(providers: &[Arc<MockProvider>]) -> FallbackProvider {
+    let providers = providers.iter().map(|p| Box::new(p.clone()) as Box<dyn Provider + Send + Sync>).collect();
+    FallbackProvider::new(providers).names(["anthropic", "openai", "gemini"])
+}
+
+#[tokio::test]
+async fn test_falls_back_on_retryable_errors() {
+    let anthropic = mock(vec![MockReply::error(Error::Overloaded(api_error(529)))]);
+    let openai = mock(vec![MockReply::error(Error::RateLimited(api_error(429)))]);
+    let gemini = mock(vec![MockReply::text("from gemini")]);
+    let client = Client::new(Box::new(fallback(&[anthropic.clone(), openai.clone(), gemini.clone()])));
+
+    let response = client.chat(vec![Message::user("Hi")]).await.unwrap();
+
+    assert_eq!(response.content(), "from gemini");
+    assert_eq!(response.provider.as_deref(), Some("gemini"));
+    assert_eq!((anthropic.calls(), openai.calls(), gemini.calls()), (1, 1, 1));
+    assert_eq!(gemini.last_request().unwrap().last_user_text().as_deref(), Some("Hi"));
+}
+
+#[tokio::test]
+async fn test_first_success_wins() {
+    let anthropic = mock(vec![MockReply::text("from anthropic")]);
+    let openai = mock(vec![MockReply::text("from openai")]);
+    let provider = fallback(&[anthropic.clone(), openai.clone()]);
+
+    let response = provider.chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+
+    assert_eq!(response.provider.as_deref(), Some("anthropic"));
+    assert_eq!(openai.calls(), 0);
+}
+
+#[tokio::test]
+async fn test_unnamed_providers_are_named_by_position() {
+    let providers: Vec<Box<dyn Provider + Send + Sync>> = vec![
+        Box::new(MockProvider::with_error("down".to_string())),
+        Box::new(MockProvider::new("up".to_string())),
+    ];
+    let provider = FallbackProvider::new(providers).fallback_if(|_| true);
+
+    let response = provider.chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+
+    assert_eq!(response.provider.as_deref(), Some("1"));
+}
+
+#[tokio::test]
+async fn test_non_retryable_and_last_errors_are_returned() {
+    let anthropic = mock(vec![MockReply::error(Error::InvalidRequest(api_error(400)))]);
+    let openai = mock(vec![MockReply::text("from openai")]);
+    let provider = fallback(&[anthropic, openai.clone()]);
+
+    let error = provider.chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap_err();
+    assert!(matches!(error, Error::InvalidRequest(_)));
+    assert_eq!(openai.calls(), 0);
+
+    let anthropic = mock(vec![MockReply::error(Error::Overloaded(api_error(529)))]);
+    let openai = mock(vec![MockReply::error(Error::ServerError(api_error(503)))]);
+    let provider = fallback(&[anthropic, openai]);
+
+    let error = provider.complete("Hi").await.unwrap_err();
+    assert!(matches!(error, Error::ServerError(details) if details.status == Some(503)));
+
+    let error = FallbackProvider::new(Vec::new()).complete("Hi").await.unwrap_err();
+    assert!(matches!(error, Error::Configuration(_)));
+}
+
+#[tokio::test]
+async fn test_stream_falls_back_before_first_chunk() {
+    let anthropic = mock(vec![MockReply::error(Error::Overloaded(api_error(529)))]);
+    let openai = mock(vec![MockReply::events(vec![Err(Error::StreamTruncated)])]);
+    let gemini = mock(vec![MockReply::chunks(["Hel", "lo"])]);
+    let provider = fallback(&[anthropic, openai.clone(), gemini]);
+
+    let events = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    let response = stream::collect(events).await.unwrap();
+
+    assert_eq!(response.content(), "Hello");
+    assert_eq!(response.provider.as_deref(), Some("gemini"));
+    assert_eq!(openai.calls(), 1);
+}
+
+#[tokio::test]
+async fn test_stream_failure_after_first_chunk_is_not_retried() {
+    let anthropic = mock(vec![MockReply::events(vec![
+        Ok(StreamEvent::Start { id: None, model: "claude".to_string(), provider: None }),
+        Ok(StreamEvent::TextDelta("Hel".to_string())),
+        Err(Error::Overloaded(api_error(529))),
+    ])]);
+    let openai = mock(vec![MockReply::chunks(["Hello"])]);
+    let provider = fallback(&[anthropic, openai.clone()]);
+
+    let events = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    let error = stream::collect(events).await.unwrap_err();
+
+    assert!(matches!(error, Error::Overloaded(_)));
+    assert_eq!(openai.calls(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_providers_wait_for_their_own_budgets() {
+    let anthropic = Arc::new(MockProvider::builder().fallback(MockReply::error(Error::Overloaded(api_error(529)))).build());
+    let openai = Arc::new(MockProvider::new("Hello".to_string()));
+    let provider = fallback(&[anthropic.clone(), openai.clone()])
+        .rate_limiters([RateLimiter::new().requests_per_minute(1), RateLimiter::new()]);
+
+    let started = Instant::now();
+    for _ in 0..2 {
+        let response = provider.chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+        assert_eq!(response.provider.as_deref(), Some("openai"));
+    }
+
+    // Anthropic's budget holds one request a minute; OpenAI's own budget is unlimited.
+    assert_eq!(started.elapsed(), Duration::from_secs(60));
+    assert_eq!((anthropic.calls(), openai.calls()), (2, 2));
+}
@@ -0,0 +1,295 @@
+mod common;
+
+use common::serve;
+use inklings::{
+    Agent, Client,
+    provider::{GeminiProvider, Provider},
+    testing::{TestResponse, TestServer},
+    types::{ChatOptions, ContentPart, Error, FinishReason, Message, Role, Tool, ToolResult, Usage},
+};
+use serde_json::json;
+
+fn api_key() -> String {
+    std::env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY not set")
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_gemini_specific_response() {
+    common::test_specific_response(GeminiProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_gemini_stream_specific_response() {
+    common::test_stream_specific_response(GeminiProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_gemini_tool_call() {
+    common::test_tool_call(GeminiProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_gemini_response_metadata() {
+    common::test_response_metadata(GeminiProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_gemini_max_tokens_truncation() {
+    common::test_max_tokens_truncation(GeminiProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_gemini_system_prompt() {
+    common::test_system_prompt(GeminiProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_gemini_stream_collect() {
+    common::test_stream_collect(GeminiProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_gemini_stream_tool_call() {
+    common::test_stream_tool_call(GeminiProvider::new(api_key(), None)).await;
+}
+
+/// Reads a response recorded from the Gemini API.
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(format!("{}/tests/fixtures/gemini/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+fn json_fixture(status: u16, name: &str) -> TestResponse {
+    TestResponse::json(status, serde_json::from_str(&fixture(name)).unwrap())
+}
+
+fn sse_fixture(name: &str) -> TestResponse {
+    let chunks = fixture(name).split_inclusive("\n\n").map(|chunk| chunk.as_bytes().to_vec()).collect();
+    TestResponse::raw(200, "text/event-stream", chunks)
+}
+
+fn server_provider(server: &TestServer) -> GeminiProvider {
+    GeminiProvider::builder("test_key")
+        .base_url(server.url())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_gemini_generate_content() {
+    let server = serve(vec![json_fixture(200, "generate_content.json")]).await;
+    let provider = GeminiProvider::builder("test_key")
+        .model("gemini-1.5-pro")
+        .base_url(server.url())
+        .build()
+        .unwrap();
+    let client = Client::new(Box::new(provider));
+
+    let options = ChatOptions::new().temperature(0.5).max_tokens(100).stop(vec!["END".to_string()]).seed(3);
+    let messages = vec![
+        Message::new(Role::System, "Be brief"),
+        Message::new(Role::User, "Say pineapple"),
+        Message::new(Role::Assistant, "Sure:"),
+    ];
+    let response = client.chat_with_options(messages, &options).await.unwrap();
+
+    assert_eq!(response.content(), "pineapple");
+    assert_eq!(response.id.as_deref(), Some("resp-abc123"));
+    assert_eq!(response.model, "gemini-1.5-flash-002");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 11, output_tokens: 2 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/models/gemini-1.5-pro:generateContent");
+    assert_eq!(request.header("x-goog-api-key"), Some("test_key"));
+    let body = request.json();
+    assert_eq!(body["systemInstruction"], json!({ "parts": [{ "text": "Be brief" }] }));
+    assert_eq!(body["contents"], json!([
+        { "role": "user", "parts": [{ "text": "Say pineapple" }] },
+        { "role": "model", "parts": [{ "text": "Sure:" }] },
+    ]));
+    assert_eq!(body["generationConfig"], json!({
+        "temperature": 0.5,
+        "maxOutputTokens": 100,
+        "stopSequences": ["END"],
+        "seed": 3
+    }));
+}
+
+#[tokio::test]
+async fn test_gemini_sends_inline_and_file_data() {
+    let server = serve(vec![json_fixture(200, "generate_content.json")]).await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let message = Message::with_parts(Role::User, vec![
+        ContentPart::text("Compare these"),
+        ContentPart::image_bytes("image/png", b"\x89PNG"),
+        ContentPart::pdf(b"%PDF-1.7"),
+        ContentPart::document_url("https://generativelanguage.googleapis.com/v1beta/files/abc-123"),
+        ContentPart::image_url("gs://bucket/cat.jpg").media_type("image/jpeg"),
+    ]);
+    client.chat(vec![message]).await.unwrap();
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["contents"][0]["parts"], json!([
+        { "text": "Compare these" },
+        { "inlineData": { "mimeType": "image/png", "data": "iVBORw==" } },
+        { "inlineData": { "mimeType": "application/pdf", "data": "JVBERi0xLjc=" } },
+        { "fileData": { "mimeType": "application/pdf", "fileUri": "https://generativelanguage.googleapis.com/v1beta/files/abc-123" } },
+        { "fileData": { "mimeType": "image/jpeg", "fileUri": "gs://bucket/cat.jpg" } }
+    ]));
+}
+
+#[tokio::test]
+async fn test_gemini_rejects_image_urls_without_a_media_type() {
+    let server = TestServer::start().await;
+    let message = Message::with_parts(Role::User, vec![ContentPart::image_url("gs://bucket/cat.jpg")]);
+
+    let error = server_provider(&server).chat(vec![message], &ChatOptions::default()).await.unwrap_err();
+
+    assert!(matches!(error, Error::Unsupported(_)), "{:?}", error);
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_gemini_function_calls_round_trip() {
+    let server = serve(vec![
+        json_fixture(200, "function_call.json"),
+        json_fixture(200, "generate_content.json"),
+    ])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+    let tools = vec![Tool::new("get_weather", "Get the weather", json!({ "type": "object" }))];
+
+    let mut messages = vec![Message::new(Role::User, "Weather in Paris?")];
+    let reply = client.chat_with_tools(messages.clone(), &tools).await.unwrap();
+
+    assert_eq!(reply.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(reply.tool_calls()[0].name, "get_weather");
+    assert_eq!(reply.tool_calls()[0].arguments, json!({ "city": "Paris" }));
+
+    let id = reply.tool_calls()[0].id.clone();
+    messages.push(reply.message);
+    messages.push(Message::tool_results(vec![ToolResult::new(id, "Sunny")]));
+    client.chat_with_tools(messages, &tools).await.unwrap();
+
+    let body = server.requests()[1].json();
+    assert_eq!(body["tools"], json!([{ "functionDeclarations": [{
+        "name": "get_weather",
+        "description": "Get the weather",
+        "parameters": { "type": "object" }
+    }] }]));
+    assert_eq!(body["contents"][1], json!({
+        "role": "model",
+        "parts": [{ "functionCall": { "name": "get_weather", "args": { "city": "Paris" } } }]
+    }));
+    assert_eq!(body["contents"][2], json!({
+        "role": "user",
+        "parts": [{ "functionResponse": { "name": "get_weather", "response": { "content": "Sunny" } } }]
+    }));
+}
+
+#[tokio::test]
+async fn test_gemini_function_responses_answer_the_latest_call() {
+    // Neither call has an id from Gemini, so both rounds number theirs `call_0`.
+    let time_call = TestResponse::json(200, json!({
+        "candidates": [{
+            "content": { "parts": [{ "functionCall": { "name": "get_time", "args": { "city": "Paris" } } }], "role": "model" },
+            "finishReason": "STOP"
+        }],
+        "modelVersion": "gemini-1.5-flash-002"
+    }));
+    let server = serve(vec![
+        json_fixture(200, "function_call.json"),
+        time_call,
+        json_fixture(200, "generate_content.json"),
+    ])
+    .await;
+    let agent = Agent::new(Client::new(Box::new(server_provider(&server))))
+        .tool(Tool::new("get_weather", "Get the weather", json!({ "type": "object" })), |_| async { Ok("Sunny".to_string()) })
+        .tool(Tool::new("get_time", "Get the local time", json!({ "type": "object" })), |_| async { Ok("14:05".to_string()) });
+
+    let run = agent.run(vec![Message::new(Role::User, "Weather and time in Paris?")]).await.unwrap();
+
+    assert_eq!(run.iterations(), 3);
+    let ids: Vec<&str> = run.tool_calls().map(|(call, _)| call.id.as_str()).collect();
+    assert_eq!(ids, vec!["call_0", "call_0"]);
+    let contents = server.requests()[2].json()["contents"].clone();
+    assert_eq!(contents[2]["parts"][0]["functionResponse"], json!({ "name": "get_weather", "response": { "content": "Sunny" } }));
+    assert_eq!(contents[4]["parts"][0]["functionResponse"], json!({ "name": "get_time", "response": { "content": "14:05" } }));
+}
+
+#[tokio::test]
+async fn test_gemini_safety_blocks_are_errors() {
+    let server = serve(vec![
+        json_fixture(200, "safety_block.json"),
+        json_fixture(200, "prompt_blocked.json"),
+    ])
+    .await;
+    let provider = server_provider(&server);
+
+    let error = provider.complete("Something dangerous").await.unwrap_err();
+    assert!(matches!(&error, Error::ContentFiltered(details) if details.error_type.as_deref() == Some("SAFETY")));
+
+    let error = provider.complete("Something prohibited").await.unwrap_err();
+    assert!(matches!(&error, Error::ContentFiltered(details) if details.error_type.as_deref() == Some("PROHIBITED_CONTENT")));
+    assert!(!error.is_retryable());
+}
+
+#[tokio::test]
+async fn test_gemini_stream_events() {
+    let server = serve(vec![sse_fixture("stream.sse")]).await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let response = inklings::stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Hello!");
+    assert_eq!(response.id.as_deref(), Some("resp-stream1"));
+    assert_eq!(response.model, "gemini-1.5-flash-002");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 4, output_tokens: 3 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/models/gemini-1.5-flash:streamGenerateContent?alt=sse");
+}
+
+#[tokio::test]
+async fn test_gemini_stream_safety_block_and_truncation() {
+    let fixture = fixture("stream.sse");
+    let chunks: Vec<&str> = fixture.split_inclusive("\n\n").collect();
+    let blocked = format!(
+        "{}data: {}\n\n",
+        chunks[0],
+        json!({ "candidates": [{ "finishReason": "SAFETY" }], "modelVersion": "gemini-1.5-flash-002" })
+    );
+    let blocked_response = TestResponse::raw(200, "text/event-stream", vec![blocked.into_bytes()]);
+    let truncated_response = sse_fixture("stream.sse").cut_after(2);
+    let server = serve(vec![blocked_response, truncated_response]).await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let result = inklings::stream::collect(stream).await;
+    assert!(matches!(result, Err(Error::ContentFiltered(_))));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let result = inklings::stream::collect(stream).await;
+    assert!(matches!(result, Err(Error::StreamTruncated)));
+}
+
+#[tokio::test]
+async fn test_gemini_error_response() {
+    let server = serve(vec![json_fixture(400, "invalid_argument.json")]).await;
+
+    let error = server_provider(&server).complete("Hi").await.unwrap_err();
+
+    assert!(matches!(&error, Error::InvalidRequest(details) if details.error_type.as_deref() == Some("INVALID_ARGUMENT")));
+    assert_eq!(error.status(), Some(400));
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use common::serve;
+use inklings::{
+    Client,
+    provider::{MistralProvider, Provider},
+    testing::{TestResponse, TestServer},
+    types::{ChatOptions, Error, FinishReason, Message, Role, Usage},
+};
+use serde_json::json;
+
+fn api_key() -> String {
+    std::env::var("MISTRAL_API_KEY").expect("MISTRAL_API_KEY not set")
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_mistral_specific_response() {
+    common::test_specific_response(MistralProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_mistral_stream_specific_response() {
+    common::test_stream_specific_response(MistralProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_mistral_tool_call() {
+    common::test_tool_call(MistralProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_mistral_response_metadata() {
+    common::test_response_metadata(MistralProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_mistral_max_tokens_truncation() {
+    common::test_max_tokens_truncation(MistralProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_mistral_system_prompt() {
+    common::test_system_prompt(MistralProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_mistral_stream_collect() {
+    common::test_stream_collect(MistralProvider::new(api_key(), None)).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires API key
+async fn test_mistral_stream_tool_call() {
+    common::test_stream_tool_call(MistralProvider::new(api_key(), None)).await;
+}
+
+fn server_provider(server: &TestServer) -> MistralProvider {
+    MistralProvider::builder("test_key")
+        .base_url(server.url())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_mistral_chat_request() {
+    let server = serve(vec![TestResponse::json(200, json!({
+        "id": "cmpl-e5cc70bb28c444948073e77776eb30ef",
+        "object": "chat.completion",
+        "model": "mistral-small-latest",
+        "choices": [{ "index": 0, "message": { "role": "assistant", "content": "pineapple" }, "finish_reason": "stop" }],
+        "usage": { "prompt_tokens": 10, "completion_tokens": 3, "total_tokens": 13 }
+    }))])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let options = ChatOptions::new().temperature(0.3).seed(42);
+    let response = client.chat_with_options(vec![Message::new(Role::User, "Say pineapple")], &options).await.unwrap();
+
+    assert_eq!(response.content(), "pineapple");
+    assert_eq!(response.model, "mistral-small-latest");
+    assert_eq!(response.usage, Some(Usage { input_tokens: 10, output_tokens: 3 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/chat/completions");
+    assert_eq!(request.header("authorization"), Some("Bearer test_key"));
+    let body = request.json();
+    assert_eq!(body["model"], "mistral-small-latest");
+    assert_eq!(body["temperature"], 0.3);
+    assert_eq!(body["random_seed"], 42);
+    assert!(body.get("seed").is_none());
+}
+
+#[tokio::test]
+async fn test_mistral_stream_events() {
+    let server = serve(vec![TestResponse::sse(&[
+        ("", json!({ "id": "cmpl-1", "model": "mistral-small-latest", "choices": [{ "index": 0, "delta": { "role": "assistant", "content": "Hel" }, "finish_reason": null }] })),
+        ("", json!({ "id": "cmpl-1", "model": "mistral-small-latest", "choices": [{ "index": 0, "delta": { "content": "lo" }, "finish_reason": "model_length" }], "usage": { "prompt_tokens": 4, "completion_tokens": 2, "total_tokens": 6 } })),
+        ("", json!("[DONE]")),
+    ])])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let response = inklings::stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Hello");
+    assert_eq!(response.finish_reason, FinishReason::Length);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 4, output_tokens: 2 }));
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["stream"], true);
+    assert!(body.get("stream_options").is_none());
+}
+
+#[tokio::test]
+async fn test_mistral_error_response() {
+    let server = serve(vec![TestResponse::json(
+        401,
+        json!({ "message": "Unauthorized", "request_id": "a1b2c3" }),
+    )])
+    .await;
+
+    let error = server_provider(&server).complete("Hi").await.unwrap_err();
+
+    assert!(matches!(error, Error::Authentication(_)));
+    assert_eq!(error.request_id(), Some("a1b2c3"));
+}
@@ -0,0 +1,148 @@
+use futures::StreamExt;
+use inklings::{
+    Client,
+    provider::{MockCall, MockProvider, MockReply, Provider},
+    stream,
+    types::{ChatOptions, Error, FinishReason, Message, Role, StreamEvent, ToolCall},
+};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[tokio::test]
+async fn test_queue_is_answered_in_order_then_fallback() {
+    let provider = MockProvider::builder()
+        .reply(MockReply::text("first"))
+        .reply(MockReply::error(Error::ProviderError("second".to_string())))
+        .reply(MockReply::chunks(["th", "ird"]))
+        .fallback(MockReply::text("fallback"))
+        .build();
+    let client = Client::new(Box::new(provider));
+
+    assert_eq!(client.complete("1").await.unwrap(), "first");
+    assert!(matches!(client.complete("2").await, Err(Error::ProviderError(message)) if message == "second"));
+    assert_eq!(client.complete("3").await.unwrap(), "third");
+    assert_eq!(client.complete("4").await.unwrap(), "fallback");
+    assert_eq!(client.complete("5").await.unwrap(), "fallback");
+}
+
+#[tokio::test]
+async fn test_rules_take_precedence_over_queue() {
+    let provider = MockProvider::builder()
+        .when_last_user_contains("weather", MockReply::text("Sunny"))
+        .when(|request| request.options.temperature == Some(0.0), MockReply::text("Deterministic"))
+        .reply(MockReply::text("queued"))
+        .build();
+
+    let weather = vec![Message::assistant("Hi"), Message::user("What's the weather?")];
+    let reply = provider.chat(weather, &ChatOptions::default()).await.unwrap();
+    assert_eq!(reply.content(), "Sunny");
+
+    let options = ChatOptions::new().temperature(0.0);
+    let reply = provider.chat(vec![Message::user("Hello")], &options).await.unwrap();
+    assert_eq!(reply.content(), "Deterministic");
+
+    // Only the last user message is matched.
+    let earlier = vec![Message::user("weather?"), Message::assistant("Sunny"), Message::user("Thanks")];
+    let reply = provider.chat(earlier, &ChatOptions::default()).await.unwrap();
+    assert_eq!(reply.content(), "queued");
+}
+
+#[tokio::test]
+async fn test_requests_are_recorded() {
+    let provider = Arc::new(MockProvider::new("ok".to_string()));
+    let client = Client::new(Box::new(provider.clone())).with_options(ChatOptions::new().max_tokens(50));
+
+    provider.complete("Hello").await.unwrap();
+    client.chat(vec![Message::new(Role::System, "Be brief"), Message::user("Hi")]).await.unwrap();
+    let _stream = client.stream_chat(vec![Message::user("Stream")]).await.unwrap();
+
+    let requests = provider.requests();
+    assert_eq!(provider.calls(), 3);
+    assert_eq!(requests[0].call, MockCall::Complete);
+    assert_eq!(requests[0].messages[0].text(), "Hello");
+    assert_eq!(requests[1].call, MockCall::Chat);
+    assert_eq!(requests[1].messages.len(), 2);
+    assert_eq!(requests[1].options.max_tokens, Some(50));
+    assert_eq!(requests[1].last_user_text().as_deref(), Some("Hi"));
+    let last = provider.last_request().unwrap();
+    assert_eq!(last.call, MockCall::StreamChat);
+    assert_eq!(last.last_user_text().as_deref(), Some("Stream"));
+}
+
+#[tokio::test]
+async fn test_replies_can_be_pushed_later() {
+    let provider = Arc::new(MockProvider::builder().fallback(MockReply::text("fallback")).build());
+    let client = Client::new(Box::new(provider.clone()));
+
+    assert_eq!(client.complete("1").await.unwrap(), "fallback");
+    provider.push_reply(MockReply::text("pushed"));
+    assert_eq!(client.complete("2").await.unwrap(), "pushed");
+}
+
+#[tokio::test]
+async fn test_messages_stream_as_tool_call_deltas() {
+    let turn = Message {
+        tool_calls: vec![ToolCall {
+            id: "call_1".to_string(),
+            name: "get_weather".to_string(),
+            arguments: json!({ "city": "Paris" }),
+        }],
+        ..Message::assistant("Checking.")
+    };
+    let provider = MockProvider::builder().reply(MockReply::message(turn)).build();
+
+    let stream = provider.stream_chat(vec![Message::user("Weather?")], &ChatOptions::default()).await.unwrap();
+    let response = stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Checking.");
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls()[0].arguments, json!({ "city": "Paris" }));
+}
+
+#[tokio::test]
+async fn test_scripted_events() {
+    let events = vec![
+        Ok(StreamEvent::Start { id: None, model: "mock".to_string(), provider: None }),
+        Ok(StreamEvent::TextDelta("Hel".to_string())),
+        Err(Error::StreamTruncated),
+    ];
+    let provider = MockProvider::builder().fallback(MockReply::events(events)).build();
+
+    let stream = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    let received: Vec<Result<StreamEvent, Error>> = stream.collect().await;
+    assert_eq!(received.len(), 3);
+    assert!(matches!(received[2], Err(Error::StreamTruncated)));
+
+    let error = provider.chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap_err();
+    assert!(matches!(error, Error::StreamTruncated));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_latency_and_chunk_delay() {
+    let provider = MockProvider::builder()
+        .fallback(MockReply::chunks(["a", "b", "c"]))
+        .latency(Duration::from_millis(200))
+        .chunk_delay(Duration::from_millis(50))
+        .build();
+
+    let started = Instant::now();
+    let stream = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    assert_eq!(started.elapsed(), Duration::from_millis(200));
+
+    let events: Vec<_> = stream.collect().await;
+    assert_eq!(events.len(), 6);
+    assert_eq!(started.elapsed(), Duration::from_millis(200 + 6 * 50));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_no_delay_by_default() {
+    let provider = MockProvider::with_stream_response(vec!["a".to_string(), "b".to_string()]);
+
+    let started = Instant::now();
+    let stream = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    stream::collect(stream).await.unwrap();
+
+    assert_eq!(started.elapsed(), Duration::ZERO);
+}
@@ -0,0 +1,103 @@
+use inklings::{
+    provider::normalize::{normalize, RoleRules, SystemPlacement, LEADING_USER_PLACEHOLDER},
+    types::{ContentPart, Message, Role, ToolResult},
+};
+
+const STRICT: RoleRules = RoleRules {
+    system: SystemPlacement::TopLevel,
+    alternate: true,
+    leading_user: true,
+    drop_empty: true,
+};
+
+#[test]
+fn test_default_rules_leave_messages_untouched() {
+    let messages = vec![
+        Message::new(Role::System, "Be brief"),
+        Message::new(Role::User, "Hi"),
+        Message::new(Role::User, "Anyone there?"),
+    ];
+
+    let normalized = normalize(messages, &RoleRules::default());
+    assert!(normalized.system.is_none());
+    assert_eq!(normalized.messages.len(), 3);
+    assert_eq!(normalized.messages[0].role, Role::System);
+}
+
+#[test]
+fn test_system_messages_are_hoisted_and_merged() {
+    let messages = vec![
+        Message::new(Role::System, "You are a pirate."),
+        Message::new(Role::User, "Hi"),
+        Message::new(Role::Assistant, "Arr!"),
+        Message::new(Role::System, "Now answer in French."),
+        Message::new(Role::User, "How are you?"),
+    ];
+
+    let normalized = normalize(messages, &STRICT);
+    assert_eq!(normalized.system.as_deref(), Some("You are a pirate.\n\nNow answer in French."));
+    let roles: Vec<Role> = normalized.messages.iter().map(|m| m.role).collect();
+    assert_eq!(roles, vec![Role::User, Role::Assistant, Role::User]);
+}
+
+#[test]
+fn test_consecutive_turns_are_merged() {
+    let messages = vec![
+        Message::tool_results(vec![ToolResult::new("call_1", "42")]),
+        Message::tool_results(vec![ToolResult::new("call_2", "43")]),
+        Message::new(Role::User, "Summarise the results"),
+    ];
+
+    let normalized = normalize(messages, &STRICT);
+    assert_eq!(normalized.messages.len(), 1);
+    let merged = &normalized.messages[0];
+    assert_eq!(merged.tool_results.len(), 2);
+    assert_eq!(merged.text(), "Summarise the results");
+}
+
+#[test]
+fn test_leading_assistant_turn_gets_user_placeholder() {
+    let messages = vec![
+        Message::new(Role::System, "Be brief"),
+        Message::new(Role::Assistant, "Hello! How can I help?"),
+        Message::new(Role::User, "Tell me a joke"),
+    ];
+
+    let normalized = normalize(messages, &STRICT);
+    assert_eq!(normalized.messages[0].role, Role::User);
+    assert_eq!(normalized.messages[0].text(), LEADING_USER_PLACEHOLDER);
+    assert_eq!(normalized.messages.len(), 3);
+}
+
+#[test]
+fn test_empty_turns_are_dropped_except_trailing_prefill() {
+    let messages = vec![
+        Message::new(Role::User, "Say 'test'"),
+        Message::new(Role::Assistant, ""),
+        Message::new(Role::User, "Please"),
+        Message::new(Role::Assistant, ""),
+    ];
+
+    let normalized = normalize(messages, &STRICT);
+    let roles: Vec<Role> = normalized.messages.iter().map(|m| m.role).collect();
+    assert_eq!(roles, vec![Role::User, Role::Assistant]);
+    assert_eq!(normalized.messages[0].text(), "Say 'test'\n\nPlease");
+}
+
+#[test]
+fn test_merged_turns_keep_media_parts_in_order() {
+    let messages = vec![
+        Message::with_parts(Role::User, vec![ContentPart::text("First"), ContentPart::image_url("https://example.com/a.png")]),
+        Message::new(Role::User, "Second"),
+        Message::with_parts(Role::User, vec![ContentPart::text("Third"), ContentPart::pdf(b"%PDF")]),
+    ];
+
+    let normalized = normalize(messages, &STRICT);
+    assert_eq!(normalized.messages[0].content, vec![
+        ContentPart::text("First"),
+        ContentPart::image_url("https://example.com/a.png"),
+        ContentPart::text("Second\n\nThird"),
+        ContentPart::pdf(b"%PDF"),
+    ]);
+    assert_eq!(normalized.messages[0].text(), "FirstSecond\n\nThird");
+}
@@ -0,0 +1,316 @@
+mod common;
+
+use common::serve;
+use futures::StreamExt;
+use inklings::{
+    Client,
+    provider::{OllamaProvider, Provider},
+    testing::{TestResponse, TestServer},
+    types::{ChatOptions, ContentPart, Error, FinishReason, Message, Role, Tool, ToolCall, ToolResult, Usage},
+};
+use serde_json::json;
+
+/// A provider for the local Ollama server, using `OLLAMA_MODEL` if set.
+fn live_provider() -> OllamaProvider {
+    OllamaProvider::new(std::env::var("OLLAMA_MODEL").ok())
+}
+
+#[tokio::test]
+#[ignore] // Requires a running Ollama server
+async fn test_ollama_specific_response() {
+    common::test_specific_response(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running Ollama server
+async fn test_ollama_stream_specific_response() {
+    common::test_stream_specific_response(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running Ollama server
+async fn test_ollama_tool_call() {
+    common::test_tool_call(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running Ollama server
+async fn test_ollama_response_metadata() {
+    common::test_response_metadata(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running Ollama server
+async fn test_ollama_max_tokens_truncation() {
+    common::test_max_tokens_truncation(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running Ollama server
+async fn test_ollama_system_prompt() {
+    common::test_system_prompt(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running Ollama server
+async fn test_ollama_stream_collect() {
+    common::test_stream_collect(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running Ollama server
+async fn test_ollama_stream_tool_call() {
+    common::test_stream_tool_call(live_provider()).await;
+}
+
+fn server_provider(server: &TestServer) -> OllamaProvider {
+    OllamaProvider::builder().base_url(server.url()).build().unwrap()
+}
+
+fn chat_response(message: serde_json::Value, done_reason: &str) -> serde_json::Value {
+    json!({
+        "model": "llama3.2",
+        "created_at": "2024-11-01T12:00:00Z",
+        "message": message,
+        "done": true,
+        "done_reason": done_reason,
+        "prompt_eval_count": 26,
+        "eval_count": 4
+    })
+}
+
+#[tokio::test]
+async fn test_ollama_chat_sends_options_and_keep_alive() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        chat_response(json!({ "role": "assistant", "content": "pineapple" }), "stop"),
+    )])
+    .await;
+    let provider = OllamaProvider::builder()
+        .base_url(server.url())
+        .model("qwen2.5")
+        .keep_alive("10m")
+        .option("num_ctx", 8192)
+        .option("mirostat", 2)
+        .option("temperature", 0.9)
+        .build()
+        .unwrap();
+    let client = Client::new(Box::new(provider));
+
+    let options = ChatOptions::new().temperature(0.2).max_tokens(50).seed(7).stop(vec!["END".to_string()]);
+    let messages = vec![
+        Message::new(Role::System, "Be brief"),
+        Message::new(Role::User, "Say pineapple"),
+    ];
+    let response = client.chat_with_options(messages, &options).await.unwrap();
+
+    assert_eq!(response.content(), "pineapple");
+    assert_eq!(response.model, "llama3.2");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 26, output_tokens: 4 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/api/chat");
+    assert_eq!(request.header("authorization"), None);
+    let body = request.json();
+    assert_eq!(body["model"], "qwen2.5");
+    assert_eq!(body["stream"], false);
+    assert_eq!(body["keep_alive"], "10m");
+    assert_eq!(body["messages"][0], json!({ "role": "system", "content": "Be brief" }));
+    assert_eq!(body["options"], json!({
+        "num_ctx": 8192,
+        "mirostat": 2,
+        "temperature": 0.2,
+        "num_predict": 50,
+        "seed": 7,
+        "stop": ["END"]
+    }));
+}
+
+#[tokio::test]
+async fn test_ollama_sends_images() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        chat_response(json!({ "role": "assistant", "content": "A cat." }), "stop"),
+    )])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let message = Message::with_parts(Role::User, vec![ContentPart::text("What is this?"), ContentPart::image_bytes("image/png", b"\x89PNG")]);
+    client.chat(vec![message]).await.unwrap();
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["messages"][0], json!({ "role": "user", "content": "What is this?", "images": ["iVBORw=="] }));
+
+    for part in [ContentPart::image_url("https://example.com/a.png"), ContentPart::pdf(b"%PDF")] {
+        let error = client.chat(vec![Message::with_parts(Role::User, vec![part])]).await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(_)));
+    }
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_ollama_tool_calls_round_trip() {
+    let server = serve(vec![
+        TestResponse::json(200, chat_response(json!({
+            "role": "assistant",
+            "content": "",
+            "tool_calls": [{ "function": { "name": "get_weather", "arguments": { "city": "Paris" } } }]
+        }), "stop")),
+        TestResponse::json(200, chat_response(json!({ "role": "assistant", "content": "Sunny." }), "stop")),
+    ])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+    let tools = vec![Tool::new("get_weather", "Get the weather", json!({ "type": "object" }))];
+
+    let mut messages = vec![Message::new(Role::User, "Weather in Paris?")];
+    let reply = client.chat_with_tools(messages.clone(), &tools).await.unwrap();
+
+    assert_eq!(reply.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(reply.tool_calls(), &[ToolCall {
+        id: "call_0".to_string(),
+        name: "get_weather".to_string(),
+        arguments: json!({ "city": "Paris" }),
+    }]);
+
+    messages.push(reply.message);
+    messages.push(Message::tool_results(vec![ToolResult::new("call_0", "Sunny")]));
+    client.chat_with_tools(messages, &tools).await.unwrap();
+
+    let body = server.requests()[1].json();
+    assert_eq!(body["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(body["messages"][1]["tool_calls"][0]["function"]["arguments"], json!({ "city": "Paris" }));
+    assert_eq!(body["messages"][2], json!({ "role": "tool", "content": "Sunny" }));
+}
+
+#[tokio::test]
+async fn test_ollama_complete_uses_generate() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        json!({ "model": "llama3.2", "response": "Hello there", "done": true }),
+    )])
+    .await;
+    let provider = OllamaProvider::builder()
+        .base_url(server.url())
+        .keep_alive("-1")
+        .build()
+        .unwrap();
+
+    assert_eq!(provider.complete("Hi").await.unwrap(), "Hello there");
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/api/generate");
+    assert_eq!(request.json(), json!({ "model": "llama3.2", "prompt": "Hi", "stream": false, "keep_alive": "-1" }));
+}
+
+#[tokio::test]
+async fn test_ollama_stream_events() {
+    // Split lines across chunk boundaries to exercise the line buffering.
+    let response = TestResponse::ndjson(&[
+        json!({ "model": "llama3.2", "message": { "role": "assistant", "content": "Hel" }, "done": false }),
+        json!({ "model": "llama3.2", "message": { "role": "assistant", "content": "lo" }, "done": false }),
+        json!({ "model": "llama3.2", "message": { "role": "assistant", "content": "" }, "done": true, "done_reason": "length", "prompt_eval_count": 3, "eval_count": 2 }),
+    ])
+    .chunk_size(7);
+    let server = serve(vec![response]).await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let response = inklings::stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Hello");
+    assert_eq!(response.model, "llama3.2");
+    assert_eq!(response.finish_reason, FinishReason::Length);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 3, output_tokens: 2 }));
+    assert_eq!(server.requests()[0].json()["stream"], true);
+}
+
+#[tokio::test]
+async fn test_ollama_stream_error_and_truncation() {
+    let server = serve(vec![
+        TestResponse::ndjson(&[
+            json!({ "model": "llama3.2", "message": { "role": "assistant", "content": "Hel" }, "done": false }),
+            json!({ "error": "an error was encountered while running the model" }),
+        ]),
+        TestResponse::ndjson(&[
+            json!({ "model": "llama3.2", "message": { "role": "assistant", "content": "Hel" }, "done": false }),
+        ]),
+    ])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let result = inklings::stream::collect(stream).await;
+    assert!(matches!(result, Err(Error::ServerError(_))));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let result = inklings::stream::collect(stream).await;
+    assert!(matches!(result, Err(Error::StreamTruncated)));
+}
+
+#[tokio::test]
+async fn test_ollama_list_models() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        json!({ "models": [
+            { "name": "llama3.2:latest", "size": 2019393189, "digest": "a80c4f17acd5" },
+            { "name": "qwen2.5:7b", "size": 4683087332u64, "digest": "845dbda0ea48" }
+        ] }),
+    )])
+    .await;
+
+    let models = server_provider(&server).list_models().await.unwrap();
+
+    assert_eq!(models, vec!["llama3.2:latest", "qwen2.5:7b"]);
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.path, "/api/tags");
+}
+
+#[tokio::test]
+async fn test_ollama_pull_model_progress() {
+    let server = serve(vec![TestResponse::ndjson(&[
+        json!({ "status": "pulling manifest" }),
+        json!({ "status": "pulling a80c4f17acd5", "digest": "sha256:a80c4f17acd5", "total": 2000, "completed": 500 }),
+        json!({ "status": "pulling a80c4f17acd5", "digest": "sha256:a80c4f17acd5", "total": 2000, "completed": 2000 }),
+        json!({ "status": "success" }),
+    ])])
+    .await;
+
+    let progress: Vec<_> = server_provider(&server)
+        .pull_model("llama3.2")
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(progress.len(), 4);
+    assert_eq!(progress[0].fraction(), None);
+    assert_eq!(progress[1].fraction(), Some(0.25));
+    assert_eq!(progress[2].digest.as_deref(), Some("sha256:a80c4f17acd5"));
+    assert_eq!(progress[3].status, "success");
+    assert_eq!(server.requests()[0].path, "/api/pull");
+    assert_eq!(server.requests()[0].json()["model"], "llama3.2");
+}
+
+#[tokio::test]
+async fn test_ollama_error_response() {
+    let server = serve(vec![
+        TestResponse::json(404, json!({ "error": "model \"missing\" not found, try pulling it first" })),
+        TestResponse::ndjson(&[
+            json!({ "status": "pulling manifest" }),
+            json!({ "error": "pull model manifest: file does not exist" }),
+        ]),
+    ])
+    .await;
+    let provider = server_provider(&server);
+
+    let error = provider.complete("Hi").await.unwrap_err();
+    assert!(matches!(error, Error::InvalidRequest(_)));
+    assert_eq!(error.status(), Some(404));
+
+    let progress: Vec<_> = provider.pull_model("missing").await.unwrap().collect().await;
+    assert!(progress[0].is_ok());
+    assert!(progress[1].is_err());
+}
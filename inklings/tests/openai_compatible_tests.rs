@@ -0,0 +1,233 @@
+mod common;
+
+use common::serve;
+use inklings::{
+    Client,
+    provider::{OpenAICompatibleProvider, OpenAICompatibleQuirks, OpenAIProvider, Provider},
+    testing::TestResponse,
+    types::{ChatOptions, Error, FinishReason, Message, Role, Tool},
+};
+use serde_json::json;
+
+/// A provider for the server named by `OPENAI_COMPATIBLE_BASE_URL` and `OPENAI_COMPATIBLE_MODEL`,
+/// authenticated with `OPENAI_COMPATIBLE_API_KEY` when it is set.
+fn live_provider() -> OpenAICompatibleProvider {
+    let base_url = std::env::var("OPENAI_COMPATIBLE_BASE_URL").expect("OPENAI_COMPATIBLE_BASE_URL not set");
+    let model = std::env::var("OPENAI_COMPATIBLE_MODEL").expect("OPENAI_COMPATIBLE_MODEL not set");
+    let mut builder = OpenAICompatibleProvider::builder(base_url, model);
+    if let Ok(api_key) = std::env::var("OPENAI_COMPATIBLE_API_KEY") {
+        builder = builder.api_key(api_key);
+    }
+    builder.build().unwrap()
+}
+
+#[tokio::test]
+#[ignore] // Requires a running OpenAI-compatible server
+async fn test_compatible_specific_response() {
+    common::test_specific_response(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running OpenAI-compatible server
+async fn test_compatible_stream_specific_response() {
+    common::test_stream_specific_response(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running OpenAI-compatible server
+async fn test_compatible_tool_call() {
+    common::test_tool_call(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running OpenAI-compatible server
+async fn test_compatible_response_metadata() {
+    common::test_response_metadata(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running OpenAI-compatible server
+async fn test_compatible_max_tokens_truncation() {
+    common::test_max_tokens_truncation(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running OpenAI-compatible server
+async fn test_compatible_system_prompt() {
+    common::test_system_prompt(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running OpenAI-compatible server
+async fn test_compatible_stream_collect() {
+    common::test_stream_collect(live_provider()).await;
+}
+
+#[tokio::test]
+#[ignore] // Requires a running OpenAI-compatible server
+async fn test_compatible_stream_tool_call() {
+    common::test_stream_tool_call(live_provider()).await;
+}
+
+fn completion(content: &str) -> serde_json::Value {
+    json!({
+        "id": "cmpl-1",
+        "object": "chat.completion",
+        "model": "llama-3.1-8b",
+        "choices": [{ "index": 0, "message": { "role": "assistant", "content": content }, "finish_reason": "stop" }]
+    })
+}
+
+#[tokio::test]
+async fn test_compatible_sends_no_auth_by_default() {
+    let server = serve(vec![TestResponse::json(200, completion("pineapple"))]).await;
+    let provider = OpenAICompatibleProvider::new(format!("{}/v1", server.url()), "llama-3.1-8b");
+    let client = Client::new(Box::new(provider));
+
+    let response = client.chat(vec![Message::new(Role::User, "Say pineapple")]).await.unwrap();
+
+    assert_eq!(response.content(), "pineapple");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, None);
+
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/chat/completions");
+    assert_eq!(request.header("authorization"), None);
+    assert_eq!(request.json()["model"], "llama-3.1-8b");
+}
+
+#[tokio::test]
+async fn test_compatible_api_key_and_headers() {
+    let server = serve(vec![TestResponse::json(200, completion("ok"))]).await;
+    let provider = OpenAICompatibleProvider::builder(server.url(), "mixtral")
+        .api_key("secret")
+        .header("X-Tenant", "acme")
+        .build()
+        .unwrap();
+
+    provider.complete("Hi").await.unwrap();
+
+    let request = &server.requests()[0];
+    assert_eq!(request.header("authorization"), Some("Bearer secret"));
+    assert_eq!(request.header("x-tenant"), Some("acme"));
+}
+
+#[tokio::test]
+async fn test_compatible_quirks_drop_unsupported_fields() {
+    let server = serve(vec![
+        TestResponse::json(200, completion("ok")),
+        TestResponse::sse(&[
+            ("", json!({ "id": "cmpl-2", "model": "llama", "choices": [{ "index": 0, "delta": { "content": "Hi" }, "finish_reason": null }] })),
+            ("", json!({ "id": "cmpl-2", "model": "llama", "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop" }] })),
+            ("", json!("[DONE]")),
+        ]),
+    ])
+    .await;
+    let quirks = OpenAICompatibleQuirks {
+        stream_options: false,
+        seed: false,
+        penalties: false,
+        ..OpenAICompatibleQuirks::default()
+    };
+    let provider = OpenAICompatibleProvider::builder(server.url(), "llama")
+        .quirks(quirks)
+        .build()
+        .unwrap();
+    let tool = Tool::new("lookup", "Look something up", json!({ "type": "object" }));
+    let options = ChatOptions::new()
+        .tools(vec![tool])
+        .temperature(0.5)
+        .seed(42)
+        .presence_penalty(0.1)
+        .frequency_penalty(0.2);
+
+    assert_eq!(provider.unsupported_options(&options), vec!["seed", "presence_penalty", "frequency_penalty"]);
+
+    let client = Client::new(Box::new(provider));
+    client.chat_with_options(vec![Message::new(Role::User, "Hi")], &options).await.unwrap();
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let response = inklings::stream::collect(stream).await.unwrap();
+    assert_eq!(response.content(), "Hi");
+
+    let requests = server.requests();
+    let body = requests[0].json();
+    assert_eq!(body["temperature"], 0.5);
+    assert_eq!(body["tools"][0]["function"]["name"], "lookup");
+    assert!(body.get("seed").is_none());
+    assert!(body.get("presence_penalty").is_none());
+    assert!(body.get("frequency_penalty").is_none());
+
+    let body = requests[1].json();
+    assert_eq!(body["stream"], true);
+    assert!(body.get("stream_options").is_none());
+}
+
+#[tokio::test]
+async fn test_compatible_stream_without_model() {
+    // Some servers leave `model` out of their stream chunks altogether.
+    let server = serve(vec![TestResponse::sse(&[
+        ("", json!({ "id": "cmpl-3", "choices": [{ "index": 0, "delta": { "role": "assistant", "content": "Hel" }, "finish_reason": null }] })),
+        ("", json!({ "id": "cmpl-3", "choices": [{ "index": 0, "delta": { "content": "lo" }, "finish_reason": "stop" }] })),
+        ("", json!("[DONE]")),
+    ])])
+    .await;
+    let client = Client::new(Box::new(OpenAICompatibleProvider::new(server.url(), "llama")));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let response = inklings::stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Hello");
+    assert_eq!(response.id.as_deref(), Some("cmpl-3"));
+    assert_eq!(response.model, "");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+}
+
+#[tokio::test]
+async fn test_compatible_list_models() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        json!({ "object": "list", "data": [{ "id": "llama-3.1-8b", "object": "model" }, { "id": "qwen2.5-7b", "object": "model" }] }),
+    )])
+    .await;
+    let provider = OpenAICompatibleProvider::builder(server.url(), "llama-3.1-8b")
+        .api_key("secret")
+        .build()
+        .unwrap();
+
+    let models = provider.list_models().await.unwrap();
+
+    assert_eq!(models, vec!["llama-3.1-8b", "qwen2.5-7b"]);
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.path, "/models");
+    assert_eq!(request.header("authorization"), Some("Bearer secret"));
+}
+
+#[tokio::test]
+async fn test_openai_list_models() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        json!({ "object": "list", "data": [{ "id": "gpt-4o", "object": "model" }] }),
+    )])
+    .await;
+    let provider = OpenAIProvider::builder("test_key").base_url(server.url()).build().unwrap();
+
+    assert_eq!(provider.list_models().await.unwrap(), vec!["gpt-4o"]);
+}
+
+#[tokio::test]
+async fn test_compatible_error_response() {
+    let server = serve(vec![TestResponse::json(
+        404,
+        json!({ "error": { "message": "model 'missing' not found", "type": "invalid_request_error" } }),
+    )
+    .header("x-request-id", "req_7")])
+    .await;
+    let provider = OpenAICompatibleProvider::new(server.url(), "missing");
+
+    let error = provider.complete("Hi").await.unwrap_err();
+
+    assert!(matches!(error, Error::InvalidRequest(_)));
+    assert_eq!(error.status(), Some(404));
+    assert_eq!(error.request_id(), Some("req_7"));
+}
@@ -1,16 +1,27 @@
 mod common;
 
+use common::serve;
 use inklings::{
     Client,
-    provider::OpenAIProvider,
-    types::{Message, Role},
+    provider::{OpenAIProvider, Provider},
+    testing::{TestResponse, TestServer},
+    types::{ChatOptions, ContentPart, Error, FinishReason, Message, Role, Tool, ToolCall, ToolResult, Usage},
 };
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Replays the recording of a conversation with the live API; see [`common::cassette_provider`].
+async fn cassette(name: &str) -> Arc<dyn Provider + Send + Sync> {
+    common::cassette_provider(&format!("openai/{}", name), "https://api.openai.com/v1", "OPENAI_API_KEY", |api_key, base_url| {
+        OpenAIProvider::builder(api_key).base_url(base_url).build()
+    })
+    .await
+}
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_openai_complete() {
-    let api_key = std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set");
-    let provider = OpenAIProvider::new(api_key, None);
+    let provider = cassette("complete").await;
     let client = Client::new(Box::new(provider));
 
     let result = client.complete("Say 'test'").await;
@@ -18,33 +29,24 @@ async fn test_openai_complete() {
 }
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_openai_specific_response() {
-    let api_key = std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set");
-    let provider = OpenAIProvider::new(api_key, None);
+    let provider = cassette("specific_response").await;
     common::test_specific_response(provider).await;
 }
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_openai_stream_specific_response() {
-    let api_key = std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set");
-    let provider = OpenAIProvider::new(api_key, None);
+    let provider = cassette("stream_specific_response").await;
     common::test_stream_specific_response(provider).await;
 }
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_openai_chat() {
-    let api_key = std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set");
-    let provider = OpenAIProvider::new(api_key, None);
+    let provider = cassette("chat").await;
     let client = Client::new(Box::new(provider));
 
     let messages = vec![
-        Message {
-            role: Role::User,
-            content: "Say 'test'".to_string(),
-        },
+        Message::new(Role::User, "Say 'test'"),
     ];
 
     let result = client.chat(messages).await;
@@ -61,18 +63,13 @@ async fn test_openai_invalid_key() {
 }
 
 #[tokio::test]
-#[ignore] // Requires API key
 async fn test_openai_stream_chat() {
     use futures::StreamExt;
-    
-    let api_key = std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY not set");
-    let provider = OpenAIProvider::new(api_key, None);
+
+    let provider = cassette("stream_chat").await;
     let client = Client::new(Box::new(provider));
 
-    let messages = vec![Message {
-        role: Role::User,
-        content: "Count from 1 to 5 slowly.".to_string(),
-    }];
+    let messages = vec![Message::new(Role::User, "Count from 1 to 5 slowly.")];
 
     let mut stream = client.stream_chat(messages).await.unwrap();
     let mut received = Vec::new();
@@ -82,3 +79,310 @@ async fn test_openai_stream_chat() {
 
     assert!(!received.is_empty());
 }
+
+#[tokio::test]
+async fn test_openai_tool_call() {
+    let provider = cassette("tool_call").await;
+    common::test_tool_call(provider).await;
+}
+
+#[tokio::test]
+async fn test_openai_response_metadata() {
+    let provider = cassette("response_metadata").await;
+    common::test_response_metadata(provider).await;
+}
+
+#[tokio::test]
+async fn test_openai_max_tokens_truncation() {
+    let provider = cassette("max_tokens_truncation").await;
+    common::test_max_tokens_truncation(provider).await;
+}
+
+#[tokio::test]
+async fn test_openai_system_prompt() {
+    let provider = cassette("system_prompt").await;
+    common::test_system_prompt(provider).await;
+}
+
+#[tokio::test]
+async fn test_openai_stream_collect() {
+    let provider = cassette("stream_collect").await;
+    common::test_stream_collect(provider).await;
+}
+
+#[tokio::test]
+async fn test_openai_stream_tool_call() {
+    let provider = cassette("stream_tool_call").await;
+    common::test_stream_tool_call(provider).await;
+}
+
+fn server_provider(server: &TestServer) -> OpenAIProvider {
+    OpenAIProvider::builder("test_key")
+        .base_url(server.url())
+        .build()
+        .unwrap()
+}
+
+fn completion(message: serde_json::Value, finish_reason: &str) -> serde_json::Value {
+    json!({
+        "id": "chatcmpl-123",
+        "object": "chat.completion",
+        "model": "gpt-4o-mini-2024-07-18",
+        "choices": [{ "index": 0, "message": message, "finish_reason": finish_reason }],
+        "usage": { "prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15 }
+    })
+}
+
+#[tokio::test]
+async fn test_openai_builder_configures_requests() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        completion(json!({ "role": "assistant", "content": "pineapple" }), "stop"),
+    )])
+    .await;
+    let provider = OpenAIProvider::builder("test_key")
+        .model("gpt-4o")
+        .base_url(format!("{}/gateway/v1/", server.url()))
+        .organization("org-123")
+        .project("proj_456")
+        .header("X-Gateway-Key", "secret")
+        .build()
+        .unwrap();
+    let client = Client::new(Box::new(provider));
+
+    let response = client.chat(vec![Message::new(Role::User, "Say pineapple")]).await.unwrap();
+
+    assert_eq!(response.content(), "pineapple");
+    assert_eq!(response.id.as_deref(), Some("chatcmpl-123"));
+    assert_eq!(response.model, "gpt-4o-mini-2024-07-18");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 12, output_tokens: 3 }));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/gateway/v1/chat/completions");
+    assert_eq!(request.header("authorization"), Some("Bearer test_key"));
+    assert_eq!(request.header("openai-organization"), Some("org-123"));
+    assert_eq!(request.header("openai-project"), Some("proj_456"));
+    assert_eq!(request.header("x-gateway-key"), Some("secret"));
+    assert_eq!(request.json()["model"], "gpt-4o");
+    assert_eq!(request.json()["messages"], json!([{ "role": "user", "content": "Say pineapple" }]));
+}
+
+#[tokio::test]
+async fn test_openai_sends_options_and_tools() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        completion(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+            }]
+        }), "tool_calls"),
+    )])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let tool = Tool::new("get_weather", "Get the weather", json!({ "type": "object" }));
+    let options = ChatOptions::new()
+        .tools(vec![tool])
+        .temperature(0.5)
+        .max_tokens(100)
+        .stop(vec!["END".to_string()])
+        .seed(42);
+    let messages = vec![
+        Message::new(Role::System, "Be brief"),
+        Message::new(Role::User, "Weather in Paris?"),
+    ];
+    let response = client.chat_with_options(messages, &options).await.unwrap();
+
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls()[0].id, "call_1");
+    assert_eq!(response.tool_calls()[0].arguments, json!({ "city": "Paris" }));
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["messages"][0], json!({ "role": "system", "content": "Be brief" }));
+    assert_eq!(body["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(body["temperature"], 0.5);
+    assert_eq!(body["max_tokens"], 100);
+    assert_eq!(body["stop"], json!(["END"]));
+    assert_eq!(body["seed"], 42);
+    assert!(body.get("stream").is_none());
+}
+
+#[tokio::test]
+async fn test_openai_sends_tool_results() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        completion(json!({ "role": "assistant", "content": "It is sunny." }), "stop"),
+    )])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let call = ToolCall { id: "call_1".to_string(), name: "get_weather".to_string(), arguments: json!({ "city": "Paris" }) };
+    let messages = vec![
+        Message::new(Role::User, "Weather in Paris?"),
+        Message { tool_calls: vec![call], ..Message::new(Role::Assistant, "") },
+        Message::tool_results(vec![ToolResult::new("call_1", "Sunny")]),
+    ];
+    client.chat(messages).await.unwrap();
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["messages"][1]["content"], serde_json::Value::Null);
+    assert_eq!(body["messages"][1]["tool_calls"][0]["function"]["arguments"], "{\"city\":\"Paris\"}");
+    assert_eq!(body["messages"][2], json!({ "role": "tool", "tool_call_id": "call_1", "content": "Sunny" }));
+    assert_eq!(body["messages"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_openai_resends_malformed_arguments_verbatim() {
+    let malformed = "{\"city\": \"Par";
+    let server = serve(vec![
+        TestResponse::json(200, completion(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{ "id": "call_1", "type": "function", "function": { "name": "get_weather", "arguments": malformed } }]
+        }), "tool_calls")),
+        TestResponse::json(200, completion(json!({ "role": "assistant", "content": "Try again." }), "stop")),
+    ])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let mut messages = vec![Message::new(Role::User, "Weather in Paris?")];
+    let reply = client.chat(messages.clone()).await.unwrap();
+    assert_eq!(reply.tool_calls()[0].arguments, json!(malformed));
+    messages.push(reply.message);
+    messages.push(Message::tool_results(vec![ToolResult::new("call_1", "Invalid arguments")]));
+    client.chat(messages).await.unwrap();
+
+    let body = server.requests()[1].json();
+    assert_eq!(body["messages"][1]["tool_calls"][0]["function"]["arguments"], malformed);
+}
+
+#[tokio::test]
+async fn test_openai_sends_images_and_documents() {
+    let server = serve(vec![TestResponse::json(
+        200,
+        completion(json!({ "role": "assistant", "content": "A cat." }), "stop"),
+    )])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let message = Message::with_parts(Role::User, vec![
+        ContentPart::text("What is in these?"),
+        ContentPart::image_bytes("image/png", b"\x89PNG"),
+        ContentPart::image_url("https://example.com/cat.jpg"),
+        ContentPart::pdf(b"%PDF-1.7").named("report.pdf"),
+    ]);
+    client.chat(vec![message]).await.unwrap();
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["messages"][0]["content"], json!([
+        { "type": "text", "text": "What is in these?" },
+        { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw==" } },
+        { "type": "image_url", "image_url": { "url": "https://example.com/cat.jpg" } },
+        { "type": "file", "file": { "filename": "report.pdf", "file_data": "data:application/pdf;base64,JVBERi0xLjc=" } }
+    ]));
+
+    let message = Message::with_parts(Role::User, vec![ContentPart::document_url("https://example.com/report.pdf")]);
+    let error = client.chat(vec![message]).await.unwrap_err();
+    assert!(matches!(error, Error::Unsupported(_)));
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_openai_stream_events() {
+    let server = serve(vec![TestResponse::sse(&[
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": { "role": "assistant", "content": "" }, "finish_reason": null }] })),
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": { "content": "Hello" }, "finish_reason": null }] })),
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": { "tool_calls": [{ "index": 0, "id": "call_1", "type": "function", "function": { "name": "lookup", "arguments": "" } }] }, "finish_reason": null }] })),
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": { "tool_calls": [{ "index": 0, "function": { "arguments": "{\"q\":1}" } }] }, "finish_reason": null }] })),
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": {}, "finish_reason": "tool_calls" }] })),
+        ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [], "usage": { "prompt_tokens": 5, "completion_tokens": 7 } })),
+        ("", json!("[DONE]")),
+    ])])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let response = inklings::stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Hello");
+    assert_eq!(response.id.as_deref(), Some("chatcmpl-1"));
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.tool_calls()[0].arguments, json!({ "q": 1 }));
+    assert_eq!(response.usage, Some(Usage { input_tokens: 5, output_tokens: 7 }));
+
+    let body = server.requests()[0].json();
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["stream_options"]["include_usage"], true);
+}
+
+#[tokio::test]
+async fn test_openai_stream_error_and_truncation() {
+    let server = serve(vec![
+        TestResponse::sse(&[
+            ("", json!({ "id": "chatcmpl-1", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": { "content": "Hel" }, "finish_reason": null }] })),
+            ("", json!({ "error": { "message": "The server had an error", "type": "server_error" } })),
+        ]),
+        TestResponse::sse(&[
+            ("", json!({ "id": "chatcmpl-2", "model": "gpt-4o-mini", "choices": [{ "index": 0, "delta": { "content": "Hel" }, "finish_reason": null }] })),
+        ]),
+    ])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let result = inklings::stream::collect(stream).await;
+    assert!(matches!(result, Err(Error::ServerError(_))));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+    let result = inklings::stream::collect(stream).await;
+    assert!(matches!(result, Err(Error::StreamTruncated)));
+}
+
+#[tokio::test]
+async fn test_openai_error_response() {
+    let server = serve(vec![TestResponse::json(
+        429,
+        json!({ "error": { "message": "Rate limit reached", "type": "requests", "code": "rate_limit_exceeded" } }),
+    )
+    .header("retry-after-ms", "1500")
+    .header("x-request-id", "req_42")])
+    .await;
+    let client = Client::new(Box::new(server_provider(&server)));
+
+    let error = client.complete("Hi").await.unwrap_err();
+
+    assert!(matches!(error, Error::RateLimited(_)));
+    assert_eq!(error.status(), Some(429));
+    assert_eq!(error.request_id(), Some("req_42"));
+    assert_eq!(error.retry_after(), Some(Duration::from_millis(1500)));
+}
+
+#[tokio::test]
+async fn test_openai_timeout() {
+    let response = TestResponse::json(200, completion(json!({ "role": "assistant", "content": "late" }), "stop"))
+        .chunk_delay(Duration::from_secs(5));
+    let server = serve(vec![response]).await;
+    let provider = OpenAIProvider::builder("test_key")
+        .base_url(server.url())
+        .timeout(Duration::from_millis(200))
+        .build()
+        .unwrap();
+
+    let error = provider.complete("Hi").await.unwrap_err();
+    assert!(matches!(&error, Error::RequestError(e) if e.is_timeout()));
+}
+
+#[test]
+fn test_openai_builder_rejects_invalid_settings() {
+    let result = OpenAIProvider::builder("test_key").header("bad header", "value").build();
+    assert!(matches!(result, Err(Error::Configuration(_))));
+
+    let result = OpenAIProvider::builder("test_key").proxy("not a url").build();
+    assert!(matches!(result, Err(Error::Configuration(_))));
+}
@@ -0,0 +1,234 @@
+use inklings::{
+    Client,
+    provider::{MockProvider, MockReply},
+    rate_limit::{estimate_tokens, RateLimitStatus, RateLimiter},
+    retry::RetryPolicy,
+    stream,
+    testing::{TestResponse, TestServer},
+    types::{ApiError, ChatOptions, ChatResponse, Error, FinishReason, Message, StreamEvent, Usage},
+};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+
+fn reply_using(tokens: u32) -> MockReply {
+    MockReply::events(vec![
+        Ok(StreamEvent::Start { id: None, model: "mock".to_string(), provider: None }),
+        Ok(StreamEvent::TextDelta("ok".to_string())),
+        Ok(StreamEvent::Usage(Usage { input_tokens: tokens, output_tokens: 0 })),
+        Ok(StreamEvent::Stop(FinishReason::Stop)),
+        Ok(StreamEvent::Done),
+    ])
+}
+
+fn response(text: &str) -> ChatResponse {
+    ChatResponse {
+        id: None,
+        model: "mock".to_string(),
+        provider: None,
+        message: Message::assistant(text),
+        usage: None,
+        finish_reason: FinishReason::Stop,
+        rate_limit: None,
+        raw: serde_json::Value::Null,
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_requests_per_minute_queue_instead_of_failing() {
+    let client = Client::new(Box::new(MockProvider::new("ok".to_string())))
+        .with_rate_limiter(RateLimiter::new().requests_per_minute(2));
+
+    let started = Instant::now();
+    for _ in 0..4 {
+        client.complete("Hi").await.unwrap();
+    }
+
+    // Two requests use up the budget; each of the next two waits for one to refill.
+    assert_eq!(started.elapsed(), Duration::from_secs(60));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_tokens_are_estimated_then_settled() {
+    let provider = MockProvider::builder().reply(reply_using(1_500)).fallback(MockReply::text("ok")).build();
+    let client = Client::new(Box::new(provider)).with_rate_limiter(RateLimiter::new().tokens_per_minute(1_000));
+
+    let started = Instant::now();
+    client.complete("Hi").await.unwrap();
+    assert_eq!(started.elapsed(), Duration::ZERO);
+
+    // The first call used 500 tokens more than the budget holds, which have to refill before
+    // the next call's estimate fits.
+    let estimate = estimate_tokens(&[Message::user("Hi")], &ChatOptions::default());
+    client.complete("Hi").await.unwrap();
+    let expected = Duration::from_secs_f64(f64::from(500 + estimate) * 60.0 / 1_000.0);
+    assert!(started.elapsed().abs_diff(expected) < Duration::from_millis(5), "{:?}", started.elapsed());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_stream_usage_is_settled() {
+    let provider = MockProvider::builder().reply(reply_using(2_000)).fallback(MockReply::text("ok")).build();
+    let client = Client::new(Box::new(provider)).with_rate_limiter(RateLimiter::new().tokens_per_minute(1_000));
+
+    let events = client.stream_chat(vec![Message::user("Hi")]).await.unwrap();
+    stream::collect(events).await.unwrap();
+
+    let started = Instant::now();
+    client.complete("Hi").await.unwrap();
+    assert!(started.elapsed() > Duration::from_secs(60), "{:?}", started.elapsed());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_waiting_calls_are_served_in_order() {
+    let client = Arc::new(Client::new(Box::new(MockProvider::new("ok".to_string())))
+        .with_rate_limiter(RateLimiter::new().requests_per_minute(1)));
+    let finished = Arc::new(Mutex::new(Vec::new()));
+
+    let mut tasks = Vec::new();
+    for i in 0..4 {
+        let (client, finished) = (client.clone(), finished.clone());
+        tasks.push(tokio::spawn(async move {
+            client.complete("Hi").await.unwrap();
+            finished.lock().unwrap().push(i);
+        }));
+        tokio::task::yield_now().await;
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    assert_eq!(*finished.lock().unwrap(), [0, 1, 2, 3]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retry_after_pauses_the_queue() {
+    let rate_limited = ApiError {
+        status: Some(429),
+        error_type: None,
+        message: "Slow down".to_string(),
+        request_id: None,
+        retry_after: Some(Duration::from_secs(20)),
+    };
+    let provider = MockProvider::builder()
+        .reply(MockReply::error(Error::RateLimited(rate_limited)))
+        .fallback(MockReply::text("ok"))
+        .build();
+    let client = Client::new(Box::new(provider))
+        .with_rate_limiter(RateLimiter::new())
+        .with_retry(RetryPolicy::new(2).respect_retry_after(false).initial_backoff(Duration::from_millis(1)));
+
+    let started = Instant::now();
+    assert_eq!(client.complete("Hi").await.unwrap(), "ok");
+    assert_eq!(started.elapsed(), Duration::from_secs(20));
+}
+
+#[tokio::test]
+async fn test_openai_headers_adapt_the_budget() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::text("first")
+        .header("x-ratelimit-limit-requests", "500")
+        .header("x-ratelimit-remaining-requests", "0")
+        .header("x-ratelimit-reset-requests", "300ms")
+        .header("x-ratelimit-limit-tokens", "30000")
+        .header("x-ratelimit-remaining-tokens", "29000")
+        .header("x-ratelimit-reset-tokens", "1m2.5s"));
+    server.enqueue(TestResponse::text("second"));
+    let client = Client::new(Box::new(server.openai())).with_rate_limiter(RateLimiter::new());
+
+    let response = client.chat(vec![Message::user("Hi")]).await.unwrap();
+    assert_eq!(response.rate_limit, Some(RateLimitStatus {
+        requests_limit: Some(500),
+        requests_remaining: Some(0),
+        requests_reset: Some(Duration::from_millis(300)),
+        tokens_limit: Some(30_000),
+        tokens_remaining: Some(29_000),
+        tokens_reset: Some(Duration::from_millis(62_500)),
+    }));
+
+    let started = std::time::Instant::now();
+    assert_eq!(client.chat_text(vec![Message::user("Hi")]).await.unwrap(), "second");
+    assert!(started.elapsed() >= Duration::from_millis(300), "{:?}", started.elapsed());
+}
+
+#[tokio::test]
+async fn test_anthropic_headers_are_read() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::text("Hi")
+        .header("anthropic-ratelimit-requests-limit", "50")
+        .header("anthropic-ratelimit-requests-remaining", "49")
+        .header("anthropic-ratelimit-requests-reset", "2100-01-01T00:00:00Z")
+        .header("anthropic-ratelimit-tokens-limit", "40000")
+        .header("anthropic-ratelimit-tokens-remaining", "39000")
+        .header("anthropic-ratelimit-tokens-reset", "2001-02-03T04:05:06.5+01:00"));
+    let client = Client::new(Box::new(server.anthropic()));
+
+    let status = client.chat(vec![Message::user("Hi")]).await.unwrap().rate_limit.unwrap();
+
+    assert_eq!((status.requests_limit, status.requests_remaining), (Some(50), Some(49)));
+    assert_eq!((status.tokens_limit, status.tokens_remaining), (Some(40_000), Some(39_000)));
+    // 2100-01-01T00:00:00Z is 4102444800 seconds after the epoch.
+    let until_2100 = Duration::from_secs(4_102_444_800) - SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    assert!(status.requests_reset.unwrap().abs_diff(until_2100) < Duration::from_secs(5));
+    assert_eq!(status.tokens_reset, Some(Duration::ZERO));
+}
+
+#[tokio::test]
+async fn test_out_of_range_resets_are_ignored() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::text("first")
+        .header("x-ratelimit-limit-requests", "500")
+        .header("x-ratelimit-remaining-tokens", "0")
+        .header("x-ratelimit-reset-requests", "99999999999999999999h")
+        .header("x-ratelimit-reset-tokens", "5000000000000000000000m"));
+    server.enqueue(TestResponse::text("second")
+        .header("anthropic-ratelimit-requests-limit", "50")
+        .header("anthropic-ratelimit-requests-reset", "9223372036854775807-01-01T00:00:00Z")
+        .header("anthropic-ratelimit-tokens-reset", "99999999999999-12-31T23:59:59+01:00"));
+    server.enqueue(TestResponse::text("third")
+        .header("x-ratelimit-remaining-requests", "0")
+        .header("x-ratelimit-reset-requests", "4000000000000000h"));
+    let openai = Client::new(Box::new(server.openai())).with_rate_limiter(RateLimiter::new());
+    let anthropic = Client::new(Box::new(server.anthropic())).with_rate_limiter(RateLimiter::new());
+
+    let status = openai.chat(vec![Message::user("Hi")]).await.unwrap().rate_limit.unwrap();
+    assert_eq!((status.requests_limit, status.requests_reset, status.tokens_reset), (Some(500), None, None));
+
+    let status = anthropic.chat(vec![Message::user("Hi")]).await.unwrap().rate_limit.unwrap();
+    assert_eq!((status.requests_limit, status.requests_reset, status.tokens_reset), (Some(50), None, None));
+
+    // A reset too far off to add to the clock still pauses the queue, for at most an hour.
+    let status = openai.chat(vec![Message::user("Hi")]).await.unwrap().rate_limit.unwrap();
+    assert_eq!(status.requests_reset, Some(Duration::from_secs(4_000_000_000_000_000 * 3600)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_pauses_last_an_hour_at_most() {
+    let rate_limited = ApiError {
+        status: Some(429),
+        error_type: None,
+        message: "Slow down".to_string(),
+        request_id: None,
+        retry_after: Some(Duration::MAX),
+    };
+    let exhausted = RateLimitStatus { requests_remaining: Some(0), requests_reset: Some(Duration::MAX), ..RateLimitStatus::default() };
+    let provider = MockProvider::builder()
+        .reply(MockReply::error(Error::RateLimited(rate_limited)))
+        .reply(MockReply::response(ChatResponse { rate_limit: Some(exhausted), ..response("paused") }))
+        .fallback(MockReply::text("ok"))
+        .build();
+    let client = Client::new(Box::new(provider)).with_rate_limiter(RateLimiter::new());
+
+    let started = Instant::now();
+    client.complete("Hi").await.unwrap_err();
+    assert_eq!(client.complete("Hi").await.unwrap(), "paused");
+    assert_eq!(started.elapsed(), Duration::from_secs(3600));
+    assert_eq!(client.complete("Hi").await.unwrap(), "ok");
+    assert_eq!(started.elapsed(), Duration::from_secs(7200));
+}
+
+#[test]
+fn test_estimate_tokens() {
+    let messages = vec![Message::system("a".repeat(400)), Message::user("b".repeat(401))];
+
+    assert_eq!(estimate_tokens(&messages, &ChatOptions::default()), 4 + 4 + 201);
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+use std::time::Duration;
+use inklings::{
+    Client,
+    provider::MockProvider,
+    retry::RetryPolicy,
+    stream,
+    types::{ApiError, Error, Message, Role},
+};
+use tokio::time::Instant;
+
+fn api_error(status: u16, retry_after: Option<Duration>) -> ApiError {
+    ApiError {
+        status: Some(status),
+        error_type: None,
+        message: "test".to_string(),
+        request_id: None,
+        retry_after,
+    }
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::new(3)
+        .initial_backoff(Duration::from_secs(1))
+        .multiplier(2.0)
+        .jitter(0.0)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retries_with_exponential_backoff() {
+    let provider = Arc::new(MockProvider::with_failures(
+        vec![Error::Overloaded(api_error(529, None)), Error::ServerError(api_error(500, None))],
+        "Recovered".to_string(),
+    ));
+    let client = Client::new(Box::new(provider.clone())).with_retry(policy());
+
+    let start = Instant::now();
+    let result = client.complete("Test prompt").await.unwrap();
+
+    assert_eq!(result, "Recovered");
+    assert_eq!(provider.calls(), 3);
+    assert_eq!(start.elapsed(), Duration::from_secs(3));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_honours_retry_after() {
+    let provider = Arc::new(MockProvider::with_failures(
+        vec![Error::RateLimited(api_error(429, Some(Duration::from_secs(7))))],
+        "Recovered".to_string(),
+    ));
+    let client = Client::new(Box::new(provider.clone())).with_retry(policy());
+
+    let start = Instant::now();
+    client.chat(vec![Message::new(Role::User, "Hi!")]).await.unwrap();
+
+    assert_eq!(provider.calls(), 2);
+    assert_eq!(start.elapsed(), Duration::from_secs(7));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_backoff_is_capped() {
+    let provider = Arc::new(MockProvider::with_failures(
+        vec![Error::ServerError(api_error(500, None)), Error::ServerError(api_error(500, None))],
+        "Recovered".to_string(),
+    ));
+    let client = Client::new(Box::new(provider.clone()))
+        .with_retry(policy().initial_backoff(Duration::from_secs(10)).max_backoff(Duration::from_secs(15)));
+
+    let start = Instant::now();
+    client.complete("Test prompt").await.unwrap();
+
+    assert_eq!(start.elapsed(), Duration::from_secs(25));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_gives_up_after_max_attempts() {
+    let provider = Arc::new(MockProvider::with_failures(
+        vec![
+            Error::Overloaded(api_error(529, None)),
+            Error::Overloaded(api_error(529, None)),
+            Error::Overloaded(api_error(529, None)),
+        ],
+        "Too late".to_string(),
+    ));
+    let client = Client::new(Box::new(provider.clone())).with_retry(policy());
+
+    let result = client.complete("Test prompt").await;
+
+    assert!(matches!(result, Err(Error::Overloaded(_))));
+    assert_eq!(provider.calls(), 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_does_not_retry_permanent_errors() {
+    let provider = Arc::new(MockProvider::with_failures(
+        vec![Error::InvalidRequest(api_error(400, None))],
+        "Unreachable".to_string(),
+    ));
+    let client = Client::new(Box::new(provider.clone())).with_retry(policy());
+
+    let result = client.complete("Test prompt").await;
+
+    assert!(matches!(result, Err(Error::InvalidRequest(_))));
+    assert_eq!(provider.calls(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_custom_retry_predicate() {
+    let provider = Arc::new(MockProvider::with_failures(
+        vec![Error::ProviderError("flaky".to_string())],
+        "Recovered".to_string(),
+    ));
+    let client = Client::new(Box::new(provider.clone()))
+        .with_retry(policy().retry_if(|e| matches!(e, Error::ProviderError(_))));
+
+    assert_eq!(client.complete("Test prompt").await.unwrap(), "Recovered");
+    assert_eq!(provider.calls(), 2);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retries_stream_connection() {
+    let provider = Arc::new(MockProvider::with_failures(
+        vec![Error::Overloaded(api_error(529, None))],
+        "Streamed".to_string(),
+    ));
+    let client = Client::new(Box::new(provider.clone())).with_retry(policy());
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi!")]).await.unwrap();
+    let response = stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Streamed");
+    assert_eq!(provider.calls(), 2);
+}
+
+#[tokio::test]
+async fn test_no_retries_by_default() {
+    let provider = Arc::new(MockProvider::with_failures(
+        vec![Error::Overloaded(api_error(529, None))],
+        "Unreachable".to_string(),
+    ));
+    let client = Client::new(Box::new(provider.clone()));
+
+    assert!(client.complete("Test prompt").await.is_err());
+    assert_eq!(provider.calls(), 1);
+}
+
+#[test]
+fn test_jitter_stays_within_bounds() {
+    let policy = RetryPolicy::new(5).initial_backoff(Duration::from_secs(2)).jitter(0.5);
+    let error = Error::ServerError(api_error(500, None));
+
+    for _ in 0..100 {
+        let delay = policy.delay(1, &error);
+        assert!(delay > Duration::from_secs(1) && delay <= Duration::from_secs(2));
+    }
+}
+
+#[test]
+fn test_out_of_range_settings_give_valid_delays() {
+    let error = Error::ServerError(api_error(500, None));
+
+    let shrinking = RetryPolicy::new(5).initial_backoff(Duration::from_secs(1)).multiplier(-2.0).jitter(f64::NAN);
+    for attempt in 1..5 {
+        assert_eq!(shrinking.delay(attempt, &error), Duration::from_secs(1));
+    }
+
+    let unbounded = RetryPolicy::new(5).max_backoff(Duration::MAX).multiplier(1e300).jitter(0.0);
+    assert_eq!(unbounded.delay(u32::MAX, &error), Duration::MAX);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retry_after_is_capped() {
+    let provider = Arc::new(MockProvider::with_failures(
+        vec![Error::RateLimited(api_error(429, Some(Duration::MAX)))],
+        "Recovered".to_string(),
+    ));
+    let client = Client::new(Box::new(provider.clone())).with_retry(policy().max_retry_after(Duration::from_secs(60)));
+
+    let start = Instant::now();
+    client.complete("Test prompt").await.unwrap();
+
+    assert_eq!(start.elapsed(), Duration::from_secs(60));
+}
@@ -0,0 +1,218 @@
+use inklings::{
+    Client,
+    provider::{MockProvider, MockReply, Provider, RouterProvider, RoutingStrategy},
+    rate_limit::{RateLimitStatus, RateLimiter},
+    retry::RetryPolicy,
+    stream,
+    types::{ApiError, ChatOptions, ChatResponse, Error, FinishReason, Message, StreamEvent},
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+fn api_error(status: u16, retry_after: Option<Duration>) -> ApiError {
+    ApiError { status: Some(status), error_type: None, message: "failed".to_string(), request_id: None, retry_after }
+}
+
+fn router(members: &[Arc<MockProvider>]) -> RouterProvider {
+    let providers = members.iter().map(|m| Box::new(m.clone()) as Box<dyn Provider + Send + Sync>).collect();
+    RouterProvider::new(providers).names(["a", "b", "c"])
+}
+
+fn member(reply: &str) -> Arc<MockProvider> {
+    Arc::new(MockProvider::new(reply.to_string()))
+}
+
+async fn route(router: &RouterProvider, calls: usize) -> Vec<String> {
+    let mut answered = Vec::new();
+    for _ in 0..calls {
+        let response = router.chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+        answered.push(response.provider.unwrap());
+    }
+    answered
+}
+
+async fn route_errors(router: &RouterProvider, calls: usize) {
+    for _ in 0..calls {
+        router.chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap_err();
+    }
+}
+
+#[tokio::test]
+async fn test_round_robin() {
+    let members = [member("a"), member("b"), member("c")];
+    let router = router(&members);
+
+    assert_eq!(route(&router, 6).await, ["a", "b", "c", "a", "b", "c"]);
+    assert_eq!(router.complete("Hi").await.unwrap(), "a");
+}
+
+#[tokio::test]
+async fn test_weighted_is_interleaved() {
+    let members = [member("a"), member("b"), member("c")];
+    let router = router(&members).strategy(RoutingStrategy::Weighted).weights([3, 1, 0]);
+
+    let answered = route(&router, 8).await;
+
+    assert_eq!(answered, ["a", "a", "b", "a", "a", "a", "b", "a"]);
+    assert_eq!(members[2].calls(), 0);
+}
+
+#[tokio::test]
+async fn test_least_in_flight_counts_open_streams() {
+    let members = [member("a"), member("b")];
+    let router = router(&members).strategy(RoutingStrategy::LeastInFlight);
+
+    let first = router.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    let second = router.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    assert_eq!((members[0].calls(), members[1].calls()), (1, 1));
+
+    drop(second);
+    assert_eq!(route(&router, 2).await, ["b", "b"]);
+    let response = stream::collect(first).await.unwrap();
+    assert_eq!(response.provider.as_deref(), Some("a"));
+    assert_eq!(route(&router, 1).await, ["a"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_lowest_latency_prefers_the_fastest_member() {
+    let slow = Arc::new(MockProvider::builder().fallback(MockReply::text("slow")).latency(Duration::from_millis(300)).build());
+    let fast = Arc::new(MockProvider::builder().fallback(MockReply::text("fast")).latency(Duration::from_millis(100)).build());
+    let router = router(&[slow.clone(), fast.clone()]).strategy(RoutingStrategy::LowestLatency);
+
+    assert_eq!(route(&router, 5).await, ["a", "b", "b", "b", "b"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limiter_waits_do_not_count_as_latency() {
+    let slow = Arc::new(MockProvider::builder().fallback(MockReply::text("b")).latency(Duration::from_millis(100)).build());
+    let router = router(&[member("a"), slow])
+        .strategy(RoutingStrategy::LowestLatency)
+        .rate_limiters([RateLimiter::new().requests_per_minute(1)]);
+
+    // `a` waits a minute for its budget on the second call but answers at once, so it stays
+    // the fastest member.
+    assert_eq!(route(&router, 3).await, ["a", "a", "a"]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_failing_members_are_ejected_until_the_cooldown_ends() {
+    let flaky = Arc::new(MockProvider::builder().reply(MockReply::error(Error::RateLimited(api_error(429, None)))).build());
+    let members = [flaky.clone(), member("b")];
+    let router = router(&members).cooldown(Duration::from_secs(10));
+
+    let error = router.chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap_err();
+    assert!(matches!(error, Error::RateLimited(_)));
+    assert_eq!(router.ejected(), ["a"]);
+    assert_eq!(route(&router, 3).await, ["b", "b", "b"]);
+
+    tokio::time::advance(Duration::from_secs(10)).await;
+    assert!(router.ejected().is_empty());
+    let answered = route(&router, 2).await;
+    assert!(answered.contains(&"a".to_string()), "{:?}", answered);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_retry_after_extends_the_cooldown() {
+    let flaky = Arc::new(MockProvider::builder().reply(MockReply::error(Error::Overloaded(api_error(529, Some(Duration::from_secs(60)))))).build());
+    let router = router(&[flaky, member("b")]).cooldown(Duration::from_secs(10));
+
+    route_errors(&router, 1).await;
+    tokio::time::advance(Duration::from_secs(30)).await;
+    assert_eq!(router.ejected(), ["a"]);
+    tokio::time::advance(Duration::from_secs(30)).await;
+    assert!(router.ejected().is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_ejections_last_a_day_at_most() {
+    let flaky = Arc::new(MockProvider::builder().reply(MockReply::error(Error::Overloaded(api_error(529, Some(Duration::MAX))))).build());
+    let router = router(&[flaky, member("b")]).cooldown(Duration::MAX);
+
+    route_errors(&router, 1).await;
+    tokio::time::advance(Duration::from_secs(24 * 3600 - 1)).await;
+    assert_eq!(router.ejected(), ["a"]);
+    tokio::time::advance(Duration::from_secs(1)).await;
+    assert!(router.ejected().is_empty());
+}
+
+#[tokio::test]
+async fn test_other_errors_do_not_eject() {
+    let invalid = Arc::new(MockProvider::builder().reply(MockReply::error(Error::InvalidRequest(api_error(400, None)))).build());
+    let router = router(&[invalid, member("b")]);
+
+    route_errors(&router, 1).await;
+
+    assert!(router.ejected().is_empty());
+}
+
+#[tokio::test]
+async fn test_all_ejected_members_still_serve() {
+    let server_error = || MockReply::error(Error::ServerError(api_error(500, None)));
+    let first = Arc::new(MockProvider::builder().reply(server_error()).build());
+    let second = Arc::new(MockProvider::builder().reply(server_error()).build());
+    let router = router(&[first, second]);
+
+    route_errors(&router, 2).await;
+    assert_eq!(router.ejected(), ["a", "b"]);
+
+    assert_eq!(route(&router, 1).await, ["a"]);
+}
+
+#[tokio::test]
+async fn test_mid_stream_errors_eject() {
+    let broken = Arc::new(MockProvider::builder().reply(MockReply::events(vec![
+        Ok(StreamEvent::TextDelta("Hel".to_string())),
+        Err(Error::Overloaded(api_error(529, None))),
+    ])).build());
+    let router = router(&[broken, member("b")]);
+
+    let events = router.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    assert!(router.ejected().is_empty());
+    stream::collect(events).await.unwrap_err();
+
+    assert_eq!(router.ejected(), ["a"]);
+}
+
+#[tokio::test]
+async fn test_client_retries_go_to_another_member() {
+    let flaky = Arc::new(MockProvider::builder().reply(MockReply::error(Error::RateLimited(api_error(429, None)))).build());
+    let client = Client::new(Box::new(router(&[flaky, member("b")])))
+        .with_retry(RetryPolicy::new(2).initial_backoff(Duration::from_millis(1)));
+
+    let response = client.chat(vec![Message::user("Hi")]).await.unwrap();
+
+    assert_eq!(response.content(), "b");
+    assert_eq!(response.provider.as_deref(), Some("b"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_members_follow_their_own_rate_limits() {
+    let exhausted = RateLimitStatus {
+        requests_remaining: Some(0),
+        requests_reset: Some(Duration::from_secs(10)),
+        ..RateLimitStatus::default()
+    };
+    let response = ChatResponse {
+        id: None,
+        model: "mock".to_string(),
+        provider: None,
+        message: Message::assistant("a"),
+        usage: None,
+        finish_reason: FinishReason::Stop,
+        rate_limit: Some(exhausted),
+        raw: serde_json::Value::Null,
+    };
+    let members = [Arc::new(MockProvider::builder().fallback(MockReply::response(response)).build()), member("b")];
+    let router = router(&members).rate_limiters([RateLimiter::new(), RateLimiter::new()]);
+
+    let started = Instant::now();
+    assert_eq!(route(&router, 2).await, ["a", "b"]);
+    assert_eq!(started.elapsed(), Duration::ZERO);
+
+    // Only `a` reported an exhausted budget, so only `a` waits for it to reset.
+    assert_eq!(route(&router, 1).await, ["a"]);
+    assert_eq!(started.elapsed(), Duration::from_secs(10));
+    assert_eq!(route(&router, 1).await, ["b"]);
+    assert_eq!(started.elapsed(), Duration::from_secs(10));
+}
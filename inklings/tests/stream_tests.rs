@@ -0,0 +1,133 @@
+use futures::StreamExt;
+use inklings::{
+    Client,
+    provider::MockProvider,
+    stream::{self, StreamAccumulator},
+    types::{ApiError, Error, FinishReason, Message, Role, StreamEvent, ToolCallDelta, Usage},
+};
+
+#[tokio::test]
+async fn test_stream_events() {
+    let provider = MockProvider::with_stream_response(vec!["Hello".to_string(), ", world".to_string()]);
+    let client = Client::new(Box::new(provider));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi!")]).await.unwrap();
+    let events: Vec<StreamEvent> = stream.map(Result::unwrap).collect().await;
+
+    assert_eq!(events, vec![
+        StreamEvent::Start { id: None, model: "mock".to_string(), provider: None },
+        StreamEvent::TextDelta("Hello".to_string()),
+        StreamEvent::TextDelta(", world".to_string()),
+        StreamEvent::Stop(FinishReason::Stop),
+        StreamEvent::Done,
+    ]);
+}
+
+#[tokio::test]
+async fn test_collect_stream() {
+    let provider = MockProvider::with_stream_response(vec!["Hello".to_string(), ", world".to_string()]);
+    let client = Client::new(Box::new(provider));
+
+    let stream = client.stream_chat(vec![Message::new(Role::User, "Hi!")]).await.unwrap();
+    let response = stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "Hello, world");
+    assert_eq!(response.model, "mock");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+}
+
+#[test]
+fn test_accumulator_assembles_tool_calls() {
+    let events = vec![
+        StreamEvent::Start { id: Some("msg_1".to_string()), model: "test-model".to_string(), provider: None },
+        StreamEvent::TextDelta("Let me check.".to_string()),
+        StreamEvent::ToolCallDelta(ToolCallDelta {
+            index: 1,
+            id: Some("call_1".to_string()),
+            name: Some("get_weather".to_string()),
+            arguments: String::new(),
+        }),
+        StreamEvent::ToolCallDelta(ToolCallDelta {
+            index: 1,
+            id: None,
+            name: None,
+            arguments: "{\"city\":".to_string(),
+        }),
+        StreamEvent::ToolCallDelta(ToolCallDelta {
+            index: 1,
+            id: None,
+            name: None,
+            arguments: " \"Paris\"}".to_string(),
+        }),
+        StreamEvent::Usage(Usage { input_tokens: 10, output_tokens: 1 }),
+        StreamEvent::Usage(Usage { input_tokens: 10, output_tokens: 25 }),
+        StreamEvent::Stop(FinishReason::ToolCalls),
+        StreamEvent::Done,
+    ];
+
+    let mut accumulator = StreamAccumulator::new();
+    for event in &events {
+        accumulator.push(event);
+    }
+    let response = accumulator.finish();
+
+    assert_eq!(response.id.as_deref(), Some("msg_1"));
+    assert_eq!(response.content(), "Let me check.");
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 10, output_tokens: 25 }));
+    assert_eq!(response.tool_calls().len(), 1);
+    assert_eq!(response.tool_calls()[0].id, "call_1");
+    assert_eq!(response.tool_calls()[0].name, "get_weather");
+    assert_eq!(response.tool_calls()[0].arguments["city"], "Paris");
+}
+
+#[tokio::test]
+async fn test_guard_reports_truncated_stream() {
+    let events = vec![
+        Ok(StreamEvent::TextDelta("Once upon".to_string())),
+        Ok(StreamEvent::TextDelta(" a time".to_string())),
+    ];
+    let results: Vec<Result<StreamEvent, Error>> = stream::guard(futures::stream::iter(events)).collect().await;
+
+    assert_eq!(results.len(), 3);
+    assert!(matches!(results[2], Err(Error::StreamTruncated)));
+}
+
+#[tokio::test]
+async fn test_guard_ends_stream_after_error() {
+    let events = vec![
+        Ok(StreamEvent::TextDelta("Once upon".to_string())),
+        Err(Error::Overloaded(ApiError {
+            status: None,
+            error_type: Some("overloaded_error".to_string()),
+            message: "Overloaded".to_string(),
+            request_id: None,
+            retry_after: None,
+        })),
+        Ok(StreamEvent::TextDelta(" a time".to_string())),
+    ];
+    let results: Vec<Result<StreamEvent, Error>> = stream::guard(futures::stream::iter(events)).collect().await;
+
+    assert_eq!(results.len(), 2);
+    assert!(matches!(&results[1], Err(Error::Overloaded(_))));
+}
+
+#[tokio::test]
+async fn test_guard_passes_complete_stream_through() {
+    let events = vec![
+        Ok(StreamEvent::TextDelta("Done".to_string())),
+        Ok(StreamEvent::Stop(FinishReason::Stop)),
+        Ok(StreamEvent::Done),
+    ];
+    let response = stream::collect(stream::guard(futures::stream::iter(events))).await.unwrap();
+
+    assert_eq!(response.content(), "Done");
+}
+
+#[tokio::test]
+async fn test_collect_fails_on_truncated_stream() {
+    let events = vec![Ok(StreamEvent::TextDelta("Once upon".to_string()))];
+    let result = stream::collect(stream::guard(futures::stream::iter(events))).await;
+
+    assert!(matches!(result, Err(Error::StreamTruncated)));
+}
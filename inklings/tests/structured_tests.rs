@@ -0,0 +1,198 @@
+use inklings::{
+    Client,
+    provider::{AnthropicProvider, MockProvider, OpenAICompatibleProvider, OpenAICompatibleQuirks, OpenAIProvider},
+    structured::response_format,
+    testing::{TestResponse, TestServer},
+    types::{Error, Message, Role},
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, PartialEq, Deserialize, JsonSchema)]
+struct Person {
+    name: String,
+    age: u32,
+    email: Option<String>,
+    address: Address,
+}
+
+#[derive(Debug, PartialEq, Deserialize, JsonSchema)]
+struct Address {
+    city: String,
+}
+
+fn alice() -> Person {
+    Person {
+        name: "Alice".to_string(),
+        age: 30,
+        email: None,
+        address: Address { city: "Paris".to_string() },
+    }
+}
+
+fn openai_completion(content: &str) -> TestResponse {
+    TestResponse::json(200, json!({
+        "id": "chatcmpl-123",
+        "model": "gpt-4o-mini",
+        "choices": [{ "index": 0, "message": { "role": "assistant", "content": content }, "finish_reason": "stop" }],
+        "usage": { "prompt_tokens": 12, "completion_tokens": 3 }
+    }))
+}
+
+fn openai_client(server: &TestServer) -> Client {
+    let provider = OpenAIProvider::builder("test_key").base_url(server.url()).build().unwrap();
+    Client::new(Box::new(provider))
+}
+
+#[test]
+fn test_schema_is_strict() {
+    let format = response_format::<Person>();
+
+    assert_eq!(format.name, "Person");
+    assert_eq!(format.schema, json!({
+        "type": "object",
+        "properties": {
+            "name": { "type": "string" },
+            "age": { "type": "integer", "minimum": 0.0 },
+            "email": { "type": ["string", "null"] },
+            "address": {
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"],
+                "additionalProperties": false
+            }
+        },
+        "required": ["address", "age", "email", "name"],
+        "additionalProperties": false
+    }));
+}
+
+#[test]
+fn test_non_object_schema_is_wrapped() {
+    let format = response_format::<Vec<String>>();
+
+    assert_eq!(format.name, "Array_of_String");
+    assert_eq!(format.schema, json!({
+        "type": "object",
+        "properties": { "value": { "type": "array", "items": { "type": "string" } } },
+        "required": ["value"],
+        "additionalProperties": false
+    }));
+}
+
+#[tokio::test]
+async fn test_openai_structured_output() {
+    let server = TestServer::start().await;
+    server.enqueue(openai_completion(r#"{"name":"Alice","age":30,"email":null,"address":{"city":"Paris"}}"#));
+    let client = openai_client(&server);
+
+    let person: Person = client.chat_structured(vec![Message::new(Role::User, "Alice, 30, lives in Paris")]).await.unwrap();
+
+    assert_eq!(person, alice());
+    let body = server.requests()[0].json();
+    assert_eq!(body["response_format"]["type"], "json_schema");
+    assert_eq!(body["response_format"]["json_schema"]["name"], "Person");
+    assert_eq!(body["response_format"]["json_schema"]["strict"], true);
+    assert_eq!(body["response_format"]["json_schema"]["schema"], response_format::<Person>().schema);
+    assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_anthropic_structured_output_forces_tool() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::json(200, json!({
+        "id": "msg_123",
+        "type": "message",
+        "role": "assistant",
+        "model": "claude-3-5-haiku-20241022",
+        "content": [{
+            "type": "tool_use",
+            "id": "toolu_1",
+            "name": "Array_of_String",
+            "input": { "value": ["red", "green"] }
+        }],
+        "stop_reason": "tool_use",
+        "usage": { "input_tokens": 20, "output_tokens": 4 }
+    })));
+    let provider = AnthropicProvider::builder("test_key").base_url(server.url()).build().unwrap();
+    let client = Client::new(Box::new(provider));
+
+    let colours: Vec<String> = client.chat_structured(vec![Message::new(Role::User, "Two colours")]).await.unwrap();
+
+    assert_eq!(colours, vec!["red", "green"]);
+    let body = server.requests()[0].json();
+    assert_eq!(body["tool_choice"], json!({ "type": "tool", "name": "Array_of_String" }));
+    assert_eq!(body["tools"][0]["name"], "Array_of_String");
+    assert_eq!(body["tools"][0]["input_schema"], response_format::<Vec<String>>().schema);
+}
+
+#[tokio::test]
+async fn test_invalid_reply_is_reprompted() {
+    let server = TestServer::start().await;
+    server.enqueue(openai_completion(r#"{"name":"Alice","age":"thirty","email":null,"address":{"city":"Paris"}}"#));
+    server.enqueue(openai_completion(r#"{"name":"Alice","age":30,"email":null,"address":{"city":"Paris"}}"#));
+    let client = openai_client(&server).with_structured_retries(2);
+
+    let person: Person = client.chat_structured(vec![Message::new(Role::User, "Alice, 30, lives in Paris")]).await.unwrap();
+
+    assert_eq!(person, alice());
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    let messages = requests[1].json()["messages"].clone();
+    assert_eq!(messages[1], json!({
+        "role": "assistant",
+        "content": r#"{"name":"Alice","age":"thirty","email":null,"address":{"city":"Paris"}}"#
+    }));
+    let correction = messages[2]["content"].as_str().unwrap();
+    assert!(correction.contains("/age"), "{}", correction);
+    assert!(correction.contains("\"thirty\" is not of type \"integer\""), "{}", correction);
+}
+
+#[tokio::test]
+async fn test_gives_up_after_retries() {
+    let server = TestServer::start().await;
+    server.enqueue(openai_completion("Sure! Here is the person you asked for."));
+    server.enqueue(openai_completion(r#"{"name":"Alice"}"#));
+    let client = openai_client(&server).with_structured_retries(1);
+
+    let error = client.chat_structured::<Person>(vec![Message::new(Role::User, "Alice")]).await.unwrap_err();
+
+    let Error::StructuredOutput(details) = error else {
+        panic!("unexpected error: {:?}", error);
+    };
+    assert_eq!(details.attempts, 2);
+    assert_eq!(details.output, r#"{"name":"Alice"}"#);
+    assert!(details.errors.iter().any(|error| error.contains("\"age\" is a required property")), "{:?}", details.errors);
+    assert_eq!(server.requests().len(), 2);
+    assert!(server.requests()[1].json()["messages"][2]["content"].as_str().unwrap().contains("not valid JSON"));
+}
+
+#[tokio::test]
+async fn test_code_fences_are_stripped() {
+    let reply = "```json\n{\"city\": \"Paris\"}\n```";
+    let client = Client::new(Box::new(MockProvider::new(reply.to_string())));
+
+    let address: Address = client.chat_structured(vec![Message::new(Role::User, "Where?")]).await.unwrap();
+
+    assert_eq!(address, Address { city: "Paris".to_string() });
+}
+
+#[tokio::test]
+async fn test_schema_is_described_without_native_support() {
+    let server = TestServer::start().await;
+    server.enqueue(openai_completion(r#"{"city":"Paris"}"#));
+    let provider = OpenAICompatibleProvider::builder(server.url(), "llama3")
+        .quirks(OpenAICompatibleQuirks { response_format: false, ..Default::default() })
+        .build()
+        .unwrap();
+    let client = Client::new(Box::new(provider));
+
+    let address: Address = client.chat_structured(vec![Message::new(Role::User, "Where?")]).await.unwrap();
+
+    assert_eq!(address.city, "Paris");
+    let body = server.requests()[0].json();
+    assert!(body.get("response_format").is_none());
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert!(body["messages"][0]["content"].as_str().unwrap().contains("\"additionalProperties\":false"));
+}
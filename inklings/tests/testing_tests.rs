@@ -0,0 +1,182 @@
+use inklings::{
+    Client,
+    provider::{AnthropicProvider, OpenAIProvider, Provider},
+    stream,
+    testing::{TestResponse, TestServer},
+    types::{ChatOptions, Error, FinishReason, Message, Role, ToolCall, Usage},
+};
+use serde_json::json;
+use std::time::Duration;
+
+fn weather_call() -> ToolCall {
+    ToolCall { id: "call_1".to_string(), name: "get_weather".to_string(), arguments: json!({ "city": "Paris" }) }
+}
+
+#[tokio::test]
+async fn test_openai_chat() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::text("Hello there").usage(Usage { input_tokens: 7, output_tokens: 2 }));
+    let client = Client::new(Box::new(server.openai()));
+
+    let response = client.chat(vec![Message::new(Role::User, "Hi")]).await.unwrap();
+
+    assert_eq!(response.content(), "Hello there");
+    assert_eq!(response.id.as_deref(), Some("chatcmpl-test1"));
+    assert_eq!(response.model, "gpt-4o-mini");
+    assert_eq!(response.finish_reason, FinishReason::Stop);
+    assert_eq!(response.usage, Some(Usage { input_tokens: 7, output_tokens: 2 }));
+    let request = server.last_request().unwrap();
+    assert_eq!(request.path, "/chat/completions");
+    assert_eq!(request.header("Authorization"), Some("Bearer test-key"));
+    assert_eq!(request.json()["messages"][0]["content"], "Hi");
+}
+
+#[tokio::test]
+async fn test_streams_survive_any_chunking() {
+    let server = TestServer::start().await;
+    let reply = || TestResponse::text("The quick brown fox").with_tool_call(weather_call());
+    for size in [1, 3, 17] {
+        server.enqueue(reply().chunk_size(size));
+        server.enqueue(reply().chunk_size(size));
+    }
+
+    for _ in 0..3 {
+        for provider in [Box::new(server.openai()) as Box<dyn Provider + Send + Sync>, Box::new(server.anthropic())] {
+            let client = Client::new(provider);
+            let stream = client.stream_chat(vec![Message::user("Weather?")]).await.unwrap();
+            let response = stream::collect(stream).await.unwrap();
+
+            assert_eq!(response.content(), "The quick brown fox");
+            assert_eq!(response.tool_calls(), &[weather_call()]);
+            assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+            assert_eq!(response.usage, Some(Usage { input_tokens: 10, output_tokens: 5 }));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_anthropic_tool_call() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::tool_call(weather_call()));
+    let provider = server.anthropic();
+
+    let response = provider.chat(vec![Message::user("Weather?")], &ChatOptions::default()).await.unwrap();
+
+    assert_eq!(response.id.as_deref(), Some("msg_test1"));
+    assert_eq!(response.tool_calls(), &[weather_call()]);
+    assert_eq!(response.finish_reason, FinishReason::ToolCalls);
+    let request = server.last_request().unwrap();
+    assert_eq!(request.path, "/messages");
+    assert_eq!(request.header("x-api-key"), Some("test-key"));
+}
+
+#[tokio::test]
+async fn test_errors_use_each_providers_format() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::error(429, "Slow down").header("retry-after", "2"));
+    server.enqueue(TestResponse::error(529, "Overloaded"));
+    server.enqueue(TestResponse::error(400, "Too long").error_type("context_length_exceeded"));
+
+    let error = server.openai().chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap_err();
+    let Error::RateLimited(details) = error else {
+        panic!("unexpected error: {:?}", error);
+    };
+    assert_eq!(details.message, "Slow down");
+    assert_eq!(details.retry_after, Some(Duration::from_secs(2)));
+    assert_eq!(details.request_id.as_deref(), Some("req_test1"));
+
+    let error = server.anthropic().chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap_err();
+    assert!(matches!(error, Error::Overloaded(details) if details.error_type.as_deref() == Some("overloaded_error")));
+
+    let error = server.openai().chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap_err();
+    assert!(matches!(error, Error::ContextLengthExceeded(_)));
+}
+
+#[tokio::test]
+async fn test_cut_off_stream_is_truncated() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::text("one two three").cut_after(3));
+    let client = Client::new(Box::new(server.openai()));
+
+    let stream = client.stream_chat(vec![Message::user("Count")]).await.unwrap();
+    let error = stream::collect(stream).await.unwrap_err();
+
+    assert!(matches!(error, Error::StreamTruncated));
+}
+
+#[tokio::test]
+async fn test_stalled_and_slow_responses_time_out() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::text("one two three").stall_after(2));
+    server.enqueue(TestResponse::text("late").delay(Duration::from_secs(5)));
+    let provider = OpenAIProvider::builder("test-key")
+        .base_url(server.url())
+        .timeout(Duration::from_millis(200))
+        .build()
+        .unwrap();
+
+    let stream = provider.stream_chat(vec![Message::user("Count")], &ChatOptions::default()).await.unwrap();
+    let error = stream::collect(stream).await.unwrap_err();
+    assert!(error.to_string().contains("timed out"), "{:?}", error);
+
+    let error = provider.chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap_err();
+    assert!(matches!(&error, Error::RequestError(e) if e.is_timeout()), "{:?}", error);
+}
+
+#[tokio::test]
+async fn test_chunk_delay() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::text("a b c").chunk_delay(Duration::from_millis(20)));
+    let provider = AnthropicProvider::builder("test-key").base_url(server.url()).build().unwrap();
+
+    let started = std::time::Instant::now();
+    let stream = provider.stream_chat(vec![Message::user("Hi")], &ChatOptions::default()).await.unwrap();
+    let response = stream::collect(stream).await.unwrap();
+
+    assert_eq!(response.content(), "a b c");
+    // message_start, block start, three deltas, block stop, message_delta and message_stop.
+    assert!(started.elapsed() >= Duration::from_millis(8 * 20));
+}
+
+#[tokio::test]
+async fn test_raw_responses_and_fallback() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::json(200, json!({
+        "id": "chatcmpl-raw",
+        "model": "gpt-4o",
+        "choices": [{ "index": 0, "message": { "role": "assistant", "content": "Raw" }, "finish_reason": "length" }]
+    })));
+    let client = Client::new(Box::new(server.openai()));
+
+    let response = client.chat(vec![Message::user("Hi")]).await.unwrap();
+    assert_eq!(response.content(), "Raw");
+    assert!(response.is_truncated());
+
+    let error = client.chat(vec![Message::user("Hi")]).await.unwrap_err();
+    assert!(error.to_string().contains("no response queued"), "{}", error);
+
+    server.fallback(TestResponse::text("Always"));
+    assert_eq!(client.chat_text(vec![Message::user("Hi")]).await.unwrap(), "Always");
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn test_raw_responses_on_any_path() {
+    let server = TestServer::start().await;
+    server.enqueue(TestResponse::ndjson(&[json!({ "n": 1 }), json!({ "n": 2 })]).chunk_size(5));
+    server.enqueue(TestResponse::raw(503, "text/plain", vec![b"down ".to_vec(), b"for now".to_vec()]));
+    server.enqueue(TestResponse::text("Not here"));
+    let http = reqwest::Client::new();
+
+    let response = http.post(format!("{}/api/chat", server.url())).send().await.unwrap();
+    assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+    assert_eq!(response.text().await.unwrap(), "{\"n\":1}\n{\"n\":2}\n");
+
+    let response = http.get(format!("{}/health", server.url())).send().await.unwrap();
+    assert_eq!(response.status(), 503);
+    assert_eq!(response.text().await.unwrap(), "down for now");
+
+    let response = http.post(format!("{}/chat", server.url())).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(server.requests()[1].path, "/health");
+}